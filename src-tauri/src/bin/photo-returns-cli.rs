@@ -0,0 +1,482 @@
+//! PhotoReturns のコマンドライン版（GUIなしでスキャン・処理・取り消しを実行）
+//!
+//! 例:
+//!   photo-returns-cli scan /mnt/photos/inbox --format table
+//!   photo-returns-cli process /mnt/photos/inbox /mnt/photos/library --transfer-mode move --duplicate-policy skip
+//!   photo-returns-cli undo /mnt/photos/library/.photo-returns/journal_20250115_103000_000.jsonl
+use anyhow::Result;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use photo_returns_lib::burst::{BurstDetectorConfig, BurstPolicy};
+use photo_returns_lib::duplicate::DuplicatePolicy;
+use photo_returns_lib::journal::{self, UndoResult};
+use photo_returns_lib::photo_core::{self, MediaInfo, ProcessOptions, ProcessResult, RotationStrategy, TransferMode};
+use photo_returns_lib::progress::{CancellationToken, ProgressCallback, ProgressEvent, RunControl};
+use photo_returns_lib::template;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+
+#[derive(Debug, Parser)]
+#[command(name = "photo-returns-cli", version, about = "PhotoReturns: Take back your memories")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+
+    /// 出力形式
+    #[arg(long, value_enum, global = true, default_value_t = OutputFormat::Table)]
+    format: OutputFormat,
+
+    /// 進捗を標準エラー出力に表示
+    #[arg(long, global = true)]
+    progress: bool,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// メディアファイルをスキャンして日付・新しいファイル名を表示
+    Scan {
+        /// 入力ディレクトリ
+        input_dir: PathBuf,
+        #[command(flatten)]
+        scan: ScanArgs,
+    },
+    /// メディアファイルをリネームして出力ディレクトリに整理
+    Process {
+        /// 入力ディレクトリ
+        input_dir: PathBuf,
+        /// 出力ディレクトリ
+        output_dir: PathBuf,
+        #[command(flatten)]
+        scan: ScanArgs,
+        #[command(flatten)]
+        process: ProcessArgs,
+    },
+    /// ジャーナルに記録された処理を取り消す
+    Undo {
+        /// ジャーナルファイル（<output>/.photo-returns/journal_*.jsonl）
+        journal_path: PathBuf,
+    },
+}
+
+/// スキャンに関するオプション
+#[derive(Debug, Args)]
+struct ScanArgs {
+    /// 動画ファイルを対象外にする
+    #[arg(long)]
+    no_videos: bool,
+    /// 並列処理を無効化
+    #[arg(long)]
+    sequential: bool,
+    /// 知覚ハッシュで類似写真を検出
+    #[arg(long)]
+    detect_similar: bool,
+    /// EXIF Orientationが無い写真の向きを画像の内容から推定
+    #[arg(long)]
+    detect_orientation: bool,
+    /// 撮影地が分からない日時に使うIANAタイムゾーン（例："Asia/Tokyo"）
+    #[arg(long)]
+    timezone: Option<String>,
+    /// 撮影地が分からない日時に使うタイムゾーンオフセット（秒、--timezoneがない場合）
+    #[arg(long, allow_hyphen_values = true)]
+    timezone_offset: Option<i32>,
+    /// ファイル名テンプレート（例："{YYYY}{MM}{DD}_{HH}{mm}{ss}[_{burst_index}].{ext}"）
+    #[arg(long, default_value = template::DEFAULT_FILE_NAME_TEMPLATE)]
+    name_template: String,
+    /// バーストとみなす最大時間間隔（秒、0.5 のように秒未満も指定可能）
+    #[arg(long, default_value_t = 3.0)]
+    burst_interval: f64,
+    /// バーストとみなす最小枚数
+    #[arg(long, default_value_t = 3)]
+    burst_min_count: usize,
+    /// カメラが記録した連写の情報を使わず、撮影時刻の間隔だけでバーストを判定
+    #[arg(long)]
+    burst_time_only: bool,
+    /// バースト内で残す候補の写真を選ばない
+    #[arg(long)]
+    no_burst_best: bool,
+}
+
+/// 書き込みに関するオプション
+#[derive(Debug, Args)]
+struct ProcessArgs {
+    /// バックアップディレクトリ
+    #[arg(long)]
+    backup_dir: Option<PathBuf>,
+    /// 出力ディレクトリ以下の階層テンプレート（例："{camera_model}/{YYYY}/{YYYY}{MM}"）
+    #[arg(long, default_value = template::DEFAULT_DIRECTORY_TEMPLATE)]
+    dir_template: String,
+    /// EXIF Orientationに従って画素を回転する（JPEGは再エンコードされる）
+    #[arg(long)]
+    auto_orient: bool,
+    /// 回転・反転の適用方法
+    #[arg(long, value_enum, default_value_t = RotationArg::Pixels)]
+    rotation_strategy: RotationArg,
+    /// ディスクに書き込まず操作計画のみ表示
+    #[arg(long)]
+    dry_run: bool,
+    /// 出力先へのファイル転送方法
+    #[arg(long, value_enum, default_value_t = TransferArg::Copy)]
+    transfer_mode: TransferArg,
+    /// 内容が同一のファイルの扱い
+    #[arg(long, value_enum, default_value_t = DuplicateArg::KeepBoth)]
+    duplicate_policy: DuplicateArg,
+    /// バーストグループの扱い
+    #[arg(long, value_enum, default_value_t = BurstArg::Suffix)]
+    burst_policy: BurstArg,
+    /// モーションフォトに埋め込まれた動画を写真と同じ名前の .mp4 として書き出す
+    #[arg(long)]
+    extract_motion_video: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Json,
+    Table,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum TransferArg {
+    Copy,
+    Move,
+    Hardlink,
+}
+
+impl From<TransferArg> for TransferMode {
+    fn from(arg: TransferArg) -> Self {
+        match arg {
+            TransferArg::Copy => TransferMode::Copy,
+            TransferArg::Move => TransferMode::Move,
+            TransferArg::Hardlink => TransferMode::HardLink,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum DuplicateArg {
+    Skip,
+    Link,
+    KeepBoth,
+}
+
+impl From<DuplicateArg> for DuplicatePolicy {
+    fn from(arg: DuplicateArg) -> Self {
+        match arg {
+            DuplicateArg::Skip => DuplicatePolicy::Skip,
+            DuplicateArg::Link => DuplicatePolicy::Link,
+            DuplicateArg::KeepBoth => DuplicatePolicy::KeepBoth,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum RotationArg {
+    Pixels,
+    Metadata,
+}
+
+impl From<RotationArg> for RotationStrategy {
+    fn from(arg: RotationArg) -> Self {
+        match arg {
+            RotationArg::Pixels => RotationStrategy::Pixels,
+            RotationArg::Metadata => RotationStrategy::Metadata,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum BurstArg {
+    Suffix,
+    Subfolder,
+    KeepBest,
+    KeepFirst,
+    Ignore,
+}
+
+impl From<BurstArg> for BurstPolicy {
+    fn from(arg: BurstArg) -> Self {
+        match arg {
+            BurstArg::Suffix => BurstPolicy::Suffix,
+            BurstArg::Subfolder => BurstPolicy::Subfolder,
+            BurstArg::KeepBest => BurstPolicy::KeepBest,
+            BurstArg::KeepFirst => BurstPolicy::KeepFirst,
+            BurstArg::Ignore => BurstPolicy::Ignore,
+        }
+    }
+}
+
+/// コマンドライン引数から処理オプションを組み立て
+fn build_options(scan: &ScanArgs, process: Option<&ProcessArgs>) -> ProcessOptions {
+    let mut options = ProcessOptions {
+        parallel: !scan.sequential,
+        include_videos: !scan.no_videos,
+        detect_similar: scan.detect_similar,
+        detect_orientation: scan.detect_orientation,
+        timezone: scan.timezone.clone(),
+        timezone_offset: scan.timezone_offset,
+        file_name_template: scan.name_template.clone(),
+        burst: BurstDetectorConfig {
+            max_interval_seconds: scan.burst_interval,
+            min_count: scan.burst_min_count,
+            use_metadata: !scan.burst_time_only,
+            select_best: !scan.no_burst_best,
+        },
+        ..Default::default()
+    };
+
+    if let Some(process) = process {
+        options.backup_dir = process.backup_dir.clone();
+        options.directory_template = process.dir_template.clone();
+        options.auto_correct_orientation = process.auto_orient;
+        options.rotation_strategy = process.rotation_strategy.into();
+        options.dry_run = process.dry_run;
+        options.transfer_mode = process.transfer_mode.into();
+        options.duplicate_policy = process.duplicate_policy.into();
+        options.burst_policy = process.burst_policy.into();
+        options.extract_motion_photo_video = process.extract_motion_video;
+    }
+
+    options
+}
+
+/// 進捗表示の有無に応じた制御を作成
+fn run_control(show_progress: bool) -> RunControl {
+    let callback: Option<ProgressCallback> = show_progress.then(|| {
+        Arc::new(|event: &ProgressEvent| {
+            eprintln!(
+                "[{:?}] {}/{} {}",
+                event.phase,
+                event.index,
+                event.total,
+                event.current_path.display()
+            );
+        }) as ProgressCallback
+    });
+    RunControl::new(CancellationToken::new(), callback)
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    match run(&cli) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// サブコマンドを実行し、エラーなく完了したかを返す
+fn run(cli: &Cli) -> Result<bool> {
+    let control = run_control(cli.progress);
+
+    match &cli.command {
+        Command::Scan { input_dir, scan } => {
+            let options = build_options(scan, None);
+            let media = photo_core::scan_media_with_control(input_dir, &options, &control)?;
+            match cli.format {
+                OutputFormat::Json => print_json(&media)?,
+                OutputFormat::Table => print_scan_table(&media),
+            }
+            Ok(true)
+        }
+        Command::Process {
+            input_dir,
+            output_dir,
+            scan,
+            process,
+        } => {
+            let options = build_options(scan, Some(process));
+            let result = photo_core::process_media_with_control(input_dir, output_dir, &options, &control)?;
+            match cli.format {
+                OutputFormat::Json => print_json(&result)?,
+                OutputFormat::Table => print_process_table(&result),
+            }
+            Ok(result.errors.is_empty())
+        }
+        Command::Undo { journal_path } => {
+            let result = journal::undo_run(journal_path)?;
+            match cli.format {
+                OutputFormat::Json => print_json(&result)?,
+                OutputFormat::Table => print_undo_table(&result),
+            }
+            Ok(result.errors.is_empty())
+        }
+    }
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// 列幅をそろえて表を出力
+fn print_table(headers: &[&str], rows: &[Vec<String>]) {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let format_row = |cells: Vec<String>| {
+        cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+
+    println!("{}", format_row(headers.iter().map(|h| h.to_string()).collect()));
+    println!("{}", format_row(widths.iter().map(|w| "-".repeat(*w)).collect()));
+    for row in rows {
+        println!("{}", format_row(row.clone()));
+    }
+}
+
+fn print_scan_table(media: &[MediaInfo]) {
+    let rows: Vec<Vec<String>> = media
+        .iter()
+        .map(|m| {
+            vec![
+                m.file_name.clone(),
+                format!("{:?}", m.media_type),
+                m.date_taken
+                    .map(|d| d.format("%Y-%m-%d %H:%M:%S").to_string())
+                    .unwrap_or_else(|| "-".to_string()),
+                format!("{:?}", m.date_source),
+                m.new_name.clone(),
+            ]
+        })
+        .collect();
+
+    print_table(&["FILE", "TYPE", "DATE", "SOURCE", "NEW NAME"], &rows);
+    println!("\n{} files", media.len());
+}
+
+fn print_process_table(result: &ProcessResult) {
+    if let Some(plan) = &result.plan {
+        let rows: Vec<Vec<String>> = plan
+            .operations
+            .iter()
+            .map(|op| {
+                vec![
+                    op.source.display().to_string(),
+                    op.target_path.display().to_string(),
+                    format!("{:?}", op.transfer_mode),
+                    op.rotation.map(|r| r.to_string()).unwrap_or_default(),
+                ]
+            })
+            .collect();
+        print_table(&["SOURCE", "TARGET", "MODE", "ROTATE"], &rows);
+        println!("\nDry run: {} operations, {} new directories", plan.operations.len(), plan.directories.len());
+    } else {
+        let rows: Vec<Vec<String>> = result
+            .media
+            .iter()
+            .filter(|m| !m.new_path.as_os_str().is_empty())
+            .map(|m| vec![m.original_path.display().to_string(), m.new_path.display().to_string()])
+            .collect();
+        print_table(&["SOURCE", "TARGET"], &rows);
+        println!(
+            "\nProcessed: {}  Skipped: {}  Duplicates: {}  Errors: {}  Total: {}{}",
+            result.processed_files,
+            result.skipped_files,
+            result.duplicate_files,
+            result.errors.len(),
+            result.total_files,
+            if result.cancelled { "  (cancelled)" } else { "" }
+        );
+        if let Some(journal_path) = &result.journal_path {
+            println!("Journal: {}", journal_path.display());
+        }
+    }
+
+    for frame in &result.skipped_burst_frames {
+        println!("Burst frame skipped: {} (kept {})", frame.path.display(), frame.kept.display());
+    }
+
+    for error in &result.errors {
+        eprintln!("Error: {}", error);
+    }
+}
+
+fn print_undo_table(result: &UndoResult) {
+    println!(
+        "Removed: {}  Restored: {}  Directories removed: {}  Errors: {}",
+        result.removed_files,
+        result.restored_files,
+        result.removed_directories,
+        result.errors.len()
+    );
+    for error in &result.errors {
+        eprintln!("Error: {}", error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_arguments_map_to_options() {
+        let cli = Cli::try_parse_from([
+            "photo-returns-cli",
+            "process",
+            "in",
+            "out",
+            "--sequential",
+            "--timezone-offset",
+            "-18000",
+            "--dry-run",
+            "--transfer-mode",
+            "hardlink",
+            "--duplicate-policy",
+            "skip",
+            "--burst-policy",
+            "keep-best",
+            "--dir-template",
+            "{camera_model}/{YYYY}",
+            "--rotation-strategy",
+            "metadata",
+            "--detect-similar",
+            "--auto-orient",
+            "--format",
+            "json",
+        ])
+        .unwrap();
+
+        assert_eq!(cli.format, OutputFormat::Json);
+        let Command::Process { scan, process, .. } = &cli.command else {
+            panic!("expected process command");
+        };
+        let options = build_options(scan, Some(process));
+
+        assert!(!options.parallel);
+        assert!(options.include_videos);
+        assert!(options.dry_run);
+        assert!(options.detect_similar);
+        assert!(options.auto_correct_orientation);
+        assert_eq!(options.rotation_strategy, RotationStrategy::Metadata);
+        assert_eq!(options.timezone_offset, Some(-18000));
+        assert_eq!(options.transfer_mode, TransferMode::HardLink);
+        assert_eq!(options.duplicate_policy, DuplicatePolicy::Skip);
+        assert_eq!(options.burst_policy, BurstPolicy::KeepBest);
+        assert_eq!(options.directory_template, "{camera_model}/{YYYY}");
+        assert_eq!(options.file_name_template, template::DEFAULT_FILE_NAME_TEMPLATE);
+
+        // 指定しなければファイルを書き換えない（向きの修正は明示的に有効化する）
+        let cli = Cli::try_parse_from(["photo-returns-cli", "process", "in", "out"]).unwrap();
+        let Command::Process { scan, process, .. } = &cli.command else {
+            panic!("expected process command");
+        };
+        let options = build_options(scan, Some(process));
+        assert!(!options.auto_correct_orientation);
+        assert_eq!(options.transfer_mode, TransferMode::Copy);
+    }
+}
@@ -1,11 +1,44 @@
-/// 連続撮影写真（バースト）のグループ化機能
-use chrono::{DateTime, Duration, Local};
+/// 連続撮影写真（バースト）のグループ化と、グループ内で残す写真の選択
+use anyhow::{bail, Result};
+use chrono::{DateTime, Duration, FixedOffset};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+use crate::similarity;
+
+/// 写真の評価に使う縮小サイズ（長辺のピクセル数）
+const SCORE_SIZE: u32 = 512;
 
 /// バーストグループID
 pub type BurstGroupId = usize;
 
+/// バーストを見つけた方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum BurstMethod {
+    /// カメラが記録したバーストの識別子・連写の通し番号
+    Metadata,
+    /// 撮影時刻の間隔
+    #[default]
+    TimeGap,
+}
+
+/// 処理時のバーストグループの扱い
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BurstPolicy {
+    /// ファイル名に連番（`_NN`）を付けて通常の出力先に置く
+    #[default]
+    Suffix,
+    /// グループごとに `burst_<開始時刻>` サブフォルダにまとめる
+    Subfolder,
+    /// 残す候補の写真だけを出力し、残りは結果に一覧する（候補が無い場合は先頭の写真）
+    KeepBest,
+    /// 先頭の写真だけを出力し、残りは結果に一覧する
+    KeepFirst,
+    /// グループ化を無視して通常の写真として扱う
+    Ignore,
+}
+
 /// バースト写真の情報
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BurstGroup {
@@ -14,109 +47,271 @@ pub struct BurstGroup {
     /// グループ内の写真のインデックス（元のリスト内）
     pub photo_indices: Vec<usize>,
     /// グループの開始時刻
-    pub start_time: DateTime<Local>,
+    pub start_time: DateTime<FixedOffset>,
     /// グループの終了時刻
-    pub end_time: DateTime<Local>,
+    pub end_time: DateTime<FixedOffset>,
     /// グループ内の写真枚数
     pub count: usize,
+    /// グループを見つけた方法
+    #[serde(default)]
+    pub method: BurstMethod,
+    /// 残す候補の写真（元のリスト内のインデックス、評価できなかった場合はNone）
+    #[serde(default)]
+    pub best_index: Option<usize>,
 }
 
 /// バースト検出の設定
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct BurstDetectorConfig {
-    /// バーストとみなす最大時間間隔（秒）
-    pub max_interval_seconds: i64,
+    /// バーストとみなす最大時間間隔（秒、0.5 のように秒未満も指定可能）
+    pub max_interval_seconds: f64,
     /// バーストとみなす最小枚数
     pub min_count: usize,
+    /// カメラが記録したバーストの識別子・連写の通し番号を先に使う（該当しない写真は時刻の間隔で判定）
+    pub use_metadata: bool,
+    /// グループ内で残す候補の写真をシャープさと露出から選ぶ
+    pub select_best: bool,
 }
 
 impl Default for BurstDetectorConfig {
     fn default() -> Self {
         Self {
-            max_interval_seconds: 3, // 3秒以内
-            min_count: 3,             // 3枚以上
+            max_interval_seconds: 3.0, // 3秒以内
+            min_count: 3,               // 3枚以上
+            use_metadata: true,
+            select_best: true,
+        }
+    }
+}
+
+impl BurstDetectorConfig {
+    /// 設定値を検証（間隔は正の有限値、最小枚数は2枚以上）
+    pub fn validate(&self) -> Result<()> {
+        if !self.max_interval_seconds.is_finite() || self.max_interval_seconds <= 0.0 {
+            bail!("Burst interval must be a positive number of seconds: {}", self.max_interval_seconds);
+        }
+        if self.min_count < 2 {
+            bail!("Burst minimum count must be at least 2: {}", self.min_count);
         }
+        Ok(())
+    }
+}
+
+/// バースト検出の対象となる1ファイル分の情報
+#[derive(Debug, Clone, Default)]
+pub struct BurstCandidate {
+    /// 撮影日時（日時がないものは対象外）
+    pub date: Option<DateTime<FixedOffset>>,
+    /// 秒未満の撮影時刻（ミリ秒）
+    pub subsec_millis: Option<u32>,
+    /// 撮影した機器（機器が分からない場合は元のフォルダ）
+    ///
+    /// 別の機器で同時刻に撮影した写真を同じバーストにしないよう、機器ごとに検出します。
+    pub source: String,
+    /// カメラが記録したバーストの識別子（AppleのBurstUUID、ファイル名のBURSTなど）
+    pub burst_id: Option<String>,
+    /// 連写中の通し番号（Canon・Sony）
+    pub sequence_number: Option<u32>,
+}
+
+impl BurstCandidate {
+    /// 秒未満を含めた撮影時刻
+    fn precise_time(&self) -> Option<DateTime<FixedOffset>> {
+        self.date
+            .map(|date| date + Duration::milliseconds(i64::from(self.subsec_millis.unwrap_or(0))))
     }
 }
 
 /// 写真の撮影時刻に基づいてバーストグループを検出
 ///
 /// # Arguments
-/// * `dates` - 各写真の撮影日時のリスト
+/// * `dates` - 各写真の撮影日時のリスト（順不同）
 /// * `config` - バースト検出の設定
 ///
 /// # Returns
 /// 検出されたバーストグループのリスト
 pub fn detect_burst_groups(
-    dates: &[Option<DateTime<Local>>],
+    dates: &[Option<DateTime<FixedOffset>>],
     config: &BurstDetectorConfig,
 ) -> Vec<BurstGroup> {
-    let mut groups = Vec::new();
-    let mut current_group: Option<Vec<usize>> = None;
-    let mut last_time: Option<DateTime<Local>> = None;
-
-    for (i, date_opt) in dates.iter().enumerate() {
-        if let Some(date) = date_opt {
-            match (current_group.as_mut(), last_time) {
-                (Some(group), Some(last)) => {
-                    // 前の写真との時間差を計算
-                    let diff = *date - last;
-
-                    if diff <= Duration::seconds(config.max_interval_seconds)
-                        && diff >= Duration::seconds(0)
-                    {
-                        // 同じグループに追加
-                        group.push(i);
-                        last_time = Some(*date);
-                    } else {
-                        // 現在のグループを確定
-                        if group.len() >= config.min_count {
-                            let start_time = dates[group[0]].unwrap();
-                            let end_time = dates[*group.last().unwrap()].unwrap();
-
-                            groups.push(BurstGroup {
-                                id: groups.len(),
-                                photo_indices: group.clone(),
-                                start_time,
-                                end_time,
-                                count: group.len(),
-                            });
-                        }
-
-                        // 新しいグループを開始
-                        current_group = Some(vec![i]);
-                        last_time = Some(*date);
-                    }
-                }
-                _ => {
-                    // 最初の写真、または最初の有効な日時
-                    current_group = Some(vec![i]);
-                    last_time = Some(*date);
-                }
+    let candidates: Vec<BurstCandidate> = dates
+        .iter()
+        .map(|&date| BurstCandidate {
+            date,
+            ..Default::default()
+        })
+        .collect();
+    detect_burst_groups_per_source(&candidates, config)
+}
+
+/// 機器（またはフォルダ）ごとに、撮影時刻順に並べてバーストグループを検出
+///
+/// `use_metadata` が有効な場合は、カメラが記録したバーストの識別子・連写の通し番号で先にグループ化し、
+/// 残りの写真を撮影時刻の間隔で判定します。
+/// 入力の順序によらず同じ結果になるよう、グループ内の写真は撮影時刻順（同時刻は元のインデックス順）、
+/// グループIDは開始時刻順に付けます。
+pub fn detect_burst_groups_per_source(
+    candidates: &[BurstCandidate],
+    config: &BurstDetectorConfig,
+) -> Vec<BurstGroup> {
+    // 機器ごとに (撮影時刻, インデックス) を時刻順に並べる
+    let mut by_source: BTreeMap<&str, Vec<TimedPhoto>> = BTreeMap::new();
+    for (i, candidate) in candidates.iter().enumerate() {
+        if let Some(time) = candidate.precise_time() {
+            by_source.entry(candidate.source.as_str()).or_default().push((time, i));
+        }
+    }
+
+    let mut found: Vec<(BurstMethod, Vec<TimedPhoto>)> = Vec::new();
+    for mut photos in by_source.into_values() {
+        photos.sort();
+        if config.use_metadata {
+            let groups = metadata_groups(candidates, &photos);
+            let grouped: HashSet<usize> = groups.iter().flatten().map(|&(_, i)| i).collect();
+            photos.retain(|(_, i)| !grouped.contains(i));
+            found.extend(groups.into_iter().map(|group| (BurstMethod::Metadata, group)));
+        }
+        found.extend(time_gap_groups(photos, config).into_iter().map(|group| (BurstMethod::TimeGap, group)));
+    }
+    found.sort_by_key(|(_, group)| group[0]);
+
+    found
+        .into_iter()
+        .enumerate()
+        .map(|(id, (method, group))| BurstGroup {
+            id,
+            start_time: group[0].0,
+            end_time: group[group.len() - 1].0,
+            count: group.len(),
+            photo_indices: group.into_iter().map(|(_, i)| i).collect(),
+            method,
+            best_index: None,
+        })
+        .collect()
+}
+
+/// (撮影時刻, 元のリスト内のインデックス)
+type TimedPhoto = (DateTime<FixedOffset>, usize);
+
+/// カメラが記録したバーストの識別子・連写の通し番号でグループ化（2枚以上のもの）
+///
+/// 通し番号は、撮影時刻順に並べて1つずつ増えている間を同じ連写とします。
+fn metadata_groups(candidates: &[BurstCandidate], photos: &[TimedPhoto]) -> Vec<Vec<TimedPhoto>> {
+    let mut by_id: BTreeMap<&str, Vec<TimedPhoto>> = BTreeMap::new();
+    let mut runs: Vec<Vec<TimedPhoto>> = Vec::new();
+    let mut last_sequence: Option<u32> = None;
+
+    for &photo in photos {
+        let candidate = &candidates[photo.1];
+        if let Some(id) = candidate.burst_id.as_deref() {
+            by_id.entry(id).or_default().push(photo);
+        } else if let Some(sequence) = candidate.sequence_number {
+            match runs.last_mut() {
+                Some(run) if last_sequence.is_some_and(|last| sequence == last + 1) => run.push(photo),
+                _ => runs.push(vec![photo]),
             }
+            last_sequence = Some(sequence);
         }
     }
 
-    // 最後のグループを確定
-    if let Some(group) = current_group {
-        if group.len() >= config.min_count {
-            let start_time = dates[group[0]].unwrap();
-            let end_time = dates[*group.last().unwrap()].unwrap();
-            let count = group.len();
-
-            groups.push(BurstGroup {
-                id: groups.len(),
-                photo_indices: group,
-                start_time,
-                end_time,
-                count,
-            });
+    by_id.into_values().chain(runs).filter(|group| group.len() >= 2).collect()
+}
+
+/// 撮影時刻順に並んだ写真を、前の写真との時間差が開いたところで区切る（`min_count` 枚以上のもの）
+fn time_gap_groups(photos: Vec<TimedPhoto>, config: &BurstDetectorConfig) -> Vec<Vec<TimedPhoto>> {
+    let max_interval = Duration::milliseconds((config.max_interval_seconds * 1000.0).round() as i64);
+    let mut groups: Vec<Vec<TimedPhoto>> = Vec::new();
+    let mut current: Vec<TimedPhoto> = Vec::new();
+
+    for photo in photos {
+        // 前の写真との時間差が開いたら現在のグループを確定
+        if current.last().is_some_and(|&(last, _)| photo.0 - last > max_interval) {
+            groups.push(std::mem::take(&mut current));
         }
+        current.push(photo);
     }
+    groups.push(current);
 
+    groups.retain(|group| !group.is_empty() && group.len() >= config.min_count);
     groups
 }
 
+/// ファイル名に記録されたバーストの識別子
+///
+/// - Google（Pixelなど）: `00001IMG_00001_BURST20181016181814893.jpg` の `BURST` に続く撮影時刻
+/// - Samsung: `20180520_174538_Burst01.jpg` の `_Burst` より前の部分
+pub fn filename_burst_id(file_name: &str) -> Option<String> {
+    let stem = Path::new(file_name).file_stem()?.to_str()?;
+    let upper = stem.to_ascii_uppercase();
+    let position = upper.rfind("BURST")?;
+    let after = &stem[position + "BURST".len()..];
+    let digits = after.bytes().take_while(u8::is_ascii_digit).count();
+
+    if digits >= 8 {
+        // Googleの形式（BURST + 撮影時刻）
+        Some(format!("BURST{}", &after[..digits]))
+    } else if digits > 0 && digits == after.len() && stem[..position].ends_with('_') {
+        // Samsungの形式（<撮影時刻>_Burst<連番>）
+        Some(stem[..position - 1].to_string())
+    } else {
+        None
+    }
+}
+
+/// 写真の評価値（大きいほど残す候補に向く）
+///
+/// シャープさ（ラプラシアンの分散）に、露出の良さ（平均の明るさが中間に近く、白飛び・黒つぶれが少ない）で
+/// 0.5〜1.0倍の重みを掛けます。手ぶれ・ピンぼけの写真はシャープさが大きく下がります。
+pub fn frame_score(path: &Path) -> Result<f64> {
+    let gray = similarity::load_scaled(path, SCORE_SIZE as u16)?
+        .thumbnail(SCORE_SIZE, SCORE_SIZE)
+        .to_luma8();
+    let (width, height) = gray.dimensions();
+    if width < 3 || height < 3 {
+        bail!("Image too small to score: {}x{}", width, height);
+    }
+    let pixel = |x: u32, y: u32| f64::from(gray.get_pixel(x, y)[0]);
+
+    // 4近傍のラプラシアンの分散
+    let (mut sum, mut sum_sq) = (0.0, 0.0);
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            let laplacian =
+                4.0 * pixel(x, y) - pixel(x - 1, y) - pixel(x + 1, y) - pixel(x, y - 1) - pixel(x, y + 1);
+            sum += laplacian;
+            sum_sq += laplacian * laplacian;
+        }
+    }
+    let count = f64::from((width - 2) * (height - 2));
+    let sharpness = sum_sq / count - (sum / count).powi(2);
+
+    // 平均の明るさと、白飛び・黒つぶれした画素の割合
+    let pixels = gray.as_raw();
+    let mean = pixels.iter().map(|&p| f64::from(p)).sum::<f64>() / pixels.len() as f64 / 255.0;
+    let clipped = pixels.iter().filter(|&&p| p <= 5 || p >= 250).count() as f64 / pixels.len() as f64;
+    let exposure = (1.0 - (mean - 0.5).abs() * 2.0) * (1.0 - clipped);
+
+    Ok(sharpness * (0.5 + 0.5 * exposure))
+}
+
+/// 各グループで評価値が最も高い写真を残す候補にする（同じ評価値は撮影時刻が早いもの）
+///
+/// `score` は元のリスト内のインデックスから評価値を返します（評価できない写真はNone）。
+pub fn mark_best_frames(groups: &mut [BurstGroup], score: impl Fn(usize) -> Option<f64>) {
+    for group in groups {
+        group.best_index = group
+            .photo_indices
+            .iter()
+            .filter_map(|&i| Some((i, score(i)?)))
+            .fold(None, |best: Option<(usize, f64)>, (i, s)| match best {
+                Some((_, best_score)) if best_score >= s => best,
+                _ => Some((i, s)),
+            })
+            .map(|(i, _)| i);
+    }
+}
+
 /// 各写真がどのバーストグループに属するかのマップを作成
 ///
 /// # Arguments
@@ -143,17 +338,17 @@ mod tests {
 
     #[test]
     fn test_detect_burst_groups() {
-        let base_time = Utc::now().with_timezone(&Local);
+        let base_time = Utc::now().fixed_offset();
 
         let dates = vec![
             Some(base_time),                                      // 0
             Some(base_time + Duration::seconds(1)),               // 1 - グループ1
             Some(base_time + Duration::seconds(2)),               // 2 - グループ1
             Some(base_time + Duration::seconds(3)),               // 3 - グループ1
-            Some(base_time + Duration::seconds(10)),              // 4 - 間隔が空く
-            Some(base_time + Duration::seconds(11)),              // 5
+            Some(base_time + Duration::seconds(10)),              // 4 - 間隔が空く、グループ2
+            Some(base_time + Duration::seconds(11)),              // 5 - グループ2
             Some(base_time + Duration::seconds(12)),              // 6 - グループ2
-            Some(base_time + Duration::seconds(13)),              // 7 - グループ2
+            Some(base_time + Duration::seconds(20)),              // 7 - 間隔が空く
         ];
 
         let config = BurstDetectorConfig::default();
@@ -164,9 +359,142 @@ mod tests {
         assert_eq!(groups[1].count, 3); // 2つ目は3枚
     }
 
+    #[test]
+    fn test_unsorted_input_and_sources() {
+        let base_time = Utc::now().fixed_offset();
+        let at = |seconds: i64, millis: u32, source: &str| BurstCandidate {
+            date: Some(base_time + Duration::seconds(seconds)),
+            subsec_millis: Some(millis),
+            source: source.to_string(),
+            ..Default::default()
+        };
+
+        // 並列スキャンで順不同になった入力、同時刻に別の機器で撮影した写真を含む
+        let candidates = vec![
+            at(2, 0, "Canon EOS R5"),
+            at(0, 500, "Canon EOS R5"),
+            at(1, 0, "iPhone 15"),
+            at(0, 0, "Canon EOS R5"),
+            at(1, 0, "Canon EOS R5"),
+            at(2, 0, "iPhone 15"),
+        ];
+        let groups = detect_burst_groups_per_source(&candidates, &BurstDetectorConfig::default());
+
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].id, 0);
+        assert_eq!(groups[0].photo_indices, vec![3, 1, 4, 0]); // 秒未満も含めた撮影時刻順
+        assert_eq!(groups[0].start_time, base_time);
+    }
+
+    #[test]
+    fn test_metadata_groups() {
+        let base_time = Utc::now().fixed_offset();
+        let at = |seconds: i64, burst_id: Option<&str>, sequence_number: Option<u32>| BurstCandidate {
+            date: Some(base_time + Duration::seconds(seconds)),
+            burst_id: burst_id.map(str::to_string),
+            sequence_number,
+            ..Default::default()
+        };
+
+        let candidates = vec![
+            // 10秒おきでも同じBurstUUIDなら1つのバースト
+            at(0, Some("A"), None),
+            at(10, Some("A"), None),
+            // 通し番号が1から振り直されたら別の連写
+            at(100, None, Some(1)),
+            at(101, None, Some(2)),
+            at(102, None, Some(1)),
+            at(103, None, Some(2)),
+            // メタデータのない写真は時刻の間隔で判定
+            at(200, None, None),
+            at(201, None, None),
+            at(202, None, None),
+        ];
+        let groups = detect_burst_groups_per_source(&candidates, &BurstDetectorConfig::default());
+
+        let summary: Vec<(Vec<usize>, BurstMethod)> =
+            groups.iter().map(|g| (g.photo_indices.clone(), g.method)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (vec![0, 1], BurstMethod::Metadata),
+                (vec![2, 3], BurstMethod::Metadata),
+                (vec![4, 5], BurstMethod::Metadata),
+                (vec![6, 7, 8], BurstMethod::TimeGap),
+            ]
+        );
+
+        // メタデータを使わない場合は時刻の間隔だけで判定
+        let config = BurstDetectorConfig {
+            use_metadata: false,
+            ..Default::default()
+        };
+        let groups = detect_burst_groups_per_source(&candidates, &config);
+        assert_eq!(groups.len(), 2);
+        assert!(groups.iter().all(|g| g.method == BurstMethod::TimeGap));
+    }
+
+    #[test]
+    fn test_sub_second_interval() {
+        let base_time = Utc::now().fixed_offset();
+        let candidates: Vec<BurstCandidate> = [0, 300, 600, 1500, 1800, 2100]
+            .iter()
+            .map(|&millis| BurstCandidate {
+                date: Some(base_time + Duration::seconds(millis / 1000)),
+                subsec_millis: Some((millis % 1000) as u32),
+                ..Default::default()
+            })
+            .collect();
+        let config = BurstDetectorConfig {
+            max_interval_seconds: 0.5,
+            ..Default::default()
+        };
+
+        let groups = detect_burst_groups_per_source(&candidates, &config);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].photo_indices, vec![0, 1, 2]);
+        assert_eq!(groups[1].photo_indices, vec![3, 4, 5]);
+    }
+
+    #[test]
+    fn test_frame_score_prefers_sharp_frame() {
+        let dir = std::env::temp_dir().join(format!("photo_returns_burst_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // 細かい市松模様と、同じ明るさの一様な画像（ぶれて細部が失われた写真の代わり）
+        let sharp = image::GrayImage::from_fn(64, 64, |x, y| image::Luma([if (x + y) % 2 == 0 { 64 } else { 192 }]));
+        let blurred = image::GrayImage::from_pixel(64, 64, image::Luma([128]));
+        let (sharp_path, blurred_path) = (dir.join("sharp.png"), dir.join("blurred.png"));
+        sharp.save(&sharp_path).unwrap();
+        blurred.save(&blurred_path).unwrap();
+
+        let scores = [frame_score(&blurred_path).unwrap(), frame_score(&sharp_path).unwrap()];
+        assert!(scores[1] > scores[0]);
+
+        let mut groups = detect_burst_groups(&[Some(Utc::now().fixed_offset()); 3], &BurstDetectorConfig::default());
+        mark_best_frames(&mut groups, |i| scores.get(i).copied());
+        assert_eq!(groups[0].best_index, Some(1));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_filename_burst_id() {
+        assert_eq!(
+            filename_burst_id("00001IMG_00001_BURST20181016181814893.jpg").as_deref(),
+            Some("BURST20181016181814893")
+        );
+        assert_eq!(
+            filename_burst_id("00000IMG_00000_BURST20181016181814893_COVER.jpg").as_deref(),
+            Some("BURST20181016181814893")
+        );
+        assert_eq!(filename_burst_id("20180520_174538_Burst01.jpg").as_deref(), Some("20180520_174538"));
+        assert_eq!(filename_burst_id("IMG_1234.JPG"), None);
+        assert_eq!(filename_burst_id("burst.jpg"), None);
+    }
+
     #[test]
     fn test_create_photo_to_group_map() {
-        let base_time = Utc::now().with_timezone(&Local);
+        let base_time = Utc::now().fixed_offset();
 
         let groups = vec![
             BurstGroup {
@@ -175,6 +503,8 @@ mod tests {
                 start_time: base_time,
                 end_time: base_time + Duration::seconds(2),
                 count: 3,
+                method: BurstMethod::TimeGap,
+                best_index: None,
             },
             BurstGroup {
                 id: 1,
@@ -182,6 +512,8 @@ mod tests {
                 start_time: base_time + Duration::seconds(10),
                 end_time: base_time + Duration::seconds(12),
                 count: 3,
+                method: BurstMethod::TimeGap,
+                best_index: None,
             },
         ];
 
@@ -196,7 +528,7 @@ mod tests {
 
     #[test]
     fn test_min_count_filter() {
-        let base_time = Utc::now().with_timezone(&Local);
+        let base_time = Utc::now().fixed_offset();
 
         let dates = vec![
             Some(base_time),
@@ -209,4 +541,23 @@ mod tests {
 
         assert_eq!(groups.len(), 0); // min_count=3なのでグループなし
     }
+
+    #[test]
+    fn test_validate_config() {
+        assert!(BurstDetectorConfig::default().validate().is_ok());
+
+        for interval in [0.0, -1e300, f64::NAN, f64::INFINITY] {
+            let config = BurstDetectorConfig {
+                max_interval_seconds: interval,
+                ..Default::default()
+            };
+            assert!(config.validate().is_err(), "interval {} should be rejected", interval);
+        }
+
+        let config = BurstDetectorConfig {
+            min_count: 1,
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
 }
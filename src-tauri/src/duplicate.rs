@@ -0,0 +1,171 @@
+/// コンテンツハッシュによる完全一致の重複検出
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::journal::{hash_file, JOURNAL_DIR_NAME};
+use crate::photo_core::MediaInfo;
+use crate::progress::{ProgressPhase, RunControl};
+
+/// 重複ファイルの扱い
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DuplicatePolicy {
+    /// 重複ファイルは出力しない
+    Skip,
+    /// 既存のファイルへのハードリンクを作成
+    Link,
+    /// 両方残す（名前衝突時は連番を付与）
+    #[default]
+    KeepBoth,
+}
+
+/// 重複の状態
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DuplicateStatus {
+    /// 重複なし
+    #[default]
+    Unique,
+    /// 入力内の別ファイルと同一
+    InInput,
+    /// 出力ディレクトリの既存ファイルと同一
+    InOutput,
+}
+
+/// 出力ディレクトリ内の既存ファイルの索引
+///
+/// 全ファイルをハッシュすると重いため、サイズが一致したファイルだけを必要になった時点でハッシュします。
+pub struct OutputIndex {
+    by_size: HashMap<u64, Vec<PathBuf>>,
+    hashes: HashMap<PathBuf, Option<String>>,
+}
+
+impl OutputIndex {
+    /// 出力ディレクトリを走査して索引を作成（ジャーナルディレクトリは除外）
+    pub fn build(output_dir: &Path) -> Self {
+        let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+
+        if output_dir.is_dir() {
+            let files = WalkDir::new(output_dir)
+                .follow_links(false)
+                .into_iter()
+                .filter_entry(|e| e.file_name() != JOURNAL_DIR_NAME)
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_file());
+
+            for entry in files {
+                if let Ok(metadata) = entry.metadata() {
+                    by_size
+                        .entry(metadata.len())
+                        .or_default()
+                        .push(entry.into_path());
+                }
+            }
+        }
+
+        Self {
+            by_size,
+            hashes: HashMap::new(),
+        }
+    }
+
+    /// 同じサイズ・同じハッシュの既存ファイルを探す
+    pub fn find(&mut self, size: u64, hash: &str) -> Option<PathBuf> {
+        let candidates = self.by_size.get(&size)?;
+        for path in candidates {
+            let existing = self
+                .hashes
+                .entry(path.clone())
+                .or_insert_with(|| hash_file(path).ok());
+            if existing.as_deref() == Some(hash) {
+                return Some(path.clone());
+            }
+        }
+        None
+    }
+}
+
+/// 選択されたファイルのハッシュを計算し、入力内・出力ディレクトリとの重複を判定
+///
+/// 入力内の重複は先に現れたファイルを正とし、後続のファイルを重複として記録します。
+/// 結果は `MediaInfo::content_hash` / `duplicate_status` / `duplicate_of` に反映されます。
+pub fn detect_duplicates(media: &mut [MediaInfo], output_dir: &Path, parallel: bool, control: &RunControl) {
+    let total = media.iter().filter(|m| m.selected).count();
+    let progress = control.phase(ProgressPhase::Hashing, total);
+
+    let hash_one = |item: &mut MediaInfo| {
+        if !item.selected || control.is_cancelled() {
+            return;
+        }
+        if item.content_hash.is_none() {
+            item.content_hash = hash_file(&item.original_path).ok();
+        }
+        progress.tick(&item.original_path);
+    };
+    if parallel {
+        media.par_iter_mut().for_each(hash_one);
+    } else {
+        media.iter_mut().for_each(hash_one);
+    }
+
+    let mut index = OutputIndex::build(output_dir);
+    let mut seen: HashMap<String, PathBuf> = HashMap::new();
+
+    for item in media.iter_mut().filter(|m| m.selected) {
+        item.duplicate_status = DuplicateStatus::Unique;
+        item.duplicate_of = None;
+
+        let Some(hash) = item.content_hash.clone() else {
+            continue;
+        };
+        let size = fs::metadata(&item.original_path)
+            .map(|m| m.len())
+            .unwrap_or(item.file_size);
+
+        if let Some(existing) = index.find(size, &hash) {
+            item.duplicate_status = DuplicateStatus::InOutput;
+            item.duplicate_of = Some(existing);
+        } else if let Some(first) = seen.get(&hash) {
+            item.duplicate_status = DuplicateStatus::InInput;
+            item.duplicate_of = Some(first.clone());
+        } else {
+            seen.insert(hash, item.original_path.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_index_matches_by_size_and_hash() {
+        let root = std::env::temp_dir().join(format!("photo_returns_dup_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let day_dir = root.join("2025").join("2025-01").join("2025-01-15");
+        fs::create_dir_all(&day_dir).unwrap();
+        fs::create_dir_all(root.join(JOURNAL_DIR_NAME)).unwrap();
+
+        let existing = day_dir.join("2025-01-15_10-30-00.jpg");
+        fs::write(&existing, b"photo-a").unwrap();
+        fs::write(day_dir.join("other.jpg"), b"photo-b").unwrap();
+        fs::write(root.join(JOURNAL_DIR_NAME).join("journal.json"), b"photo-a").unwrap();
+
+        let source = root.join("source.jpg");
+        fs::write(&source, b"photo-a").unwrap();
+        let hash = hash_file(&source).unwrap();
+
+        let mut index = OutputIndex::build(&day_dir);
+        assert_eq!(index.find(7, &hash), Some(existing));
+        assert_eq!(index.find(8, &hash), None);
+
+        // ジャーナルディレクトリは索引に含まれない
+        let index = OutputIndex::build(&root);
+        let matches = index.by_size.get(&7).unwrap();
+        assert!(matches.iter().all(|p| !p.starts_with(root.join(JOURNAL_DIR_NAME))));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,287 @@
+/// デスクトップアプリ（Tauri）のコマンドとエントリーポイント
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, State};
+
+use crate::burst::{BurstDetectorConfig, BurstPolicy};
+use crate::duplicate::DuplicatePolicy;
+use crate::journal::{self, UndoResult};
+use crate::photo_core::{self, MediaInfo, ProcessOptions, ProcessResult, RotationStrategy, TransferMode};
+use crate::progress::{CancellationToken, ProgressCallback, ProgressEvent, RunControl};
+use crate::template::Templates;
+
+/// 進捗イベント名（フロントエンドは listen("media-progress") で受け取る）
+const PROGRESS_EVENT: &str = "media-progress";
+
+/// 実行中の操作の共有状態
+#[derive(Default)]
+struct OperationState {
+    cancel: CancellationToken,
+}
+
+/// 新しい操作用の制御を作成（キャンセル状態をリセットし、進捗をイベントとして送信）
+fn start_operation(app: &AppHandle, state: &OperationState) -> RunControl {
+    state.cancel.reset();
+    let app = app.clone();
+    let callback: ProgressCallback = Arc::new(move |event: &ProgressEvent| {
+        let _ = app.emit(PROGRESS_EVENT, event);
+    });
+    RunControl::new(state.cancel.clone(), Some(callback))
+}
+
+/// 指定されたテンプレートをオプションに反映（Noneはデフォルトのまま）
+fn apply_templates(options: &mut ProcessOptions, directory_template: Option<String>, file_name_template: Option<String>) {
+    if let Some(template) = directory_template {
+        options.directory_template = template;
+    }
+    if let Some(template) = file_name_template {
+        options.file_name_template = template;
+    }
+}
+
+/// ディレクトリ・ファイル名テンプレートを検証（実行前のチェック用）
+#[tauri::command]
+fn validate_templates(directory_template: String, file_name_template: String) -> Result<(), String> {
+    Templates::parse(&directory_template, &file_name_template)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+/// 指定ディレクトリのメディアファイルをスキャンして情報を取得
+#[tauri::command(async)]
+fn scan_media(
+    app: AppHandle,
+    state: State<'_, OperationState>,
+    input_dir: String,
+    include_videos: bool,
+    parallel: bool,
+    detect_similar: bool,
+    detect_orientation: bool,
+    file_name_template: Option<String>,
+    timezone: Option<String>,
+    timezone_offset: Option<i32>,
+    burst_config: Option<BurstDetectorConfig>,
+) -> Result<Vec<MediaInfo>, String> {
+    let path = PathBuf::from(input_dir);
+    let mut options = ProcessOptions {
+        parallel,
+        include_videos,
+        detect_similar,
+        detect_orientation,
+        timezone,
+        timezone_offset,
+        burst: burst_config.unwrap_or_default(),
+        ..Default::default()
+    };
+    if let Some(template) = file_name_template {
+        options.file_name_template = template;
+    }
+    let control = start_operation(&app, &state);
+    photo_core::scan_media_with_control(&path, &options, &control).map_err(|e| e.to_string())
+}
+
+/// メディアファイルをリネームして出力ディレクトリに整理
+#[tauri::command(async)]
+fn process_media(
+    app: AppHandle,
+    state: State<'_, OperationState>,
+    input_dir: String,
+    output_dir: String,
+    backup_dir: Option<String>,
+    include_videos: bool,
+    parallel: bool,
+    timezone_offset: Option<i32>,
+    timezone: Option<String>,
+    cleanup_temp: bool,
+    auto_correct_orientation: bool,
+    dry_run: bool,
+    transfer_mode: TransferMode,
+    duplicate_policy: DuplicatePolicy,
+    rotation_strategy: RotationStrategy,
+    extract_motion_photo_video: bool,
+    directory_template: Option<String>,
+    file_name_template: Option<String>,
+    burst_config: Option<BurstDetectorConfig>,
+    burst_policy: Option<BurstPolicy>,
+) -> Result<ProcessResult, String> {
+    let input_path = PathBuf::from(input_dir);
+    let output_path = PathBuf::from(output_dir);
+    let backup_path = backup_dir.map(PathBuf::from);
+
+    let mut options = ProcessOptions {
+        parallel,
+        include_videos,
+        backup_dir: backup_path,
+        timezone_offset,
+        timezone,
+        cleanup_temp,
+        auto_correct_orientation,
+        dry_run,
+        transfer_mode,
+        duplicate_policy,
+        rotation_strategy,
+        extract_motion_photo_video,
+        burst: burst_config.unwrap_or_default(),
+        burst_policy: burst_policy.unwrap_or_default(),
+        ..Default::default()
+    };
+
+    apply_templates(&mut options, directory_template, file_name_template);
+
+    let control = start_operation(&app, &state);
+    photo_core::process_media_with_control(&input_path, &output_path, &options, &control).map_err(|e| e.to_string())
+}
+
+/// スキャン済みのメディアリストを、行ごとのユーザー選択を反映して処理
+#[tauri::command(async)]
+fn process_plan(
+    app: AppHandle,
+    state: State<'_, OperationState>,
+    media: Vec<MediaInfo>,
+    output_dir: String,
+    backup_dir: Option<String>,
+    parallel: bool,
+    cleanup_temp: bool,
+    dry_run: bool,
+    transfer_mode: TransferMode,
+    duplicate_policy: DuplicatePolicy,
+    rotation_strategy: RotationStrategy,
+    extract_motion_photo_video: bool,
+    directory_template: Option<String>,
+    file_name_template: Option<String>,
+    burst_policy: Option<BurstPolicy>,
+) -> Result<ProcessResult, String> {
+    let output_path = PathBuf::from(output_dir);
+    let backup_path = backup_dir.map(PathBuf::from);
+
+    let mut options = ProcessOptions {
+        parallel,
+        backup_dir: backup_path,
+        cleanup_temp,
+        dry_run,
+        transfer_mode,
+        duplicate_policy,
+        rotation_strategy,
+        extract_motion_photo_video,
+        burst_policy: burst_policy.unwrap_or_default(),
+        ..Default::default()
+    };
+
+    apply_templates(&mut options, directory_template, file_name_template);
+
+    let control = start_operation(&app, &state);
+    photo_core::process_plan_with_control(media, &output_path, &options, &control).map_err(|e| e.to_string())
+}
+
+/// 実行中のスキャン・処理のキャンセルを要求
+#[tauri::command]
+fn cancel_operation(state: State<'_, OperationState>) {
+    state.cancel.cancel();
+}
+
+/// ジャーナルに記録された処理を取り消す
+#[tauri::command]
+fn undo_run(journal_path: String) -> Result<UndoResult, String> {
+    journal::undo_run(Path::new(&journal_path)).map_err(|e| e.to_string())
+}
+
+/// ファイルをファイラーで開く（ファイルを選択した状態）
+#[tauri::command]
+fn reveal_in_filemanager(path: String) -> Result<(), String> {
+    let file_path = Path::new(&path);
+
+    // ファイルが存在しない場合は親ディレクトリを開く
+    let target_path = if file_path.exists() {
+        file_path
+    } else if let Some(parent) = file_path.parent() {
+        if parent.exists() {
+            parent
+        } else {
+            return Err(format!("Path does not exist: {}", path));
+        }
+    } else {
+        return Err(format!("Invalid path: {}", path));
+    };
+
+    #[cfg(target_os = "windows")]
+    {
+        if file_path.exists() {
+            // ファイルが存在する場合は選択して開く
+            Command::new("explorer")
+                .args(["/select,", &path])
+                .spawn()
+                .map_err(|e| format!("Failed to open file manager: {}", e))?;
+        } else {
+            // ディレクトリのみ開く
+            Command::new("explorer")
+                .arg(target_path)
+                .spawn()
+                .map_err(|e| format!("Failed to open file manager: {}", e))?;
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        if file_path.exists() {
+            // ファイルが存在する場合は選択して開く
+            Command::new("open")
+                .args(["-R", &path])
+                .spawn()
+                .map_err(|e| format!("Failed to open file manager: {}", e))?;
+        } else {
+            // ディレクトリのみ開く
+            Command::new("open")
+                .arg(target_path)
+                .spawn()
+                .map_err(|e| format!("Failed to open file manager: {}", e))?;
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        // Linuxでは複数のファイルマネージャーが存在するため、xdg-openを試す
+        if file_path.exists() {
+            // xdg-openはファイルを選択できないので、親ディレクトリを開く
+            if let Some(parent) = file_path.parent() {
+                Command::new("xdg-open")
+                    .arg(parent)
+                    .spawn()
+                    .map_err(|e| format!("Failed to open file manager: {}", e))?;
+            }
+        } else {
+            Command::new("xdg-open")
+                .arg(target_path)
+                .spawn()
+                .map_err(|e| format!("Failed to open file manager: {}", e))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// テスト用のgreetコマンド
+#[tauri::command]
+fn greet(name: &str) -> String {
+    format!("Hello, {}! You've been greeted from Rust!", name)
+}
+
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    tauri::Builder::default()
+        .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_dialog::init())
+        .manage(OperationState::default())
+        .invoke_handler(tauri::generate_handler![
+            greet,
+            scan_media,
+            process_media,
+            process_plan,
+            cancel_operation,
+            validate_templates,
+            undo_run,
+            reveal_in_filemanager
+        ])
+        .run(tauri::generate_context!())
+        .expect("error while running tauri application");
+}
@@ -0,0 +1,438 @@
+/// 処理ジャーナル（実行記録）と取り消し機能
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::photo_core::TransferMode;
+
+/// ジャーナルを保存するディレクトリ名（出力ディレクトリ直下）
+pub const JOURNAL_DIR_NAME: &str = ".photo-returns";
+
+/// ジャーナルの1エントリ（1ファイル分の操作）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// 元ファイルのパス
+    pub source: PathBuf,
+    /// 出力先のパス
+    pub destination: PathBuf,
+    /// 出力ファイルのSHA-256（16進数）
+    pub hash: String,
+    /// 画像回転が適用されたか
+    pub rotation_applied: bool,
+    /// バックアップ先（バックアップなしはNone）
+    pub backup_path: Option<PathBuf>,
+    /// 処理中に新たに作成したファイル（モーションフォトから抽出した動画など、取り消し時は削除のみ）
+    #[serde(default)]
+    pub generated: bool,
+    /// 実際に使った転送方法（記録の無い古いジャーナルはNone）
+    #[serde(default)]
+    pub transfer_mode: Option<TransferMode>,
+}
+
+/// 1回の処理実行の記録
+///
+/// ファイルはJSON Lines形式で、1行目が実行の情報（`entries` を除く）、2行目以降が1ファイル分のエントリです。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Journal {
+    /// 実行ID（開始時刻から生成）
+    pub run_id: String,
+    pub started_at: DateTime<Local>,
+    pub output_dir: PathBuf,
+    #[serde(default, skip_serializing)]
+    pub entries: Vec<JournalEntry>,
+}
+
+/// 処理中にエントリを1件ずつ追記するジャーナル
+///
+/// 処理が途中で強制終了しても、それまでに完了したファイルは取り消せます。
+pub struct JournalWriter {
+    path: PathBuf,
+    file: fs::File,
+    entries: usize,
+}
+
+/// 取り消し結果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UndoResult {
+    /// 削除した出力ファイル数
+    pub removed_files: usize,
+    /// 元の場所に戻したファイル数
+    pub restored_files: usize,
+    /// 削除した空ディレクトリ数
+    pub removed_directories: usize,
+    pub errors: Vec<String>,
+}
+
+impl Journal {
+    /// 新しい実行のジャーナルを作成
+    pub fn new(output_dir: &Path) -> Self {
+        let started_at = Local::now();
+        Self {
+            run_id: started_at.format("%Y%m%d_%H%M%S_%3f").to_string(),
+            started_at,
+            output_dir: output_dir.to_path_buf(),
+            entries: Vec::new(),
+        }
+    }
+
+    /// ジャーナルファイルのパス（<output>/.photo-returns/journal_<run_id>.jsonl）
+    pub fn path(&self) -> PathBuf {
+        self.output_dir
+            .join(JOURNAL_DIR_NAME)
+            .join(format!("journal_{}.jsonl", self.run_id))
+    }
+
+    /// ジャーナルをファイルに書き出し、そのパスを返す
+    pub fn write(&self) -> Result<PathBuf> {
+        let mut writer = JournalWriter::create(self)?;
+        for entry in &self.entries {
+            writer.append(entry)?;
+        }
+        Ok(writer.path)
+    }
+
+    /// ジャーナルファイルを読み込み
+    ///
+    /// 書き込み中に強制終了して最後の行が途中で切れている場合は、その行を除いて読み込みます。
+    /// 以前の形式（全体で1つのJSON）のジャーナルも読み込めます。
+    pub fn read(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).context("Failed to read journal")?;
+        // 全体が1つのJSONとして読めるのは、以前の形式かエントリの無いジャーナル
+        if let Ok(journal) = serde_json::from_str::<Journal>(&text) {
+            return Ok(journal);
+        }
+        let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+        let header = lines.next().context("Journal is empty")?;
+        let mut journal: Journal = serde_json::from_str(header).context("Failed to parse journal")?;
+        let lines: Vec<&str> = lines.collect();
+        for (index, line) in lines.iter().enumerate() {
+            match serde_json::from_str(line) {
+                Ok(entry) => journal.entries.push(entry),
+                Err(_) if index + 1 == lines.len() => break,
+                Err(e) => return Err(e).context("Failed to parse journal entry"),
+            }
+        }
+        Ok(journal)
+    }
+}
+
+impl JournalWriter {
+    /// ジャーナルファイルを作成し、実行の情報を書き込む
+    pub fn create(journal: &Journal) -> Result<Self> {
+        let path = journal.path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context("Failed to create journal directory")?;
+        }
+        let mut file = fs::File::create(&path).context("Failed to write journal")?;
+        writeln!(file, "{}", serde_json::to_string(journal)?).context("Failed to write journal")?;
+        Ok(Self { path, file, entries: 0 })
+    }
+
+    /// エントリを1行追記してすぐにファイルへ書き出す
+    pub fn append(&mut self, entry: &JournalEntry) -> Result<()> {
+        let line = format!("{}\n", serde_json::to_string(entry)?);
+        self.file.write_all(line.as_bytes()).context("Failed to write journal")?;
+        self.file.flush().context("Failed to write journal")?;
+        self.entries += 1;
+        Ok(())
+    }
+
+    /// 書き込みを終え、エントリがあればパスを返す（1件も無い場合はファイルを削除）
+    pub fn finish(self) -> Result<Option<PathBuf>> {
+        if self.entries > 0 {
+            return Ok(Some(self.path));
+        }
+        drop(self.file);
+        fs::remove_file(&self.path).context("Failed to remove empty journal")?;
+        if let Some(parent) = self.path.parent() {
+            let _ = fs::remove_dir(parent);
+        }
+        Ok(None)
+    }
+}
+
+/// ファイル内容のSHA-256を16進数文字列で取得
+pub fn hash_file(path: &Path) -> Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+/// ジャーナルに記録された処理を取り消す
+///
+/// 移動したファイルは元の場所へ戻し、コピー・ハードリンクしたファイルと処理中に新たに作成したファイルは削除します
+/// （転送方法の記録が無い古いジャーナルは、元ファイルが残っていれば削除、無ければ元の場所へ戻します）。
+/// 出力後に内容が変更されたファイル（ハッシュ不一致）、戻す先が使われているか元のフォルダが無いファイル、
+/// バックアップが無く回転前の状態に戻せないファイルは、安全のため残してエラーに記録します。
+/// 最後に空になった YYYY/YYYY-MM/YYYY-MM-DD ディレクトリを削除します。
+pub fn undo_run(journal_path: &Path) -> Result<UndoResult> {
+    let journal = Journal::read(journal_path)?;
+    let mut result = UndoResult::default();
+
+    for entry in journal.entries.iter().rev() {
+        if !entry.destination.exists() {
+            result
+                .errors
+                .push(format!("Output file no longer exists: {}", entry.destination.display()));
+            continue;
+        }
+
+        match hash_file(&entry.destination) {
+            Ok(hash) if hash == entry.hash => {}
+            Ok(_) => {
+                result.errors.push(format!(
+                    "Output file was modified after processing, left in place: {}",
+                    entry.destination.display()
+                ));
+                continue;
+            }
+            Err(e) => {
+                result
+                    .errors
+                    .push(format!("Failed to hash {}: {}", entry.destination.display(), e));
+                continue;
+            }
+        }
+
+        let restores = !entry.generated
+            && match entry.transfer_mode {
+                Some(mode) => mode == TransferMode::Move,
+                None => !entry.source.exists(),
+            };
+        if restores {
+            if let Err(message) = check_restorable(entry) {
+                result.errors.push(message);
+                continue;
+            }
+        }
+
+        let outcome = if restores {
+            restore_file(entry)
+        } else {
+            fs::remove_file(&entry.destination)
+        };
+        match outcome {
+            Ok(_) if restores => result.restored_files += 1,
+            Ok(_) => result.removed_files += 1,
+            Err(e) => result
+                .errors
+                .push(format!("Failed to undo {}: {}", entry.destination.display(), e)),
+        }
+
+        result.removed_directories += remove_empty_parents(&entry.destination, &journal.output_dir);
+    }
+
+    // 全て取り消せた場合のみジャーナルを削除（失敗分は再実行できるよう残す）
+    if result.errors.is_empty() {
+        fs::remove_file(journal_path).context("Failed to remove journal")?;
+        let _ = fs::remove_dir(journal.output_dir.join(JOURNAL_DIR_NAME));
+    }
+
+    Ok(result)
+}
+
+/// 移動したファイルを元の場所へ戻せるか確認
+///
+/// 取り外したSDカードのように元のフォルダが無い場合はフォルダを作らず、
+/// 回転済みでバックアップが無い場合は書き換えた内容を元ファイルとして戻さずに残します。
+fn check_restorable(entry: &JournalEntry) -> std::result::Result<(), String> {
+    if entry.source.exists() {
+        return Err(format!(
+            "Original path is already in use, left in place: {}",
+            entry.destination.display()
+        ));
+    }
+    if entry.source.parent().is_some_and(|parent| !parent.is_dir()) {
+        return Err(format!(
+            "Original folder no longer exists, left in place: {}",
+            entry.destination.display()
+        ));
+    }
+    if entry.rotation_applied && !entry.backup_path.as_ref().is_some_and(|b| b.exists()) {
+        return Err(format!(
+            "Rotated file has no backup of the original, left in place: {}",
+            entry.destination.display()
+        ));
+    }
+    Ok(())
+}
+
+/// 移動したファイルを元の場所へ戻す（回転済みファイルは回転前のバックアップから復元）
+fn restore_file(entry: &JournalEntry) -> std::io::Result<()> {
+    if entry.rotation_applied {
+        if let Some(backup) = &entry.backup_path {
+            fs::copy(backup, &entry.source)?;
+            return fs::remove_file(&entry.destination);
+        }
+    }
+
+    if fs::rename(&entry.destination, &entry.source).is_err() {
+        // 別デバイス間はコピーして削除
+        fs::copy(&entry.destination, &entry.source)?;
+        fs::remove_file(&entry.destination)?;
+    }
+    Ok(())
+}
+
+/// 出力ファイルの親ディレクトリを、空であれば出力ディレクトリの手前まで順に削除
+fn remove_empty_parents(destination: &Path, output_dir: &Path) -> usize {
+    let mut removed = 0;
+    for dir in destination.ancestors().skip(1) {
+        if dir == output_dir || !dir.starts_with(output_dir) {
+            break;
+        }
+        if fs::remove_dir(dir).is_err() {
+            break;
+        }
+        removed += 1;
+    }
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_undo_run_removes_outputs_and_empty_dirs() {
+        let root = std::env::temp_dir().join(format!("photo_returns_journal_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let output = root.join("output");
+        let day_dir = output.join("2025").join("2025-01").join("2025-01-15");
+        fs::create_dir_all(&day_dir).unwrap();
+
+        let source = root.join("IMG_0001.jpg");
+        let destination = day_dir.join("2025-01-15_10-30-00.jpg");
+        fs::write(&source, b"photo").unwrap();
+        fs::copy(&source, &destination).unwrap();
+
+        let mut journal = Journal::new(&output);
+        journal.entries.push(JournalEntry {
+            source: source.clone(),
+            destination: destination.clone(),
+            hash: hash_file(&destination).unwrap(),
+            rotation_applied: false,
+            backup_path: None,
+            generated: false,
+            transfer_mode: Some(TransferMode::Copy),
+        });
+        let journal_path = journal.write().unwrap();
+
+        let result = undo_run(&journal_path).unwrap();
+
+        assert!(result.errors.is_empty());
+        assert_eq!(result.removed_files, 1);
+        assert_eq!(result.removed_directories, 3);
+        assert!(source.exists());
+        assert!(!output.join("2025").exists());
+        assert!(!journal_path.exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_undo_run_keeps_modified_outputs() {
+        let root = std::env::temp_dir().join(format!("photo_returns_journal_mod_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let output = root.join("output");
+        fs::create_dir_all(&output).unwrap();
+
+        let destination = output.join("edited.jpg");
+        fs::write(&destination, b"edited later").unwrap();
+
+        let mut journal = Journal::new(&output);
+        journal.entries.push(JournalEntry {
+            source: root.join("missing.jpg"),
+            destination: destination.clone(),
+            hash: "0".repeat(64),
+            rotation_applied: false,
+            backup_path: None,
+            generated: false,
+            transfer_mode: Some(TransferMode::Copy),
+        });
+        let journal_path = journal.write().unwrap();
+
+        let result = undo_run(&journal_path).unwrap();
+
+        assert_eq!(result.errors.len(), 1);
+        assert!(destination.exists());
+        assert!(journal_path.exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_undo_run_by_transfer_mode() {
+        let root = std::env::temp_dir().join(format!("photo_returns_journal_modes_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let card = root.join("card");
+        let output = root.join("output");
+        fs::create_dir_all(&card).unwrap();
+        fs::create_dir_all(&output).unwrap();
+
+        let entry = |name: &str, mode: TransferMode, rotation_applied: bool| {
+            let destination = output.join(name);
+            fs::write(&destination, name).unwrap();
+            JournalEntry {
+                source: card.join(name),
+                destination: destination.clone(),
+                hash: hash_file(&destination).unwrap(),
+                rotation_applied,
+                backup_path: None,
+                generated: false,
+                transfer_mode: Some(mode),
+            }
+        };
+        let mut journal = Journal::new(&output);
+        journal.entries = vec![
+            // 元ファイルが無くても、コピーしたファイルは元の場所へ戻さず削除する
+            entry("copied.jpg", TransferMode::Copy, false),
+            entry("moved.jpg", TransferMode::Move, false),
+            // 回転済みでバックアップが無いファイルは戻さずに残す
+            entry("rotated.jpg", TransferMode::Move, true),
+        ];
+        let journal_path = journal.write().unwrap();
+
+        // 書き込み途中で切れた最後の行は無視する
+        let mut file = fs::OpenOptions::new().append(true).open(&journal_path).unwrap();
+        file.write_all(b"{\"source\":").unwrap();
+
+        let result = undo_run(&journal_path).unwrap();
+
+        assert_eq!(result.removed_files, 1);
+        assert_eq!(result.restored_files, 1);
+        assert_eq!(result.errors.len(), 1, "{:?}", result.errors);
+        assert!(!card.join("copied.jpg").exists());
+        assert_eq!(fs::read(card.join("moved.jpg")).unwrap(), b"moved.jpg");
+        assert!(output.join("rotated.jpg").exists());
+        assert!(!card.join("rotated.jpg").exists());
+
+        // 取り外したSDカードの場所には戻さない
+        fs::remove_dir_all(&card).unwrap();
+        let mut journal = Journal::new(&output);
+        journal.entries = vec![entry("unmounted.jpg", TransferMode::Move, false)];
+        let result = undo_run(&journal.write().unwrap()).unwrap();
+        assert_eq!(result.errors.len(), 1);
+        assert!(!card.exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,303 @@
+/// Live Photo（静止画＋動画のペア）とモーションフォト（JPEG末尾の埋め込み動画）の検出
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// JPEGのAPP1に記録されるXMPの識別子
+const XMP_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+
+/// Live Photoの静止画の拡張子
+const LIVE_PHOTO_IMAGE_EXTENSIONS: &[&str] = &["heic", "heif", "jpg", "jpeg"];
+/// Live Photoの動画の拡張子
+const LIVE_PHOTO_VIDEO_EXTENSIONS: &[&str] = &["mov"];
+
+/// JPEGに埋め込まれた動画の位置（ファイル先頭からのバイト位置と長さ）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmbeddedVideo {
+    pub offset: u64,
+    pub length: u64,
+}
+
+/// ペア検出の対象（スキャン結果の1ファイル分）
+#[derive(Debug, Clone, Copy)]
+pub struct PairCandidate<'a> {
+    pub path: &'a Path,
+    pub is_video: bool,
+    /// Live Photoの識別子（静止画はMakerNote、動画はQuickTimeのメタデータ）
+    pub content_identifier: Option<&'a str>,
+}
+
+/// Live Photoのペア（元のリスト内のインデックス）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LivePhotoPair {
+    pub photo_index: usize,
+    pub video_index: usize,
+}
+
+/// モーションフォト（Google・Samsung）のJPEGから埋め込み動画の位置を取得
+///
+/// XMPの `GCamera:MicroVideoOffset`（旧形式）または `Container:Directory` の
+/// `Semantic="MotionPhoto"` の `Item:Length`（新形式）から、ファイル末尾にある動画の長さを読み取ります。
+/// 動画の先頭がMP4のftypボックスでない場合は埋め込み動画なしとします。
+pub fn find_motion_photo_video(path: &Path) -> Result<Option<EmbeddedVideo>> {
+    let mut file = fs::File::open(path)?;
+    let file_len = file.metadata()?.len();
+    let Some(xmp) = read_jpeg_xmp(&mut BufReader::new(&mut file))? else {
+        return Ok(None);
+    };
+    let Some(length) = motion_photo_length(&xmp).filter(|&length| length > 0 && length < file_len) else {
+        return Ok(None);
+    };
+
+    let offset = file_len - length;
+    let mut header = [0u8; 8];
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut header)?;
+    Ok((&header[4..8] == b"ftyp").then_some(EmbeddedVideo { offset, length }))
+}
+
+/// 埋め込み動画を別ファイルに書き出す（既存のファイルは上書きしない）
+pub fn extract_embedded_video(source: &Path, video: EmbeddedVideo, target: &Path) -> Result<()> {
+    let mut input = fs::File::open(source)?;
+    input.seek(SeekFrom::Start(video.offset))?;
+    let mut output = fs::OpenOptions::new().write(true).create_new(true).open(target)?;
+    let copied = std::io::copy(&mut input.take(video.length), &mut output)?;
+    if copied != video.length {
+        drop(output);
+        let _ = fs::remove_file(target);
+        anyhow::bail!("Embedded video is truncated ({} of {} bytes)", copied, video.length);
+    }
+    Ok(())
+}
+
+/// JPEGのAPP1セグメントからXMPを読み取る（画像データの手前まで）
+fn read_jpeg_xmp<R: Read>(reader: &mut R) -> Result<Option<String>> {
+    let mut marker = [0u8; 2];
+    reader.read_exact(&mut marker)?;
+    if marker != [0xFF, 0xD8] {
+        return Ok(None);
+    }
+
+    loop {
+        reader.read_exact(&mut marker)?;
+        // SOS（画像データの開始）・EOI以降にはXMPは無い
+        if marker[0] != 0xFF || matches!(marker[1], 0xDA | 0xD9) {
+            return Ok(None);
+        }
+        let mut length = [0u8; 2];
+        reader.read_exact(&mut length)?;
+        let Some(length) = (u16::from_be_bytes(length) as usize).checked_sub(2) else {
+            return Ok(None);
+        };
+        let mut payload = vec![0u8; length];
+        reader.read_exact(&mut payload)?;
+
+        if marker[1] == 0xE1 && payload.starts_with(XMP_HEADER) {
+            let xmp = String::from_utf8_lossy(&payload[XMP_HEADER.len()..]).into_owned();
+            return Ok(Some(xmp));
+        }
+    }
+}
+
+/// XMPからファイル末尾の埋め込み動画の長さを取得
+fn motion_photo_length(xmp: &str) -> Option<u64> {
+    // 旧形式（Pixel 2〜）：MicroVideoOffsetはファイル末尾から動画の先頭までのバイト数
+    if xmp_property(xmp, "MicroVideo") == Some("1") {
+        if let Some(length) = xmp_property(xmp, "MicroVideoOffset").and_then(|v| v.parse().ok()) {
+            return Some(length);
+        }
+    }
+
+    // 新形式（Motion Photo 1.0、Samsungを含む）：末尾の項目の長さ
+    if xmp_property(xmp, "MotionPhoto") != Some("1") {
+        return None;
+    }
+    xmp.split('<')
+        .filter(|element| element.split_whitespace().next().is_some_and(|name| name.ends_with(":Item")))
+        .find(|element| xmp_property(element, "Semantic") == Some("MotionPhoto"))
+        .and_then(|element| xmp_property(element, "Length")?.parse().ok())
+}
+
+/// XMPのプロパティの値を名前空間の接頭辞を問わずに取得（属性・要素の両方の書き方に対応）
+fn xmp_property<'a>(xmp: &'a str, local_name: &str) -> Option<&'a str> {
+    let attribute = format!(":{}=\"", local_name);
+    if let Some(start) = xmp.find(&attribute).map(|pos| pos + attribute.len()) {
+        let end = xmp[start..].find('"')?;
+        return Some(xmp[start..start + end].trim());
+    }
+
+    let element = format!(":{}>", local_name);
+    let start = xmp.find(&element)? + element.len();
+    let end = xmp[start..].find('<')?;
+    Some(xmp[start..start + end].trim())
+}
+
+fn extension_of(path: &Path) -> String {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase()
+}
+
+/// Live Photoの静止画と動画の組を検出
+///
+/// 1. ContentIdentifierが一致する静止画と動画（ファイル名が変わっていても組になる）
+/// 2. 同じフォルダにある同じファイル名（拡張子を除く、大文字小文字を区別しない）のHEIC/JPEGとMOV
+///
+/// 2.ではどちらにもContentIdentifierがあり、値が異なる場合は組にしません。
+/// 結果は静止画のインデックス順です。
+pub fn detect_live_photo_pairs(candidates: &[PairCandidate]) -> Vec<LivePhotoPair> {
+    let mut paired = vec![false; candidates.len()];
+    let mut pairs = Vec::new();
+
+    let mut videos_by_identifier: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (index, candidate) in candidates.iter().enumerate().filter(|(_, c)| c.is_video) {
+        if let Some(identifier) = candidate.content_identifier {
+            videos_by_identifier.entry(identifier).or_default().push(index);
+        }
+    }
+    for (photo_index, candidate) in candidates.iter().enumerate().filter(|(_, c)| !c.is_video) {
+        let Some(videos) = candidate.content_identifier.and_then(|id| videos_by_identifier.get(id)) else {
+            continue;
+        };
+        if let Some(&video_index) = videos.iter().find(|&&v| !paired[v]) {
+            paired[photo_index] = true;
+            paired[video_index] = true;
+            pairs.push(LivePhotoPair { photo_index, video_index });
+        }
+    }
+
+    let stem_key = |path: &Path| {
+        let stem = path.file_stem()?.to_str()?.to_lowercase();
+        Some((path.parent()?.to_path_buf(), stem))
+    };
+    let mut videos_by_stem: HashMap<_, Vec<usize>> = HashMap::new();
+    for (index, candidate) in candidates.iter().enumerate() {
+        if candidate.is_video
+            && !paired[index]
+            && LIVE_PHOTO_VIDEO_EXTENSIONS.contains(&extension_of(candidate.path).as_str())
+        {
+            if let Some(key) = stem_key(candidate.path) {
+                videos_by_stem.entry(key).or_default().push(index);
+            }
+        }
+    }
+    for (photo_index, candidate) in candidates.iter().enumerate() {
+        if candidate.is_video
+            || paired[photo_index]
+            || !LIVE_PHOTO_IMAGE_EXTENSIONS.contains(&extension_of(candidate.path).as_str())
+        {
+            continue;
+        }
+        let Some(videos) = stem_key(candidate.path).and_then(|key| videos_by_stem.get(&key)) else {
+            continue;
+        };
+        let compatible = |video: &PairCandidate| match (candidate.content_identifier, video.content_identifier) {
+            (Some(a), Some(b)) => a == b,
+            _ => true,
+        };
+        if let Some(&video_index) = videos.iter().find(|&&v| !paired[v] && compatible(&candidates[v])) {
+            paired[photo_index] = true;
+            paired[video_index] = true;
+            pairs.push(LivePhotoPair { photo_index, video_index });
+        }
+    }
+
+    pairs.sort_by_key(|pair| pair.photo_index);
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate<'a>(path: &'a str, is_video: bool, content_identifier: Option<&'a str>) -> PairCandidate<'a> {
+        PairCandidate {
+            path: Path::new(path),
+            is_video,
+            content_identifier,
+        }
+    }
+
+    #[test]
+    fn test_detect_live_photo_pairs() {
+        let candidates = [
+            candidate("/in/IMG_0001.HEIC", false, Some("AAAA")),
+            candidate("/in/IMG_0002.JPG", false, None),
+            candidate("/in/export/renamed.mov", true, Some("AAAA")),
+            candidate("/in/IMG_0002.MOV", true, None),
+            // 識別子が異なる同名ファイルは組にしない
+            candidate("/in/IMG_0003.HEIC", false, Some("BBBB")),
+            candidate("/in/IMG_0003.MOV", true, Some("CCCC")),
+            // 別フォルダ・MOV以外は組にしない
+            candidate("/in/other/IMG_0004.JPG", false, None),
+            candidate("/in/IMG_0004.MOV", true, None),
+            candidate("/in/IMG_0005.JPG", false, None),
+            candidate("/in/IMG_0005.MP4", true, None),
+        ];
+        let pairs = detect_live_photo_pairs(&candidates);
+        assert_eq!(
+            pairs,
+            vec![
+                LivePhotoPair { photo_index: 0, video_index: 2 },
+                LivePhotoPair { photo_index: 1, video_index: 3 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_motion_photo_length() {
+        let legacy = r#"<rdf:Description GCamera:MicroVideo="1" GCamera:MicroVideoVersion="1" GCamera:MicroVideoOffset="4096"/>"#;
+        assert_eq!(motion_photo_length(legacy), Some(4096));
+
+        let container = r#"<rdf:Description Camera:MotionPhoto="1" Camera:MotionPhotoVersion="1">
+            <Container:Directory><rdf:Seq>
+              <rdf:li rdf:parseType="Resource"><Container:Item Item:Mime="image/jpeg" Item:Semantic="Primary" Item:Length="0" Item:Padding="0"/></rdf:li>
+              <rdf:li rdf:parseType="Resource"><Container:Item Item:Mime="video/mp4" Item:Semantic="MotionPhoto" Item:Length="2048"/></rdf:li>
+            </rdf:Seq></Container:Directory></rdf:Description>"#;
+        assert_eq!(motion_photo_length(container), Some(2048));
+
+        // 要素形式の値
+        assert_eq!(xmp_property("<GCamera:MicroVideo>1</GCamera:MicroVideo>", "MicroVideo"), Some("1"));
+        assert_eq!(motion_photo_length(r#"<x Camera:MotionPhoto="0"/>"#), None);
+    }
+
+    #[test]
+    fn test_find_and_extract_motion_photo_video() {
+        let dir = std::env::temp_dir().join(format!("photo_returns_motion_photo_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let video = [&[0, 0, 0, 16][..], b"ftypisom", &[0, 0, 0, 0], &[0, 0, 0, 8], b"mdat"].concat();
+        let xmp = format!(
+            r#"<x:xmpmeta><rdf:Description GCamera:MicroVideo="1" GCamera:MicroVideoOffset="{}"/></x:xmpmeta>"#,
+            video.len()
+        );
+        let app1 = [XMP_HEADER, xmp.as_bytes()].concat();
+        let jpeg = [
+            &[0xFF, 0xD8, 0xFF, 0xE1][..],
+            &((app1.len() + 2) as u16).to_be_bytes(),
+            &app1,
+            &[0xFF, 0xDA, 0, 2, 0xFF, 0xD9],
+            &video,
+        ]
+        .concat();
+        let source = dir.join("PXL_20240501_123456789.MP.jpg");
+        fs::write(&source, &jpeg).unwrap();
+
+        let embedded = find_motion_photo_video(&source).unwrap().unwrap();
+        assert_eq!(embedded.length, video.len() as u64);
+        assert_eq!(embedded.offset, (jpeg.len() - video.len()) as u64);
+
+        let target = dir.join("motion.mp4");
+        extract_embedded_video(&source, embedded, &target).unwrap();
+        assert_eq!(fs::read(&target).unwrap(), video);
+        // 既存のファイルは上書きしない
+        assert!(extract_embedded_video(&source, embedded, &target).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,312 @@
+/// メーカー固有のEXIF情報（MakerNote）の読み取り
+///
+/// MakerNoteはメーカーごとに独自のIFD構造を持つため、連写・Live Photoの識別に使うタグだけを読み取ります。
+/// - Apple: `BurstUUID`（同じバーストの写真で共通）、`ContentIdentifier`（Live Photoの動画と共通）
+/// - Canon: `CameraSettings` の `ContinuousDrive`、`ShotInfo` の `SequenceNumber`
+/// - Sony: `SequenceNumber`（0xB04A）
+use crate::tiff::{ENTRY_SIZE, TYPE_SHORT};
+
+/// AppleのMakerNoteの先頭
+const APPLE_MAKER_NOTE_HEADER: &[u8] = b"Apple iOS\0";
+/// AppleのMakerNoteのIFDの位置（ヘッダー10バイト + バージョン2バイト + "MM"）
+const APPLE_IFD_OFFSET: usize = 14;
+/// AppleのMakerNoteのBurstUUID
+const APPLE_BURST_UUID_TAG: u16 = 0x000B;
+/// AppleのMakerNoteのContentIdentifier
+const APPLE_CONTENT_IDENTIFIER_TAG: u16 = 0x0011;
+
+/// SonyのMakerNoteの先頭（古い機種はヘッダーなしでIFDから始まる）
+const SONY_MAKER_NOTE_HEADERS: &[&[u8]] = &[b"SONY DSC \0\0\0", b"SONY CAM \0\0\0"];
+/// SonyのMakerNoteのSequenceNumber（0: 単写、65535: 不明）
+const SONY_SEQUENCE_NUMBER_TAG: u16 = 0xB04A;
+
+/// CanonのMakerNoteのCameraSettings（SHORTの配列、先頭は配列のバイト数）
+const CANON_CAMERA_SETTINGS_TAG: u16 = 0x0001;
+/// CanonのMakerNoteのShotInfo（SHORTの配列、先頭は配列のバイト数）
+const CANON_SHOT_INFO_TAG: u16 = 0x0004;
+/// CameraSettings内のContinuousDrive（0: 単写）
+const CANON_CONTINUOUS_DRIVE_INDEX: usize = 5;
+/// ShotInfo内のSequenceNumber
+const CANON_SEQUENCE_NUMBER_INDEX: usize = 9;
+
+/// TIFFのASCII型
+const TYPE_ASCII: u16 = 2;
+
+/// MakerNoteから読み取った連写・Live Photoの識別情報
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MakerNoteInfo {
+    /// Live Photoの識別子（Apple）
+    pub content_identifier: Option<String>,
+    /// バーストの識別子（Apple）
+    pub burst_id: Option<String>,
+    /// 連写中の通し番号（Canon・Sony、単写ではNone）
+    pub sequence_number: Option<u32>,
+}
+
+/// MakerNoteを読み取る
+///
+/// `tiff` はEXIFのTIFF構造全体、`offset` はその中のMakerNoteの位置です。
+/// CanonとSonyのMakerNoteは値のオフセットがTIFFヘッダーからの位置のため、TIFF構造全体が必要です。
+pub fn parse(make: Option<&str>, tiff: &[u8], offset: usize) -> MakerNoteInfo {
+    let maker_note = tiff.get(offset..).unwrap_or_default();
+    let little_endian = tiff.starts_with(b"II");
+    let make = make.unwrap_or("").to_ascii_lowercase();
+
+    if maker_note.starts_with(APPLE_MAKER_NOTE_HEADER) {
+        return parse_apple(maker_note);
+    }
+    let sequence_number = if make.starts_with("canon") {
+        canon_sequence_number(&IfdReader {
+            data: tiff,
+            offset,
+            little_endian,
+        })
+    } else if make.starts_with("sony") {
+        let header = SONY_MAKER_NOTE_HEADERS
+            .iter()
+            .find(|header| maker_note.starts_with(header))
+            .map_or(0, |header| header.len());
+        sony_sequence_number(&IfdReader {
+            data: tiff,
+            offset: offset + header,
+            little_endian,
+        })
+    } else {
+        None
+    };
+
+    MakerNoteInfo {
+        sequence_number,
+        ..Default::default()
+    }
+}
+
+/// AppleのMakerNoteを読み取る
+///
+/// "Apple iOS\0" + バージョン(2バイト) + "MM" の後にビッグエンディアンのIFDが続きます。
+/// 値のオフセットはMakerNoteの先頭からの位置です。
+pub fn parse_apple(maker_note: &[u8]) -> MakerNoteInfo {
+    if !maker_note.starts_with(APPLE_MAKER_NOTE_HEADER) || maker_note.get(12..14) != Some(b"MM") {
+        return MakerNoteInfo::default();
+    }
+    let ifd = IfdReader {
+        data: maker_note,
+        offset: APPLE_IFD_OFFSET,
+        little_endian: false,
+    };
+    MakerNoteInfo {
+        content_identifier: ifd.ascii(APPLE_CONTENT_IDENTIFIER_TAG),
+        burst_id: ifd.ascii(APPLE_BURST_UUID_TAG),
+        sequence_number: None,
+    }
+}
+
+/// Canonの連写中の通し番号（ドライブモードが単写の場合はNone）
+fn canon_sequence_number(ifd: &IfdReader) -> Option<u32> {
+    let drive = *ifd
+        .shorts(CANON_CAMERA_SETTINGS_TAG)?
+        .get(CANON_CONTINUOUS_DRIVE_INDEX)?;
+    if drive == 0 {
+        return None;
+    }
+    let sequence = *ifd
+        .shorts(CANON_SHOT_INFO_TAG)?
+        .get(CANON_SEQUENCE_NUMBER_INDEX)?;
+    Some(u32::from(sequence))
+}
+
+/// Sonyの連写中の通し番号（単写・不明の場合はNone）
+fn sony_sequence_number(ifd: &IfdReader) -> Option<u32> {
+    match *ifd.shorts(SONY_SEQUENCE_NUMBER_TAG)?.first()? {
+        0 | u16::MAX => None,
+        sequence => Some(u32::from(sequence)),
+    }
+}
+
+/// IFDのタグの値を読み取る（値のオフセットは `data` の先頭からの位置）
+struct IfdReader<'a> {
+    data: &'a [u8],
+    /// IFDの位置
+    offset: usize,
+    little_endian: bool,
+}
+
+impl IfdReader<'_> {
+    /// タグの (型, 値のバイト列)
+    fn entry(&self, tag: u16) -> Option<(u16, &[u8])> {
+        let count = usize::from(self.read_u16(self.offset)?);
+        let entry = (0..count)
+            .map(|i| self.offset + 2 + i * ENTRY_SIZE)
+            .find(|&entry| self.read_u16(entry) == Some(tag))?;
+        let kind = self.read_u16(entry + 2)?;
+        let unit = match kind {
+            TYPE_ASCII => 1,
+            TYPE_SHORT => 2,
+            _ => return None,
+        };
+        let size = (self.read_u32(entry + 4)? as usize).checked_mul(unit)?;
+        let start = if size <= 4 {
+            entry + 8
+        } else {
+            self.read_u32(entry + 8)? as usize
+        };
+        Some((kind, self.data.get(start..start.checked_add(size)?)?))
+    }
+
+    /// ASCIIの値（NUL・前後の空白を除き、空ならNone）
+    fn ascii(&self, tag: u16) -> Option<String> {
+        let (TYPE_ASCII, value) = self.entry(tag)? else {
+            return None;
+        };
+        let text = String::from_utf8_lossy(value)
+            .trim_matches(|c: char| c == '\0' || c.is_whitespace())
+            .to_string();
+        (!text.is_empty()).then_some(text)
+    }
+
+    /// SHORTの配列
+    fn shorts(&self, tag: u16) -> Option<Vec<u16>> {
+        let (TYPE_SHORT, value) = self.entry(tag)? else {
+            return None;
+        };
+        let little_endian = self.little_endian;
+        Some(
+            value
+                .chunks_exact(2)
+                .map(|b| {
+                    if little_endian {
+                        u16::from_le_bytes([b[0], b[1]])
+                    } else {
+                        u16::from_be_bytes([b[0], b[1]])
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    fn read_u16(&self, pos: usize) -> Option<u16> {
+        let bytes: [u8; 2] = self.data.get(pos..pos.checked_add(2)?)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    }
+
+    fn read_u32(&self, pos: usize) -> Option<u32> {
+        let bytes: [u8; 4] = self.data.get(pos..pos.checked_add(4)?)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// IFDエントリ（リトルエンディアン、値は4バイト以内はそのまま、それ以外はオフセット）
+    fn entry_le(tag: u16, kind: u16, count: u32, value: u32) -> Vec<u8> {
+        [
+            tag.to_le_bytes().as_slice(),
+            &kind.to_le_bytes(),
+            &count.to_le_bytes(),
+            &value.to_le_bytes(),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn test_parse_apple() {
+        let uuid = b"6A7B8C9D-0000-1111-2222-333344445555\0";
+        let mut note = [APPLE_MAKER_NOTE_HEADER, &[0, 1], b"MM", &[0, 1]].concat();
+        let value_offset = (note.len() + ENTRY_SIZE + 4) as u32;
+        note.extend(APPLE_BURST_UUID_TAG.to_be_bytes());
+        note.extend(TYPE_ASCII.to_be_bytes());
+        note.extend((uuid.len() as u32).to_be_bytes());
+        note.extend(value_offset.to_be_bytes());
+        note.extend([0; 4]);
+        note.extend(uuid);
+
+        let info = parse_apple(&note);
+        assert_eq!(
+            info.burst_id.as_deref(),
+            Some("6A7B8C9D-0000-1111-2222-333344445555")
+        );
+        assert_eq!(info.content_identifier, None);
+    }
+
+    #[test]
+    fn test_apple_content_identifier() {
+        let identifier = b"5C1C7A0E-2B1D-4E4A-9D0B-1F2E3D4C5B6A\0";
+        let mut maker_note = [APPLE_MAKER_NOTE_HEADER, &[0, 1], b"MM", &[0, 2]].concat();
+        let value_offset = (maker_note.len() + 2 * ENTRY_SIZE + 4) as u32;
+        // タグ1（SLONG）とタグ0x11（ASCII、値はIFDの後ろ）
+        maker_note.extend([0, 1, 0, 9, 0, 0, 0, 1, 0, 0, 0, 7]);
+        maker_note.extend([0, 0x11, 0, 2]);
+        maker_note.extend((identifier.len() as u32).to_be_bytes());
+        maker_note.extend(value_offset.to_be_bytes());
+        maker_note.extend([0; 4]);
+        maker_note.extend(identifier);
+
+        assert_eq!(
+            parse_apple(&maker_note).content_identifier.as_deref(),
+            Some("5C1C7A0E-2B1D-4E4A-9D0B-1F2E3D4C5B6A")
+        );
+        assert_eq!(
+            parse_apple(b"Nikon\0\x02\x10\0\0"),
+            MakerNoteInfo::default()
+        );
+    }
+
+    #[test]
+    fn test_parse_canon_and_sony() {
+        // TIFFヘッダー + MakerNote（IFD: CameraSettings, ShotInfo）+ 値
+        let header = b"II*\0\x08\0\0\0".to_vec();
+        let maker_note_offset = header.len();
+        let values_offset = (maker_note_offset + 2 + ENTRY_SIZE * 2 + 4) as u32;
+        let camera_settings: Vec<u16> = vec![12, 0, 0, 0, 0, 1];
+        let shot_info: Vec<u16> = vec![20, 0, 0, 0, 0, 0, 0, 0, 0, 3];
+        let shot_info_offset = values_offset + (camera_settings.len() * 2) as u32;
+
+        let tiff = [
+            header,
+            2u16.to_le_bytes().to_vec(),
+            entry_le(
+                CANON_CAMERA_SETTINGS_TAG,
+                TYPE_SHORT,
+                camera_settings.len() as u32,
+                values_offset,
+            ),
+            entry_le(
+                CANON_SHOT_INFO_TAG,
+                TYPE_SHORT,
+                shot_info.len() as u32,
+                shot_info_offset,
+            ),
+            vec![0; 4],
+            camera_settings
+                .iter()
+                .chain(&shot_info)
+                .flat_map(|v| v.to_le_bytes())
+                .collect(),
+        ]
+        .concat();
+        assert_eq!(
+            parse(Some("Canon"), &tiff, maker_note_offset).sequence_number,
+            Some(3)
+        );
+
+        let sony = [
+            b"II*\0\x08\0\0\0".as_slice(),
+            SONY_MAKER_NOTE_HEADERS[0],
+            &1u16.to_le_bytes(),
+            &entry_le(SONY_SEQUENCE_NUMBER_TAG, TYPE_SHORT, 1, 2),
+        ]
+        .concat();
+        assert_eq!(parse(Some("SONY"), &sony, 8).sequence_number, Some(2));
+        assert_eq!(parse(Some("Nikon"), &sony, 8).sequence_number, None);
+    }
+}
@@ -1,22 +1,60 @@
 /// 画像の向き検出・修正機能
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use exif::{In, Reader, Tag};
-use image::{self, DynamicImage};
-use img_parts::jpeg::Jpeg;
+use image::codecs::jpeg::JpegEncoder;
+use image::{self, ColorType, DynamicImage, ImageFormat, ImageOutputFormat};
+use img_parts::jpeg::{markers, Jpeg, JpegSegment};
+use img_parts::png::{Png, PngChunk};
 use img_parts::{Bytes, ImageEXIF};
 use serde::{Deserialize, Serialize};
 use std::fs;
+use std::io::Cursor;
+use std::ops::Range;
 use std::path::Path;
 
+use crate::similarity;
+use crate::tiff::{Ifd, Tiff};
+
+/// JPEGのAPP1セグメントでEXIFを示す識別子
+const EXIF_PREFIX: &[u8] = b"Exif\0\0";
+
+/// PNGのEXIFチャンク
+const CHUNK_EXIF: [u8; 4] = *b"eXIf";
+
+/// 回転後のPNGへ引き継ぐ補助チャンク（カラープロファイル・EXIF・テキスト・解像度など）
+const PNG_METADATA_CHUNKS: [[u8; 4]; 10] = [
+    *b"iCCP", *b"sRGB", *b"gAMA", *b"cHRM", *b"pHYs", *b"eXIf", *b"tEXt", *b"zTXt", *b"iTXt", *b"tIME",
+];
+
+/// IJG標準の輝度量子化テーブル（品質50相当）
+const STD_LUMINANCE_QUANT_TABLE: [u32; 64] = [
+    16, 11, 10, 16, 24, 40, 51, 61, 12, 12, 14, 19, 26, 58, 60, 55, 14, 13, 16, 24, 40, 57, 69, 56, 14,
+    17, 22, 29, 51, 87, 80, 62, 18, 22, 37, 56, 68, 109, 103, 77, 24, 35, 55, 64, 81, 104, 113, 92, 49,
+    64, 78, 87, 103, 121, 120, 101, 72, 92, 95, 98, 112, 100, 103, 99,
+];
+
+/// 元の量子化テーブルが読めない場合に使うJPEG品質
+const DEFAULT_JPEG_QUALITY: u8 = 95;
+
 /// 画像の向き（EXIF Orientation値）
+///
+/// 各値は、保存されている画素を正しい向きで表示するために必要な変換を表します。
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Orientation {
     /// 1: 正常（回転不要）
     Normal,
+    /// 2: 左右反転
+    FlipHorizontal,
     /// 3: 180度回転
     Rotate180,
+    /// 4: 上下反転
+    FlipVertical,
+    /// 5: 左上-右下の対角線で反転（90度時計回りに回転してから左右反転）
+    Transpose,
     /// 6: 90度時計回りに回転（右に90度）
     Rotate90CW,
+    /// 7: 右上-左下の対角線で反転（90度反時計回りに回転してから左右反転）
+    Transverse,
     /// 8: 90度反時計回りに回転（左に90度）
     Rotate90CCW,
     /// その他/不明
@@ -27,23 +65,167 @@ impl From<u32> for Orientation {
     fn from(value: u32) -> Self {
         match value {
             1 => Orientation::Normal,
+            2 => Orientation::FlipHorizontal,
             3 => Orientation::Rotate180,
+            4 => Orientation::FlipVertical,
+            5 => Orientation::Transpose,
             6 => Orientation::Rotate90CW,
+            7 => Orientation::Transverse,
             8 => Orientation::Rotate90CCW,
             _ => Orientation::Unknown,
         }
     }
 }
 
+impl Orientation {
+    /// rotation_mode の文字列から変換を取得（"exif" と "none" は呼び出し側で扱う）
+    pub fn from_mode(mode: &str) -> Option<Self> {
+        match mode {
+            "90" => Some(Orientation::Rotate90CW),
+            "180" => Some(Orientation::Rotate180),
+            "270" => Some(Orientation::Rotate90CCW),
+            "flip-horizontal" => Some(Orientation::FlipHorizontal),
+            "flip-vertical" => Some(Orientation::FlipVertical),
+            "transpose" => Some(Orientation::Transpose),
+            "transverse" => Some(Orientation::Transverse),
+            _ => None,
+        }
+    }
+
+    /// EXIF Orientation値（不明な場合は1）
+    pub fn exif_value(self) -> u16 {
+        match self {
+            Orientation::Normal | Orientation::Unknown => 1,
+            Orientation::FlipHorizontal => 2,
+            Orientation::Rotate180 => 3,
+            Orientation::FlipVertical => 4,
+            Orientation::Transpose => 5,
+            Orientation::Rotate90CW => 6,
+            Orientation::Transverse => 7,
+            Orientation::Rotate90CCW => 8,
+        }
+    }
+
+    /// 画素の変換が必要かどうか
+    pub fn is_identity(self) -> bool {
+        matches!(self, Orientation::Normal | Orientation::Unknown)
+    }
+
+    /// 幅と高さが入れ替わる変換（90度/270度回転と転置）かどうか
+    pub fn swaps_dimensions(self) -> bool {
+        self.dihedral().0 % 2 == 1
+    }
+
+    /// この変換の後に `next` を適用した合成変換
+    pub fn then(self, next: Orientation) -> Orientation {
+        let (a, flip_a) = self.dihedral();
+        let (b, flip_b) = next.dihedral();
+        // 左右反転の後に回転を適用すると回転の向きが逆になる（F・R = R⁻¹・F）
+        let rotation = if flip_b { b + 4 - a } else { a + b } % 4;
+        Self::from_dihedral(rotation, flip_a != flip_b)
+    }
+
+    /// 「左右反転（任意）→ 時計回りに90度×n回転」の形に分解
+    fn dihedral(self) -> (u8, bool) {
+        match self {
+            Orientation::Normal | Orientation::Unknown => (0, false),
+            Orientation::Rotate90CW => (1, false),
+            Orientation::Rotate180 => (2, false),
+            Orientation::Rotate90CCW => (3, false),
+            Orientation::FlipHorizontal => (0, true),
+            Orientation::Transverse => (1, true),
+            Orientation::FlipVertical => (2, true),
+            Orientation::Transpose => (3, true),
+        }
+    }
+
+    fn from_dihedral(rotation: u8, flip: bool) -> Self {
+        match (rotation % 4, flip) {
+            (0, false) => Orientation::Normal,
+            (1, false) => Orientation::Rotate90CW,
+            (2, false) => Orientation::Rotate180,
+            (3, false) => Orientation::Rotate90CCW,
+            (0, true) => Orientation::FlipHorizontal,
+            (1, true) => Orientation::Transverse,
+            (2, true) => Orientation::FlipVertical,
+            _ => Orientation::Transpose,
+        }
+    }
+}
+
+impl std::fmt::Display for Orientation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            Orientation::Normal => "none",
+            Orientation::FlipHorizontal => "flip horizontal",
+            Orientation::Rotate180 => "180°",
+            Orientation::FlipVertical => "flip vertical",
+            Orientation::Transpose => "transpose",
+            Orientation::Rotate90CW => "90°",
+            Orientation::Transverse => "transverse",
+            Orientation::Rotate90CCW => "270°",
+            Orientation::Unknown => "unknown",
+        };
+        f.write_str(label)
+    }
+}
+
+/// 向きの判定元
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OrientationSource {
+    /// EXIF Orientationタグ
+    Exif,
+    /// 画像の内容からの推定
+    Detected,
+    /// 情報なし（正常とみなす）
+    #[default]
+    Unspecified,
+}
+
 /// 画像の向き情報
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OrientationInfo {
-    /// EXIF Orientation値
+    /// EXIF Orientation値（推定の場合は正しい向きにするための変換）
     pub orientation: Orientation,
     /// 修正が必要かどうか
     pub needs_correction: bool,
+    /// 判定の確からしさ（0.0〜1.0、EXIFタグは1.0）
+    #[serde(default)]
+    pub confidence: f32,
+    /// 判定元
+    #[serde(default)]
+    pub source: OrientationSource,
+}
+
+impl OrientationInfo {
+    /// 向きの情報が無い場合（正常とみなす）
+    fn unspecified() -> Self {
+        Self {
+            orientation: Orientation::Normal,
+            needs_correction: false,
+            confidence: 0.0,
+            source: OrientationSource::Unspecified,
+        }
+    }
 }
 
+/// 自動検出で回転を提案する最小の確からしさ
+pub const MIN_DETECTION_CONFIDENCE: f32 = 0.6;
+
+/// 自動検出の回転候補（正しい向きにするための変換）
+const DETECTION_CANDIDATES: [Orientation; 4] = [
+    Orientation::Normal,
+    Orientation::Rotate90CW,
+    Orientation::Rotate180,
+    Orientation::Rotate90CCW,
+];
+
+/// 候補間のスコア差を確からしさに変換する際の鋭さ
+const DETECTION_SHARPNESS: f32 = 5.0;
+
+/// 解析用に縮小する最大サイズ（ピクセル）
+const DETECTION_SIZE: u32 = 64;
+
 /// EXIF情報から画像の向きを取得
 pub fn get_orientation(path: &Path) -> Result<OrientationInfo> {
     let file = fs::File::open(path)?;
@@ -52,12 +234,7 @@ pub fn get_orientation(path: &Path) -> Result<OrientationInfo> {
     let exifreader = Reader::new();
     let exif = match exifreader.read_from_container(&mut bufreader) {
         Ok(exif) => exif,
-        Err(_) => {
-            return Ok(OrientationInfo {
-                orientation: Orientation::Normal,
-                needs_correction: false,
-            });
-        }
+        Err(_) => return Ok(OrientationInfo::unspecified()),
     };
 
     // Orientation タグを取得
@@ -70,25 +247,122 @@ pub fn get_orientation(path: &Path) -> Result<OrientationInfo> {
                 return Ok(OrientationInfo {
                     orientation,
                     needs_correction,
+                    confidence: 1.0,
+                    source: OrientationSource::Exif,
                 });
             }
         }
     }
 
     // Orientationタグがない場合は正常とみなす
-    Ok(OrientationInfo {
-        orientation: Orientation::Normal,
-        needs_correction: false,
-    })
+    Ok(OrientationInfo::unspecified())
 }
 
-/// 画像を向きに応じて回転
-#[allow(dead_code)]
+/// 画像ファイルの内容から向きを推定（EXIF Orientationが無い写真用）
+pub fn detect_orientation_file(path: &Path) -> Result<OrientationInfo> {
+    Ok(detect_orientation(&similarity::load_preview(path)?))
+}
+
+/// 画像の内容から向きを推定
+///
+/// CPUのみで動く簡易的な解析です。90度単位の各回転候補について
+/// 「上ほど明るく青い（空）」「下ほど細部が多い（地面）」という傾向を評価し、
+/// 最も自然な候補を選びます。候補間の差が小さい場合は確からしさが低くなり、修正は提案しません。
+pub fn detect_orientation(img: &DynamicImage) -> OrientationInfo {
+    let preview = DynamicImage::ImageRgb8(img.thumbnail(DETECTION_SIZE, DETECTION_SIZE).to_rgb8());
+
+    let scores: Vec<f32> = DETECTION_CANDIDATES
+        .iter()
+        .map(|&candidate| upright_score(&correct_orientation(preview.clone(), candidate).to_rgb8()))
+        .collect();
+
+    // ソフトマックスで各候補の確からしさに変換
+    let max = scores.iter().cloned().fold(f32::MIN, f32::max);
+    let weights: Vec<f32> = scores
+        .iter()
+        .map(|score| ((score - max) * DETECTION_SHARPNESS).exp())
+        .collect();
+    let total: f32 = weights.iter().sum();
+    let (best, weight) = weights
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(b.1))
+        .map(|(i, w)| (i, *w))
+        .unwrap_or((0, 1.0));
+
+    let orientation = DETECTION_CANDIDATES[best];
+    let confidence = weight / total;
+    OrientationInfo {
+        orientation,
+        needs_correction: orientation != Orientation::Normal && confidence >= MIN_DETECTION_CONFIDENCE,
+        confidence,
+        source: OrientationSource::Detected,
+    }
+}
+
+/// 画像の上端・下端の帯の特徴量
+struct BandStats {
+    /// 平均輝度（0.0〜1.0）
+    brightness: f32,
+    /// 青みの強さ（青 - 赤緑の平均）
+    blueness: f32,
+    /// 隣接画素との輝度差の平均（細部の多さ）
+    texture: f32,
+}
+
+/// 正しい向きの写真らしさを評価（大きいほど上下が自然）
+fn upright_score(img: &image::RgbImage) -> f32 {
+    let (width, height) = img.dimensions();
+    if width < 3 || height < 3 {
+        return 0.0;
+    }
+
+    let band = height / 3;
+    let top = band_stats(img, 0..band);
+    let bottom = band_stats(img, height - band..height);
+
+    (top.brightness - bottom.brightness) + 1.5 * (top.blueness - bottom.blueness) + (bottom.texture - top.texture)
+}
+
+fn band_stats(img: &image::RgbImage, rows: Range<u32>) -> BandStats {
+    let (width, height) = img.dimensions();
+    let luma = |x: u32, y: u32| {
+        let [r, g, b] = img.get_pixel(x, y).0;
+        (0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32) / 255.0
+    };
+
+    let (mut brightness, mut blueness, mut texture) = (0.0, 0.0, 0.0);
+    let mut count = 0.0;
+    for y in rows {
+        for x in 0..width {
+            let [r, g, b] = img.get_pixel(x, y).0;
+            let here = luma(x, y);
+            brightness += here;
+            blueness += (b as f32 - (r as f32 + g as f32) / 2.0) / 255.0;
+            let right = if x + 1 < width { luma(x + 1, y) } else { here };
+            let below = if y + 1 < height { luma(x, y + 1) } else { here };
+            texture += (here - right).abs() + (here - below).abs();
+            count += 1.0;
+        }
+    }
+
+    BandStats {
+        brightness: brightness / count,
+        blueness: blueness / count,
+        texture: texture / count,
+    }
+}
+
+/// 画像を向きに応じて回転・反転
 pub fn correct_orientation(img: DynamicImage, orientation: Orientation) -> DynamicImage {
     match orientation {
         Orientation::Normal => img,
-        Orientation::Rotate90CW => img.rotate90(),
+        Orientation::FlipHorizontal => img.fliph(),
         Orientation::Rotate180 => img.rotate180(),
+        Orientation::FlipVertical => img.flipv(),
+        Orientation::Transpose => img.rotate90().fliph(),
+        Orientation::Rotate90CW => img.rotate90(),
+        Orientation::Transverse => img.rotate270().fliph(),
         Orientation::Rotate90CCW => img.rotate270(),
         Orientation::Unknown => img,
     }
@@ -117,113 +391,835 @@ pub fn correct_image_file(input_path: &Path, output_path: &Path) -> Result<bool>
     Ok(true)
 }
 
+/// 画像ファイルに向きの変換（回転・反転）を適用して上書き保存
+///
+/// 画素は再エンコードしますが、JPEGは元の量子化テーブルから推定した品質で保存し、
+/// APPnセグメント（EXIF/XMP/ICC/IPTCなど）とコメントを元のファイルからそのまま書き戻します。
+/// JFIF（APP0）とAdobe（APP14）は画素の符号化に関わるため、エンコーダーが出力したものを使います。
+/// PNGは色空間・EXIF・テキストなどの補助チャンクを書き戻します。
+/// メタデータを引き継げないその他の形式と、CMYKなど1/3チャンネル以外のJPEGはエラーになります。
+pub fn rotate_image_file(image_path: &Path, transform: Orientation) -> Result<()> {
+    let original = fs::read(image_path).context("Failed to read image for rotation")?;
+    let format = image::guess_format(&original).context("Unsupported image format")?;
+    if !matches!(format, ImageFormat::Jpeg | ImageFormat::Png) {
+        bail!("Rotating {:?} images would drop their metadata", format);
+    }
+    let img = image::load_from_memory_with_format(&original, format)
+        .context("Failed to decode image for rotation")?;
+
+    let rotated = correct_orientation(img, transform);
+
+    let bytes = match format {
+        ImageFormat::Jpeg => encode_jpeg_with_metadata(&rotated, original.into())?,
+        _ => encode_png_with_metadata(&rotated, original.into())?,
+    };
+
+    replace_file(image_path, &bytes).context("Failed to write rotated image")?;
+    Ok(())
+}
+
+/// 同じフォルダの一時ファイルに書き込んでから置き換える
+///
+/// 移動では出力先が唯一のコピーになるため、書き込みの途中で失敗しても元の内容が残るようにします。
+fn replace_file(path: &Path, bytes: &[u8]) -> Result<()> {
+    let file_name = path.file_name().context("Invalid image path")?.to_string_lossy();
+    let temp_path = path.with_file_name(format!(".{}.{}.tmp", file_name, std::process::id()));
+
+    let written = fs::write(&temp_path, bytes)
+        .and_then(|_| fs::set_permissions(&temp_path, fs::metadata(path)?.permissions()))
+        .and_then(|_| fs::rename(&temp_path, path));
+    if let Err(e) = written {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+/// 回転後の画像をJPEGにエンコードし、元のメタデータセグメントを書き戻す
+fn encode_jpeg_with_metadata(img: &DynamicImage, original: Bytes) -> Result<Bytes> {
+    let source = Jpeg::from_bytes(original).context("Failed to parse JPEG structure")?;
+    // CMYK/YCCKはRGBで再エンコードされ、元のICCプロファイル（CMYK）と合わなくなる
+    match jpeg_component_count(&source) {
+        Some(1 | 3) => {}
+        Some(count) => bail!("JPEG with {} color components cannot be rotated without changing its colors", count),
+        None => bail!("JPEG has no frame header"),
+    }
+    let quality = estimate_jpeg_quality(&source).unwrap_or(DEFAULT_JPEG_QUALITY);
+
+    let mut encoded = Vec::new();
+    let mut encoder = JpegEncoder::new_with_quality(&mut encoded, quality);
+    match img {
+        DynamicImage::ImageLuma8(luma) => {
+            encoder.encode(luma.as_raw(), luma.width(), luma.height(), ColorType::L8)?
+        }
+        _ => {
+            let rgb = img.to_rgb8();
+            encoder.encode(rgb.as_raw(), rgb.width(), rgb.height(), ColorType::Rgb8)?
+        }
+    }
+
+    let mut rotated = Jpeg::from_bytes(encoded.into()).context("Failed to parse re-encoded JPEG")?;
+    let metadata: Vec<JpegSegment> = source
+        .segments()
+        .iter()
+        .filter(|segment| is_metadata_segment(segment.marker()))
+        .cloned()
+        .collect();
+
+    // エンコーダーが付けたJFIFヘッダーの直後に元のセグメントを挿入する
+    let segments = rotated.segments_mut();
+    segments.retain(|segment| !is_metadata_segment(segment.marker()));
+    let index = segments
+        .iter()
+        .take_while(|segment| matches!(segment.marker(), markers::APP0 | markers::APP14))
+        .count();
+    segments.splice(index..index, metadata);
+
+    Ok(rotated.encoder().bytes())
+}
+
+/// APPnとCOMはメタデータとして引き継ぐ（エンコーダーが作り直すJFIFとAdobeは除く）
+fn is_metadata_segment(marker: u8) -> bool {
+    match marker {
+        markers::APP0 | markers::APP14 => false,
+        marker => (markers::APP1..=markers::APP15).contains(&marker) || marker == markers::COM,
+    }
+}
+
+/// フレームヘッダー（SOFn）の色成分の数（グレースケールは1、YCbCrは3、CMYK/YCCKは4）
+fn jpeg_component_count(jpeg: &Jpeg) -> Option<u8> {
+    jpeg.segments()
+        .iter()
+        .find(|segment| {
+            (markers::SOF0..=markers::SOF15).contains(&segment.marker())
+                && !matches!(segment.marker(), markers::DHT | markers::JPG | markers::DAC)
+        })?
+        .contents()
+        .get(5)
+        .copied()
+}
+
+/// 輝度の量子化テーブルからIJG相当の品質（1〜100）を推定
+fn estimate_jpeg_quality(jpeg: &Jpeg) -> Option<u8> {
+    let contents = jpeg.segment_by_marker(markers::DQT)?.contents();
+    let (&precision_and_id, table) = contents.split_first()?;
+
+    // 上位4ビットが精度（0: 8bit, 1: 16bit）、下位4ビットがテーブル番号
+    let sum: u32 = if precision_and_id >> 4 == 0 {
+        table.get(..64)?.iter().map(|&v| v as u32).sum()
+    } else {
+        table
+            .get(..128)?
+            .chunks(2)
+            .map(|v| u16::from_be_bytes([v[0], v[1]]) as u32)
+            .sum()
+    };
+
+    let std_sum: u32 = STD_LUMINANCE_QUANT_TABLE.iter().sum();
+    let scale = (sum * 100 + std_sum / 2) / std_sum;
+    let quality = if scale <= 100 {
+        (200 - scale) / 2
+    } else {
+        5000 / scale
+    };
+    Some(quality.clamp(1, 100) as u8)
+}
+
+/// 回転後の画像をPNGにエンコードし、元の補助チャンクを書き戻す
+fn encode_png_with_metadata(img: &DynamicImage, original: Bytes) -> Result<Bytes> {
+    let source = Png::from_bytes(original).context("Failed to parse PNG structure")?;
+
+    let mut encoded = Cursor::new(Vec::new());
+    img.write_to(&mut encoded, ImageOutputFormat::Png)?;
+    let mut rotated =
+        Png::from_bytes(encoded.into_inner().into()).context("Failed to parse re-encoded PNG")?;
+
+    let metadata: Vec<PngChunk> = source
+        .chunks()
+        .iter()
+        .filter(|chunk| PNG_METADATA_CHUNKS.contains(&chunk.kind()))
+        .cloned()
+        .collect();
+
+    // 色空間系のチャンクはIDATより前に置く必要があるため、IHDRの直後に挿入する
+    let chunks = rotated.chunks_mut();
+    chunks.retain(|chunk| !PNG_METADATA_CHUNKS.contains(&chunk.kind()));
+    chunks.splice(1..1, metadata);
+
+    Ok(rotated.encoder().bytes())
+}
+
 /// 画像ファイルのEXIF Orientationを1（Normal）にリセット
 ///
 /// 画像を物理的に回転させた後、EXIF Orientationフィールドを1（正常）に上書きします。
 /// これにより、画像ビューアーで二重に回転されることを防ぎます。
-pub fn reset_exif_orientation(image_path: &Path) -> Result<()> {
-    // JPEGファイルのみ対応（PNGにはEXIF Orientationがないことが多い）
-    let extension = image_path
-        .extension()
-        .and_then(|s| s.to_str())
-        .unwrap_or("");
+/// サムネイル（IFD1）の画素は回転されないため、`applied` の向きをサムネイル側に設定します。
+/// 90度単位の回転で幅と高さが入れ替わった場合は、PixelXDimension/PixelYDimensionも入れ替えます。
+pub fn reset_exif_orientation(image_path: &Path, applied: Orientation) -> Result<()> {
+    edit_exif(image_path, &mut |tiff| {
+        if applied.swaps_dimensions() {
+            tiff.swap_pixel_dimensions()?;
+        }
+        update_tiff_orientation(tiff, 1, Some(applied.exif_value()))
+    })?;
+    Ok(())
+}
 
-    if !matches!(extension.to_lowercase().as_str(), "jpg" | "jpeg") {
-        // JPEG以外はスキップ（エラーではない）
-        return Ok(());
+/// 画像ファイルのEXIF Orientationを書き換える
+///
+/// JPEG（APP1）、PNG（eXIf）、TIFF、HEIF（Exifアイテム）に対応します。
+/// IFDを解析してOrientationエントリを書き換え、タグが無い場合は追加します。
+/// ただしHEIFは既存のOrientationエントリの書き換えのみで、タグの追加には対応していません（エラー）。
+/// `thumbnail` はIFD1（サムネイル）がある場合のみ書き換えます。
+pub fn write_exif_orientation(image_path: &Path, primary: u16, thumbnail: Option<u16>) -> Result<()> {
+    // EXIFを持てない形式はスキップ（エラーではない）
+    edit_exif(image_path, &mut |tiff| update_tiff_orientation(tiff, primary, thumbnail))?;
+    Ok(())
+}
+
+/// 既存のEXIF Orientationに変換を合成して書き換え、合成後の向きを返す
+///
+/// 画素はデコード・再エンコードせず、ビューアーが表示時に適用する向きだけを変更します。
+/// サムネイル（IFD1）がある場合は、その向きにも同じ変換を合成します。
+pub fn compose_exif_orientation(image_path: &Path, transform: Orientation) -> Result<Orientation> {
+    let read = |tiff: &Tiff, ifd: Ifd| tiff.orientation(ifd).map(|v| Orientation::from(v as u32));
+
+    let mut composed = Orientation::Normal;
+    let supported = edit_exif(image_path, &mut |tiff| {
+        let current = read(tiff, Ifd::Primary).unwrap_or(Orientation::Normal);
+        composed = current.then(transform);
+        let thumbnail = tiff
+            .has_ifd(Ifd::Thumbnail)
+            .then(|| read(tiff, Ifd::Thumbnail).unwrap_or(current).then(transform).exif_value());
+        update_tiff_orientation(tiff, composed.exif_value(), thumbnail)
+    })?;
+
+    if !supported {
+        bail!("This image format cannot store an EXIF orientation");
     }
+    Ok(composed)
+}
+
+/// 画像ファイルのEXIF（TIFF構造）を編集して書き戻す
+///
+/// EXIFが無い場合は空のEXIFに対して編集を行い、向きが正常（1）のままなら何も書き込みません。
+/// EXIFを持てない形式の場合は `false` を返します。
+fn edit_exif(image_path: &Path, edit: &mut dyn FnMut(&mut Tiff) -> Result<()>) -> Result<bool> {
+    let bytes = fs::read(image_path).context("Failed to read image for EXIF update")?;
 
-    // JPEGファイルを読み込み
-    let jpeg_bytes = fs::read(image_path)
-        .context("Failed to read JPEG file for EXIF reset")?;
+    let updated = match ExifContainer::detect(&bytes) {
+        Some(ExifContainer::Jpeg) => edit_jpeg_exif(bytes.into(), edit)?,
+        Some(ExifContainer::Png) => edit_png_exif(bytes.into(), edit)?,
+        Some(ExifContainer::Tiff) => {
+            let mut tiff = Tiff::parse(bytes)?;
+            edit(&mut tiff)?;
+            Some(tiff.into_bytes())
+        }
+        Some(ExifContainer::Heif) => Some(edit_heif_exif(bytes, edit)?),
+        None => return Ok(false),
+    };
+
+    if let Some(updated) = updated {
+        replace_file(image_path, &updated).context("Failed to write image with updated EXIF orientation")?;
+    }
+    Ok(true)
+}
 
-    let mut jpeg = Jpeg::from_bytes(jpeg_bytes.into())
-        .context("Failed to parse JPEG structure")?;
+/// EXIFが無いファイル用に、空のEXIFを編集して結果を返す（向きが正常ならNone）
+fn edit_new_exif(edit: &mut dyn FnMut(&mut Tiff) -> Result<()>) -> Result<Option<Tiff>> {
+    let mut tiff = Tiff::with_orientation(1);
+    edit(&mut tiff)?;
+    Ok((tiff.orientation(Ifd::Primary) != Some(1)).then_some(tiff))
+}
 
-    // EXIFセグメントを取得
-    if let Some(exif_segment) = jpeg.exif() {
-        // EXIF データを取得（Bytes型をVec<u8>に変換）
-        let exif_data = exif_segment.to_vec();
+/// EXIFを格納できる画像コンテナ
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExifContainer {
+    Jpeg,
+    Png,
+    Tiff,
+    Heif,
+}
 
-        // EXIF ヘッダーをスキップ（"Exif\0\0" = 6バイト）
-        if exif_data.len() < 6 {
-            // EXIFデータが短すぎる場合はスキップ
-            return Ok(());
+impl ExifContainer {
+    /// 先頭のシグネチャから判定（拡張子には依存しない）
+    fn detect(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(&[0xFF, 0xD8]) {
+            Some(Self::Jpeg)
+        } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(Self::Png)
+        } else if bytes.starts_with(b"II\x2a\x00") || bytes.starts_with(b"MM\x00\x2a") {
+            Some(Self::Tiff)
+        } else if bytes.get(4..8) == Some(b"ftyp") {
+            Some(Self::Heif)
+        } else {
+            None
         }
+    }
+}
 
-        // TIFFヘッダー以降を取得
-        let tiff_data = &exif_data[6..];
+/// TIFF構造のOrientationを書き換える
+///
+/// 値が1（正常）でタグも無い場合は、意味が変わらないためタグを追加しません。
+fn update_tiff_orientation(tiff: &mut Tiff, primary: u16, thumbnail: Option<u16>) -> Result<()> {
+    if primary != 1 || tiff.orientation(Ifd::Primary).is_some() {
+        tiff.set_orientation(Ifd::Primary, primary)?;
+    }
+    if let Some(value) = thumbnail.filter(|_| tiff.has_ifd(Ifd::Thumbnail)) {
+        if value != 1 || tiff.orientation(Ifd::Thumbnail).is_some() {
+            tiff.set_orientation(Ifd::Thumbnail, value)?;
+        }
+    }
+    Ok(())
+}
 
-        // バイトオーダーを確認（"II" = Little Endian, "MM" = Big Endian）
-        if tiff_data.len() < 2 {
-            return Ok(());
+/// JPEGのEXIFセグメント（APP1）を編集する
+fn edit_jpeg_exif(bytes: Bytes, edit: &mut dyn FnMut(&mut Tiff) -> Result<()>) -> Result<Option<Vec<u8>>> {
+    let mut jpeg = Jpeg::from_bytes(bytes).context("Failed to parse JPEG structure")?;
+
+    let existing = jpeg.segments().iter().position(|segment| {
+        segment.marker() == markers::APP1 && segment.contents().starts_with(EXIF_PREFIX)
+    });
+
+    let tiff = match existing {
+        Some(index) => {
+            let mut tiff = Tiff::parse(jpeg.segments()[index].contents()[EXIF_PREFIX.len()..].to_vec())?;
+            edit(&mut tiff)?;
+            tiff
+        }
+        // EXIFが無く、正常な向きならそのまま
+        None => match edit_new_exif(edit)? {
+            Some(tiff) => tiff,
+            None => return Ok(None),
+        },
+    };
+
+    let mut contents = EXIF_PREFIX.to_vec();
+    contents.extend_from_slice(&tiff.into_bytes());
+    // セグメント長（2バイト）に収まる必要がある
+    if contents.len() > u16::MAX as usize - 2 {
+        bail!("EXIF segment would exceed the JPEG segment size limit");
+    }
+    let segment = JpegSegment::new_with_contents(markers::APP1, contents.into());
+
+    let segments = jpeg.segments_mut();
+    match existing {
+        Some(index) => segments[index] = segment,
+        None => {
+            // JFIFヘッダー（APP0）があればその直後に置く
+            let index = usize::from(segments.first().map(|s| s.marker()) == Some(markers::APP0));
+            segments.insert(index, segment);
         }
+    }
+
+    Ok(Some(jpeg.encoder().bytes().to_vec()))
+}
+
+/// PNGのeXIfチャンクを編集する
+fn edit_png_exif(bytes: Bytes, edit: &mut dyn FnMut(&mut Tiff) -> Result<()>) -> Result<Option<Vec<u8>>> {
+    let mut png = Png::from_bytes(bytes).context("Failed to parse PNG structure")?;
+
+    let tiff = match png.exif() {
+        Some(exif) => {
+            let mut tiff = Tiff::parse(exif.to_vec())?;
+            edit(&mut tiff)?;
+            tiff
+        }
+        None => match edit_new_exif(edit)? {
+            Some(tiff) => tiff,
+            None => return Ok(None),
+        },
+    };
+
+    // eXIfはIDATより前に置く（既存の位置があればそこを維持）
+    let chunk = PngChunk::new(CHUNK_EXIF, tiff.into_bytes().into());
+    let chunks = png.chunks_mut();
+    match chunks.iter().position(|c| c.kind() == CHUNK_EXIF) {
+        Some(index) => chunks[index] = chunk,
+        None => chunks.insert(1, chunk),
+    }
+
+    Ok(Some(png.encoder().bytes().to_vec()))
+}
+
+/// HEIFのExifアイテムを編集する
+///
+/// アイテムの位置情報（iloc）を書き換えずに済むよう、既存のエントリのその場での更新のみ行います。
+/// Exifアイテムの大きさが変わる編集（Orientationタグの追加など）には対応していません。
+/// HEIFのビューアーは表示の向きをirot/imirボックスで決めるため、ここでの変更は表示には反映されません
+/// （メタデータ方式の回転ではHEIFを対象外にしています）。
+fn edit_heif_exif(mut bytes: Vec<u8>, edit: &mut dyn FnMut(&mut Tiff) -> Result<()>) -> Result<Vec<u8>> {
+    let range = heif_exif_range(&bytes).context("HEIF file has no Exif item")?;
+    let mut tiff = Tiff::parse(bytes[range.clone()].to_vec())?;
+    edit(&mut tiff)?;
+
+    let updated = tiff.into_bytes();
+    if updated.len() != range.len() {
+        bail!("Adding tags to a HEIF Exif item is not supported (no Orientation entry to update)");
+    }
+    bytes[range].copy_from_slice(&updated);
+    Ok(bytes)
+}
+
+/// HEIFのExifアイテムのうち、TIFFヘッダーから始まる範囲を探す
+fn heif_exif_range(data: &[u8]) -> Option<Range<usize>> {
+    let meta = iso_boxes(data, 0..data.len()).find(|(kind, _)| kind == b"meta")?.1;
+    // metaはFullBox（version + flags）
+    let children = meta.start + 4..meta.end;
 
-        let is_little_endian = &tiff_data[0..2] == b"II";
+    let iinf = iso_boxes(data, children.clone()).find(|(kind, _)| kind == b"iinf")?.1;
+    let version = *data.get(iinf.start)?;
+    let entries_start = iinf.start + if version == 0 { 6 } else { 8 };
+    let item_id = iso_boxes(data, entries_start..iinf.end)
+        .filter(|(kind, _)| kind == b"infe")
+        .find_map(|(_, infe)| {
+            let version = *data.get(infe.start)?;
+            let (id, id_len) = match version {
+                2 => (read_be(data, infe.start + 4, 2)?, 2),
+                3 => (read_be(data, infe.start + 4, 4)?, 4),
+                _ => return None,
+            };
+            let kind = data.get(infe.start + 4 + id_len + 2..infe.start + 4 + id_len + 6)?;
+            (kind == b"Exif").then_some(id)
+        })?;
 
-        // Orientation タグを探して書き換え
-        // タグ 0x0112 (274) = Orientation
-        // 型: SHORT (3), カウント: 1, 値: 1
-        let mut modified_data = exif_data.to_vec();
+    let iloc = iso_boxes(data, children).find(|(kind, _)| kind == b"iloc")?.1;
+    let (offset, length) = iloc_extent(data, iloc, item_id)?;
 
-        // 簡易実装：TIFFヘッダーを解析してOrientationタグを探し、値を1に変更
-        // より堅牢な実装にするには、TIFFフォーマットを完全にパースする必要があります
-        // ここでは、既存のOrientationタグが見つかった場合のみ書き換えます
+    // Exifアイテムの先頭4バイトはTIFFヘッダーまでのオフセット
+    let start = usize::try_from(offset).ok()?;
+    let end = start.checked_add(usize::try_from(length).ok()?)?;
+    let tiff_start = start + 4 + usize::try_from(read_be(data, start, 4)?).ok()?;
+    (tiff_start < end && end <= data.len()).then_some(tiff_start..end)
+}
+
+/// ilocボックスから指定アイテムの唯一のエクステント（ファイル内オフセット, 長さ）を取得
+fn iloc_extent(data: &[u8], iloc: Range<usize>, item_id: u64) -> Option<(u64, u64)> {
+    let version = *data.get(iloc.start)?;
+    let sizes = *data.get(iloc.start + 4)?;
+    let (offset_size, length_size) = ((sizes >> 4) as usize, (sizes & 0x0F) as usize);
+    let sizes = *data.get(iloc.start + 5)?;
+    let base_offset_size = (sizes >> 4) as usize;
+    let index_size = if version == 1 || version == 2 {
+        (sizes & 0x0F) as usize
+    } else {
+        0
+    };
+
+    let (count, mut pos) = if version < 2 {
+        (read_be(data, iloc.start + 6, 2)?, iloc.start + 8)
+    } else {
+        (read_be(data, iloc.start + 6, 4)?, iloc.start + 10)
+    };
+    let id_size = if version < 2 { 2 } else { 4 };
 
-        let orientation_tag: u16 = 0x0112;
-        let orientation_bytes = if is_little_endian {
-            orientation_tag.to_le_bytes()
+    for _ in 0..count {
+        let id = read_be(data, pos, id_size)?;
+        pos += id_size;
+        let construction_method = if version == 1 || version == 2 {
+            let method = read_be(data, pos, 2)? & 0x0F;
+            pos += 2;
+            method
         } else {
-            orientation_tag.to_be_bytes()
+            0
         };
+        // data_reference_index
+        pos += 2;
+        let base_offset = read_be(data, pos, base_offset_size)?;
+        pos += base_offset_size;
+        let extent_count = read_be(data, pos, 2)?;
+        pos += 2;
 
-        // TIFFデータ内でOrientationタグを検索
-        let mut found = false;
-        for i in 0..tiff_data.len().saturating_sub(12) {
-            if &tiff_data[i..i+2] == &orientation_bytes {
-                // Orientationタグ発見
-                // 値フィールドの位置は タグ(2) + 型(2) + カウント(4) = 8バイト後
-                let value_offset = 6 + i + 8;
-
-                if value_offset + 2 <= modified_data.len() {
-                    // 値を1に設定（SHORT型なので2バイト）
-                    if is_little_endian {
-                        modified_data[value_offset] = 1;
-                        modified_data[value_offset + 1] = 0;
-                    } else {
-                        modified_data[value_offset] = 0;
-                        modified_data[value_offset + 1] = 1;
-                    }
-                    found = true;
-                    break;
-                }
-            }
+        let mut extents = Vec::new();
+        for _ in 0..extent_count {
+            pos += index_size;
+            let offset = read_be(data, pos, offset_size)?;
+            pos += offset_size;
+            let length = read_be(data, pos, length_size)?;
+            pos += length_size;
+            extents.push((offset, length));
         }
 
-        if found {
-            // 修正したEXIFデータを再設定（Bytes型として）
-            jpeg.set_exif(Some(Bytes::from(modified_data)));
-
-            // ファイルに書き戻し
-            fs::write(image_path, jpeg.encoder().bytes())
-                .context("Failed to write JPEG with reset EXIF orientation")?;
+        if id == item_id {
+            // ファイル内に連続して格納されている場合のみ対応
+            return match extents.as_slice() {
+                [(offset, length)] if construction_method == 0 => {
+                    Some((base_offset.checked_add(*offset)?, *length))
+                }
+                _ => None,
+            };
         }
     }
+    None
+}
 
-    Ok(())
+/// ISOBMFFのボックスを列挙（種類, 本体の範囲）
+pub(crate) fn iso_boxes(data: &[u8], range: Range<usize>) -> impl Iterator<Item = ([u8; 4], Range<usize>)> + '_ {
+    let mut pos = range.start;
+    let end = range.end.min(data.len());
+    std::iter::from_fn(move || {
+        let size = read_be(data, pos, 4)?;
+        let kind: [u8; 4] = data.get(pos + 4..pos + 8)?.try_into().ok()?;
+        let (header, size) = match size {
+            0 => (8, (end - pos) as u64),
+            1 => (16, read_be(data, pos + 8, 8)?),
+            size => (8, size),
+        };
+        let box_end = pos.checked_add(usize::try_from(size).ok()?)?;
+        if size < header as u64 || box_end > end {
+            return None;
+        }
+        let body = pos + header..box_end;
+        pos = box_end;
+        Some((kind, body))
+    })
+}
+
+/// ビッグエンディアンの整数（0〜8バイト）を読み取る
+pub(crate) fn read_be(data: &[u8], pos: usize, size: usize) -> Option<u64> {
+    let bytes = data.get(pos..pos.checked_add(size)?)?;
+    Some(bytes.iter().fold(0u64, |acc, &b| (acc << 8) | b as u64))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use image::GenericImageView;
 
     #[test]
     fn test_orientation_from_u32() {
         assert_eq!(Orientation::from(1), Orientation::Normal);
+        assert_eq!(Orientation::from(2), Orientation::FlipHorizontal);
         assert_eq!(Orientation::from(3), Orientation::Rotate180);
+        assert_eq!(Orientation::from(4), Orientation::FlipVertical);
+        assert_eq!(Orientation::from(5), Orientation::Transpose);
         assert_eq!(Orientation::from(6), Orientation::Rotate90CW);
+        assert_eq!(Orientation::from(7), Orientation::Transverse);
         assert_eq!(Orientation::from(8), Orientation::Rotate90CCW);
         assert_eq!(Orientation::from(99), Orientation::Unknown);
+
+        for value in 1..=8 {
+            assert_eq!(Orientation::from(value).exif_value() as u32, value);
+        }
+        assert_eq!(Orientation::from_mode("transverse"), Some(Orientation::Transverse));
+        assert_eq!(Orientation::from_mode("exif"), None);
+    }
+
+    fn temp_file(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("photo_returns_orientation_{}_{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn test_replace_file() {
+        let path = temp_file("replace.jpg");
+        fs::write(&path, b"original").unwrap();
+        replace_file(&path, b"rotated").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"rotated");
+
+        // 一時ファイルは残らない
+        let temp_name = format!(".{}.{}.tmp", path.file_name().unwrap().to_string_lossy(), std::process::id());
+        assert!(!path.with_file_name(temp_name).exists());
+        let _ = fs::remove_file(&path);
+
+        // 置き換え先が無い場合は失敗し、何も作らない
+        assert!(replace_file(&path, b"rotated").is_err());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_rotate_jpeg_preserves_metadata() {
+        let img = DynamicImage::new_rgb8(32, 16);
+        let mut encoded = Vec::new();
+        JpegEncoder::new_with_quality(&mut encoded, 80)
+            .encode(img.as_bytes(), 32, 16, ColorType::Rgb8)
+            .unwrap();
+
+        let mut jpeg = Jpeg::from_bytes(encoded.into()).unwrap();
+        assert_eq!(estimate_jpeg_quality(&jpeg), Some(80));
+
+        let exif = Bytes::from_static(b"Exif\0\0MM\0\x2a\0\0\0\x08\0\0\0\0\0\0");
+        let xmp = Bytes::from_static(b"http://ns.adobe.com/xap/1.0/\0<x:xmpmeta/>");
+        let comment = Bytes::from_static(b"taken on holiday");
+        jpeg.segments_mut().splice(
+            1..1,
+            [
+                JpegSegment::new_with_contents(markers::APP1, exif.clone()),
+                JpegSegment::new_with_contents(markers::APP1, xmp.clone()),
+                JpegSegment::new_with_contents(markers::COM, comment.clone()),
+                JpegSegment::new_with_contents(markers::APP14, Bytes::from_static(b"Adobe\0\x64\0\0\0\0\x01")),
+            ],
+        );
+
+        let path = temp_file("rotate.jpg");
+        fs::write(&path, jpeg.encoder().bytes()).unwrap();
+        rotate_image_file(&path, Orientation::Rotate90CW).unwrap();
+
+        assert_eq!(image::open(&path).unwrap().dimensions(), (16, 32));
+        let rotated = Jpeg::from_bytes(fs::read(&path).unwrap().into()).unwrap();
+        let app1: Vec<_> = rotated
+            .segments_by_marker(markers::APP1)
+            .map(|segment| segment.contents().clone())
+            .collect();
+        assert_eq!(app1, vec![exif, xmp]);
+        assert_eq!(rotated.segment_by_marker(markers::COM).unwrap().contents(), &comment);
+        // Adobeセグメントは引き継がず、エンコーダーのJFIFヘッダーが先頭に残る
+        assert!(rotated.segment_by_marker(markers::APP14).is_none());
+        assert_eq!(rotated.segments()[0].marker(), markers::APP0);
+        assert_eq!(estimate_jpeg_quality(&rotated), Some(80));
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_rotate_rejects_unsupported_images() {
+        // TIFFなどメタデータを引き継げない形式は書き換えない
+        let path = temp_file("rotate.tif");
+        DynamicImage::new_rgb8(8, 4).save(&path).unwrap();
+        let original = fs::read(&path).unwrap();
+        assert!(rotate_image_file(&path, Orientation::Rotate90CW).is_err());
+        assert_eq!(fs::read(&path).unwrap(), original);
+        let _ = fs::remove_file(&path);
+
+        let img = DynamicImage::new_rgb8(8, 4);
+        let mut encoded = Vec::new();
+        JpegEncoder::new(&mut encoded).encode(img.as_bytes(), 8, 4, ColorType::Rgb8).unwrap();
+        let jpeg = Jpeg::from_bytes(encoded.clone().into()).unwrap();
+        assert_eq!(jpeg_component_count(&jpeg), Some(3));
+
+        // CMYK（4成分）のJPEGはICCプロファイルと色が合わなくなるため回転しない
+        let sof = jpeg.segment_by_marker(markers::SOF0).unwrap().contents().clone();
+        let position = encoded.windows(sof.len()).position(|w| w == &sof[..]).unwrap();
+        encoded[position + 5] = 4;
+        let cmyk = Jpeg::from_bytes(encoded.clone().into()).unwrap();
+        assert_eq!(jpeg_component_count(&cmyk), Some(4));
+        assert!(encode_jpeg_with_metadata(&img, encoded.into()).is_err());
+    }
+
+    #[test]
+    fn test_rotate_png_preserves_metadata() {
+        let mut encoded = Cursor::new(Vec::new());
+        DynamicImage::new_rgba8(8, 4)
+            .write_to(&mut encoded, ImageOutputFormat::Png)
+            .unwrap();
+
+        let mut png = Png::from_bytes(encoded.into_inner().into()).unwrap();
+        let text = PngChunk::new(*b"tEXt", Bytes::from_static(b"Author\0kako-jun"));
+        png.chunks_mut().insert(1, text.clone());
+
+        let path = temp_file("rotate.png");
+        fs::write(&path, png.encoder().bytes()).unwrap();
+        rotate_image_file(&path, Orientation::Rotate90CCW).unwrap();
+
+        assert_eq!(image::open(&path).unwrap().dimensions(), (4, 8));
+        let rotated = Png::from_bytes(fs::read(&path).unwrap().into()).unwrap();
+        assert_eq!(rotated.chunk_by_type(*b"tEXt").unwrap().contents(), text.contents());
+        let _ = fs::remove_file(&path);
+    }
+
+    fn sample_jpeg() -> Jpeg {
+        let mut encoded = Vec::new();
+        JpegEncoder::new_with_quality(&mut encoded, 90)
+            .encode(&[0; 8 * 8 * 3], 8, 8, ColorType::Rgb8)
+            .unwrap();
+        Jpeg::from_bytes(encoded.into()).unwrap()
+    }
+
+    fn jpeg_tiff(path: &Path) -> Tiff {
+        let jpeg = Jpeg::from_bytes(fs::read(path).unwrap().into()).unwrap();
+        Tiff::parse(jpeg.exif().unwrap().to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_reset_jpeg_orientation_updates_both_ifds() {
+        // IFD0（Orientation=6）とIFD1（ImageWidthのみ）を持つEXIF
+        let mut tiff = Tiff::with_orientation(6).into_bytes();
+        tiff[22..26].copy_from_slice(&26u32.to_be_bytes());
+        tiff.extend_from_slice(b"\0\x01\x01\0\0\x03\0\0\0\x01\0\xa0\0\0\0\0\0\0");
+        let mut contents = EXIF_PREFIX.to_vec();
+        contents.extend_from_slice(&tiff);
+
+        let mut jpeg = sample_jpeg();
+        jpeg.segments_mut()
+            .insert(1, JpegSegment::new_with_contents(markers::APP1, contents.into()));
+        let path = temp_file("reset.jpg");
+        fs::write(&path, jpeg.encoder().bytes()).unwrap();
+        assert_eq!(get_orientation(&path).unwrap().orientation, Orientation::Rotate90CW);
+
+        reset_exif_orientation(&path, Orientation::Rotate90CW).unwrap();
+        let tiff = jpeg_tiff(&path);
+        assert_eq!(tiff.orientation(Ifd::Primary), Some(1));
+        assert_eq!(tiff.orientation(Ifd::Thumbnail), Some(6));
+        assert_eq!(get_orientation(&path).unwrap().orientation, Orientation::Normal);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_write_orientation_without_exif() {
+        let path = temp_file("no_exif.jpg");
+        fs::write(&path, sample_jpeg().encoder().bytes()).unwrap();
+
+        // 正常な向きならファイルは変更しない
+        let before = fs::read(&path).unwrap();
+        reset_exif_orientation(&path, Orientation::Rotate180).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), before);
+
+        write_exif_orientation(&path, 8, None).unwrap();
+        assert_eq!(jpeg_tiff(&path).orientation(Ifd::Primary), Some(8));
+        assert!(image::open(&path).is_ok());
+        let _ = fs::remove_file(&path);
+
+        let mut encoded = Cursor::new(Vec::new());
+        DynamicImage::new_rgb8(4, 4)
+            .write_to(&mut encoded, ImageOutputFormat::Png)
+            .unwrap();
+        let path = temp_file("no_exif.png");
+        fs::write(&path, encoded.into_inner()).unwrap();
+
+        write_exif_orientation(&path, 3, None).unwrap();
+        let png = Png::from_bytes(fs::read(&path).unwrap().into()).unwrap();
+        assert_eq!(png.chunks()[1].kind(), CHUNK_EXIF);
+        let tiff = Tiff::parse(png.exif().unwrap().to_vec()).unwrap();
+        assert_eq!(tiff.orientation(Ifd::Primary), Some(3));
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_write_heif_orientation() {
+        fn full_box(kind: &[u8], version: u8, body: &[u8]) -> Vec<u8> {
+            let mut data = ((body.len() + 12) as u32).to_be_bytes().to_vec();
+            data.extend_from_slice(kind);
+            data.extend_from_slice(&[version, 0, 0, 0]);
+            data.extend_from_slice(body);
+            data
+        }
+
+        let mut ftyp = b"\0\0\0\x10ftypheic\0\0\0\0".to_vec();
+        let infe = full_box(b"infe", 2, b"\0\x01\0\0Exif\0");
+        let mut iinf_body = 1u16.to_be_bytes().to_vec();
+        iinf_body.extend_from_slice(&infe);
+        let iinf = full_box(b"iinf", 0, &iinf_body);
+
+        let mut payload = 6u32.to_be_bytes().to_vec();
+        payload.extend_from_slice(EXIF_PREFIX);
+        payload.extend_from_slice(&Tiff::with_orientation(6).into_bytes());
+
+        // iloc: offset_size=4, length_size=4, base_offset_size=0, item 1 に1エクステント
+        let iloc_len = 12 + 2 + 2 + 2 + 2 + 2 + 8;
+        let meta_len = 12 + iinf.len() + iloc_len;
+        let payload_offset = ftyp.len() + meta_len + 8;
+        let mut iloc_body = vec![0x44, 0x00];
+        iloc_body.extend_from_slice(&1u16.to_be_bytes());
+        iloc_body.extend_from_slice(&1u16.to_be_bytes());
+        iloc_body.extend_from_slice(&0u16.to_be_bytes());
+        iloc_body.extend_from_slice(&1u16.to_be_bytes());
+        iloc_body.extend_from_slice(&(payload_offset as u32).to_be_bytes());
+        iloc_body.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        let iloc = full_box(b"iloc", 0, &iloc_body);
+
+        let mut meta_body = iinf;
+        meta_body.extend_from_slice(&iloc);
+        ftyp.extend_from_slice(&full_box(b"meta", 0, &meta_body));
+        ftyp.extend_from_slice(&((payload.len() + 8) as u32).to_be_bytes());
+        ftyp.extend_from_slice(b"mdat");
+        ftyp.extend_from_slice(&payload);
+
+        let path = temp_file("orientation.heic");
+        fs::write(&path, &ftyp).unwrap();
+        write_exif_orientation(&path, 1, None).unwrap();
+
+        let data = fs::read(&path).unwrap();
+        assert_eq!(data.len(), ftyp.len());
+        let range = heif_exif_range(&data).unwrap();
+        assert_eq!(range.start, payload_offset + 10);
+        let tiff = Tiff::parse(data[range].to_vec()).unwrap();
+        assert_eq!(tiff.orientation(Ifd::Primary), Some(1));
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_compose_matches_pixel_transforms() {
+        let mut img = image::RgbImage::new(3, 2);
+        for (i, pixel) in img.pixels_mut().enumerate() {
+            *pixel = image::Rgb([i as u8, 0, 0]);
+        }
+        let img = DynamicImage::ImageRgb8(img);
+
+        for a in (1..=8).map(Orientation::from) {
+            for b in (1..=8).map(Orientation::from) {
+                let sequential = correct_orientation(correct_orientation(img.clone(), a), b);
+                let composed = correct_orientation(img.clone(), a.then(b));
+                assert_eq!(sequential.to_rgb8(), composed.to_rgb8(), "{:?} then {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_compose_exif_orientation_keeps_pixels() {
+        let mut jpeg = sample_jpeg();
+        let mut contents = EXIF_PREFIX.to_vec();
+        contents.extend_from_slice(&Tiff::with_orientation(6).into_bytes());
+        jpeg.segments_mut()
+            .insert(1, JpegSegment::new_with_contents(markers::APP1, contents.into()));
+        let path = temp_file("compose.jpg");
+        fs::write(&path, jpeg.encoder().bytes()).unwrap();
+
+        let composed = compose_exif_orientation(&path, Orientation::Rotate90CW).unwrap();
+        assert_eq!(composed, Orientation::Rotate180);
+        assert_eq!(jpeg_tiff(&path).orientation(Ifd::Primary), Some(3));
+
+        // APP1以外のセグメント（画素データを含む）はそのまま
+        let before = sample_jpeg();
+        let after = Jpeg::from_bytes(fs::read(&path).unwrap().into()).unwrap();
+        let without_exif = |jpeg: &Jpeg| -> Vec<Vec<u8>> {
+            jpeg.segments()
+                .iter()
+                .filter(|s| s.marker() != markers::APP1)
+                .map(|s| s.clone().encoder().bytes().to_vec())
+                .collect()
+        };
+        assert_eq!(without_exif(&before), without_exif(&after));
+
+        // EXIFを持てない形式はエラー
+        let bmp = temp_file("compose.bmp");
+        DynamicImage::new_rgb8(2, 2).save(&bmp).unwrap();
+        assert!(compose_exif_orientation(&bmp, Orientation::Rotate90CW).is_err());
+
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(&bmp);
+    }
+
+    /// 上半分が青空、下半分が細かい模様の地面の画像
+    fn landscape() -> DynamicImage {
+        DynamicImage::ImageRgb8(image::RgbImage::from_fn(96, 64, |x, y| {
+            if y < 32 {
+                image::Rgb([120, 170, 235])
+            } else if (x / 2 + y / 2) % 2 == 0 {
+                image::Rgb([60, 90, 40])
+            } else {
+                image::Rgb([110, 80, 50])
+            }
+        }))
+    }
+
+    #[test]
+    fn test_detect_orientation() {
+        let upright = detect_orientation(&landscape());
+        assert_eq!(upright.orientation, Orientation::Normal);
+        assert!(!upright.needs_correction);
+        assert!(upright.confidence > 0.9);
+        assert_eq!(upright.source, OrientationSource::Detected);
+
+        // 横倒し・逆さまに保存された画像は、元に戻す回転を提案する
+        for stored in [Orientation::Rotate90CW, Orientation::Rotate180, Orientation::Rotate90CCW] {
+            let info = detect_orientation(&correct_orientation(landscape(), stored));
+            assert_eq!(stored.then(info.orientation), Orientation::Normal, "{:?}", stored);
+            assert!(info.needs_correction);
+            assert!(info.confidence >= MIN_DETECTION_CONFIDENCE);
+        }
+
+        // 手がかりの無い画像は提案しない
+        let flat = detect_orientation(&DynamicImage::new_rgb8(64, 64));
+        assert!(!flat.needs_correction);
+        assert!(flat.confidence < MIN_DETECTION_CONFIDENCE);
     }
 
     #[test]
@@ -238,4 +1234,29 @@ mod tests {
         // 90度回転すると、幅と高さが入れ替わる
         assert_eq!(result.dimensions(), (100, 100));
     }
+
+    #[test]
+    fn test_correct_mirrored_orientations() {
+        // 左上だけ白い3x2の画像（保存されている画素）
+        let mut img = image::RgbImage::new(3, 2);
+        img.put_pixel(0, 0, image::Rgb([255, 255, 255]));
+        let img = DynamicImage::ImageRgb8(img);
+
+        // 各変換後に白い画素が来る位置
+        let expected = [
+            (Orientation::Normal, (3, 2), (0, 0)),
+            (Orientation::FlipHorizontal, (3, 2), (2, 0)),
+            (Orientation::Rotate180, (3, 2), (2, 1)),
+            (Orientation::FlipVertical, (3, 2), (0, 1)),
+            (Orientation::Transpose, (2, 3), (0, 0)),
+            (Orientation::Rotate90CW, (2, 3), (1, 0)),
+            (Orientation::Transverse, (2, 3), (1, 2)),
+            (Orientation::Rotate90CCW, (2, 3), (0, 2)),
+        ];
+        for (orientation, dimensions, (x, y)) in expected {
+            let result = correct_orientation(img.clone(), orientation).to_rgb8();
+            assert_eq!(result.dimensions(), dimensions, "{:?}", orientation);
+            assert_eq!(result.get_pixel(x, y).0, [255, 255, 255], "{:?}", orientation);
+        }
+    }
 }
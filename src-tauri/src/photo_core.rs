@@ -1,18 +1,29 @@
 /// 写真・動画リネームのコア機能
 /// y4m2d2の完全移植版
 use anyhow::Result;
-use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use chrono::{DateTime, FixedOffset, Local, NaiveDateTime, Utc};
 use exif::{In, Reader, Tag};
-use image;
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fs;
+use std::io::{self, ErrorKind};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use walkdir::WalkDir;
 
-use crate::burst::{detect_burst_groups, BurstDetectorConfig};
-use crate::orientation;
+use crate::burst::{self, detect_burst_groups_per_source, BurstCandidate, BurstDetectorConfig, BurstPolicy};
+use crate::duplicate::{self, DuplicatePolicy, DuplicateStatus};
+use crate::journal::{self, Journal, JournalEntry, JournalWriter};
+use crate::live_photo::{self, EmbeddedVideo, PairCandidate};
+use crate::maker_note;
+use crate::orientation::{self, Orientation};
+use crate::progress::{ProgressPhase, RunControl};
+use crate::raw;
+use crate::sidecar;
+use crate::similarity::{self, SimilarityConfig};
+use crate::template::{self, PathTemplate, TemplateContext, Templates};
+use crate::timezone::{self, TimeKind, TimezoneResolver};
 use crate::video_metadata;
 
 /// 処理オプション
@@ -24,12 +35,96 @@ pub struct ProcessOptions {
     pub backup_dir: Option<PathBuf>,
     /// 動画ファイルも処理する
     pub include_videos: bool,
-    /// タイムゾーンオフセット（秒、Noneの場合はローカルタイム）
+    /// 撮影地が分からない日時に使うタイムゾーンオフセット（秒、Noneの場合はローカルタイム）
     pub timezone_offset: Option<i32>,
+    /// 撮影地が分からない日時に使うIANAタイムゾーン（例："Asia/Tokyo"、timezone_offsetより優先）
+    #[serde(default)]
+    pub timezone: Option<String>,
     /// 処理後に一時ファイルをクリーンアップ
     pub cleanup_temp: bool,
-    /// 画像の向きを自動修正
+    /// 回転方法が未指定の写真をEXIFの向きに従って回転（process_mediaのみ、process_planは行ごとのrotation_modeに従う）
     pub auto_correct_orientation: bool,
+    /// ドライラン（ディスクに書き込まず操作計画のみ返す）
+    #[serde(default)]
+    pub dry_run: bool,
+    /// 出力先へのファイル転送方法
+    #[serde(default)]
+    pub transfer_mode: TransferMode,
+    /// 内容が同一のファイルの扱い
+    #[serde(default)]
+    pub duplicate_policy: DuplicatePolicy,
+    /// 回転・反転の適用方法（画素を書き換えるか、EXIF Orientationだけを書き換えるか）
+    #[serde(default)]
+    pub rotation_strategy: RotationStrategy,
+    /// スキャン時に知覚ハッシュで類似写真を検出（画像をデコードするため時間がかかる）
+    #[serde(default)]
+    pub detect_similar: bool,
+    /// スキャン時にEXIF Orientationが無い写真の向きを画像の内容から推定
+    #[serde(default)]
+    pub detect_orientation: bool,
+    /// モーションフォトに埋め込まれた動画を、写真と同じ名前の .mp4 として書き出す
+    #[serde(default)]
+    pub extract_motion_photo_video: bool,
+    /// スキャン時のバースト検出の設定
+    #[serde(default)]
+    pub burst: BurstDetectorConfig,
+    /// 処理時のバーストグループの扱い
+    #[serde(default)]
+    pub burst_policy: BurstPolicy,
+    /// 出力ディレクトリ以下の階層テンプレート（例："{YYYY}/{YYYY}-{MM}/{YYYY}-{MM}-{DD}"）
+    #[serde(default = "default_directory_template")]
+    pub directory_template: String,
+    /// ファイル名テンプレート（例："{YYYY}-{MM}-{DD}_{HH}-{mm}-{ss}[-{SSS}][_{burst_index}].{ext}"）
+    #[serde(default = "default_file_name_template")]
+    pub file_name_template: String,
+}
+
+fn default_directory_template() -> String {
+    template::DEFAULT_DIRECTORY_TEMPLATE.to_string()
+}
+
+fn default_file_name_template() -> String {
+    template::DEFAULT_FILE_NAME_TEMPLATE.to_string()
+}
+
+impl ProcessOptions {
+    /// ディレクトリ・ファイル名テンプレートを検証して解析
+    pub fn templates(&self) -> Result<Templates> {
+        Templates::parse(&self.directory_template, &self.file_name_template)
+    }
+
+    /// バースト検出の設定を検証して取得
+    pub fn burst_config(&self) -> Result<&BurstDetectorConfig> {
+        self.burst.validate()?;
+        Ok(&self.burst)
+    }
+
+    /// タイムゾーン設定を検証して解決器を作成
+    pub fn timezone_resolver(&self) -> Result<TimezoneResolver> {
+        TimezoneResolver::new(self.timezone.as_deref(), self.timezone_offset)
+    }
+}
+
+/// 出力先へのファイル転送方法
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransferMode {
+    /// コピー（元ファイルは残る）
+    #[default]
+    Copy,
+    /// 移動（同一ファイルシステムはリネーム、別デバイスはコピー＋検証＋削除）
+    Move,
+    /// ハードリンク（容量を消費しない、回転が必要なファイルはコピー）
+    HardLink,
+}
+
+/// 回転・反転の適用方法
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RotationStrategy {
+    /// 画素を回転・反転して保存し、EXIF Orientationを1にリセット
+    #[default]
+    Pixels,
+    /// 画素には触れず、EXIF Orientationを既存の値と合成して書き換える
+    Metadata,
 }
 
 impl Default for ProcessOptions {
@@ -39,8 +134,20 @@ impl Default for ProcessOptions {
             include_videos: true,
             backup_dir: None,
             timezone_offset: None,
+            timezone: None,
             cleanup_temp: false,
             auto_correct_orientation: false,
+            dry_run: false,
+            transfer_mode: TransferMode::Copy,
+            duplicate_policy: DuplicatePolicy::KeepBoth,
+            rotation_strategy: RotationStrategy::Pixels,
+            detect_similar: false,
+            detect_orientation: false,
+            extract_motion_photo_video: false,
+            burst: BurstDetectorConfig::default(),
+            burst_policy: BurstPolicy::Suffix,
+            directory_template: default_directory_template(),
+            file_name_template: default_file_name_template(),
         }
     }
 }
@@ -67,6 +174,17 @@ pub enum DateSource {
     None,
 }
 
+/// 同じベース名で出力する関連ファイルの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PairKind {
+    /// iPhoneのLive Photo（静止画とMOV）
+    LivePhoto,
+    /// 動画を末尾に埋め込んだJPEG（Google・Samsungのモーションフォト）
+    MotionPhoto,
+    /// 同時に記録したRAWとJPEG
+    RawJpeg,
+}
+
 /// ログレベル
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum LogLevel {
@@ -89,14 +207,15 @@ pub struct MediaInfo {
     pub original_path: PathBuf,
     pub file_name: String,
     pub media_type: MediaType,
-    pub date_taken: Option<DateTime<Local>>,
+    /// 撮影日時（撮影地のオフセット付き、ファイル名には壁時計の時刻を使う）
+    pub date_taken: Option<DateTime<FixedOffset>>,
     pub subsec_time: Option<u32>, // ミリ秒（0-999）
     pub timezone: Option<String>, // タイムゾーンオフセット（例："+09:00", null=TZ情報なし）
     /// 利用可能な日付候補（ユーザー選択用）
-    pub exif_date: Option<DateTime<Local>>,
-    pub filename_date: Option<DateTime<Local>>,
-    pub file_created_date: Option<DateTime<Local>>,
-    pub file_modified_date: Option<DateTime<Local>>,
+    pub exif_date: Option<DateTime<FixedOffset>>,
+    pub filename_date: Option<DateTime<FixedOffset>>,
+    pub file_created_date: Option<DateTime<FixedOffset>>,
+    pub file_modified_date: Option<DateTime<FixedOffset>>,
     pub new_name: String,
     pub new_path: PathBuf,
     pub file_size: u64,
@@ -104,32 +223,175 @@ pub struct MediaInfo {
     pub burst_group_id: Option<usize>,
     /// バーストグループ内のインデックス（1始まり）
     pub burst_index: Option<usize>,
+    /// バーストグループ内で残す候補か（シャープさと露出の評価が最も高い写真）
+    #[serde(default)]
+    pub burst_best: bool,
+    /// カメラが記録したバーストの識別子（AppleのBurstUUID、ファイル名のBURSTなど）
+    #[serde(default)]
+    pub burst_id: Option<String>,
+    /// 連写中の通し番号（Canon・SonyのMakerNote）
+    #[serde(default)]
+    pub sequence_number: Option<u32>,
     /// 日付の取得元
     pub date_source: DateSource,
+    /// 動画のメタデータ日時が撮影地の壁時計の時刻か（AVIのIDITなど、タイムゾーンを持たない日時）
+    #[serde(default)]
+    pub video_wall_clock: bool,
     /// EXIF orientation値（1-8、Noneは回転なし）
     pub exif_orientation: Option<u32>,
     /// 画像回転が適用されたか
     pub rotation_applied: bool,
     /// ユーザー選択：TZオフセット補正（例："+09:00", "none", "exif"）
     pub timezone_offset: Option<String>,
-    /// ユーザー選択：回転方法（"none", "exif", "90", "180", "270"）
+    /// ユーザー選択：回転方法（"none", "exif", "90", "180", "270",
+    /// "flip-horizontal", "flip-vertical", "transpose", "transverse"）
     pub rotation_mode: Option<String>,
-    /// 画像の幅（ピクセル）
+    /// 画像解析で推定した、正しい向きにするための変換（EXIF Orientation値、提案なしはNone）
+    #[serde(default)]
+    pub suggested_orientation: Option<u32>,
+    /// 向きの推定の確からしさ（0.0〜1.0、推定していない場合はNone）
+    #[serde(default)]
+    pub orientation_confidence: Option<f32>,
+    /// 画像の幅（ピクセル、動画は回転を適用した表示時の幅）
     pub width: Option<u32>,
-    /// 画像の高さ（ピクセル）
+    /// 画像の高さ（ピクセル、動画は回転を適用した表示時の高さ）
     pub height: Option<u32>,
+    /// 動画の回転（表示時に時計回りに回転する角度: 0/90/180/270）
+    #[serde(default)]
+    pub video_rotation: Option<u32>,
+    /// 動画のコーデック（"avc1"、"hvc1"、"V_VP9" など）
+    #[serde(default)]
+    pub video_codec: Option<String>,
+    /// 動画のフレームレート（fps）
+    #[serde(default)]
+    pub frame_rate: Option<f64>,
+    /// 動画のビットレート（bps）
+    #[serde(default)]
+    pub bitrate: Option<u64>,
+    /// カメラのメーカー（EXIF Make）
+    #[serde(default)]
+    pub camera_make: Option<String>,
+    /// カメラの機種名（EXIF Model）
+    #[serde(default)]
+    pub camera_model: Option<String>,
+    /// 撮影地の緯度・経度（度、動画のISO 6709の位置情報）
+    #[serde(default)]
+    pub latitude: Option<f64>,
+    #[serde(default)]
+    pub longitude: Option<f64>,
+    /// ユーザー選択：処理対象に含めるか（falseの場合はスキップ）
+    #[serde(default = "default_selected")]
+    pub selected: bool,
+    /// ユーザー選択：手動で編集した出力ファイル名（Noneの場合は日付から自動生成）
+    #[serde(default)]
+    pub custom_name: Option<String>,
+    /// ファイル内容のSHA-256（処理時に計算）
+    #[serde(default)]
+    pub content_hash: Option<String>,
+    /// 重複の状態
+    #[serde(default)]
+    pub duplicate_status: DuplicateStatus,
+    /// 同一内容のファイル（入力内の先行ファイル、または出力ディレクトリの既存ファイル）
+    #[serde(default)]
+    pub duplicate_of: Option<PathBuf>,
+    /// 知覚ハッシュ（dHash、16桁の16進数）
+    #[serde(default)]
+    pub perceptual_hash: Option<String>,
+    /// 類似写真グループID
+    #[serde(default)]
+    pub similar_group_id: Option<usize>,
+    /// グループ内で残す候補との類似度（0.0〜1.0）
+    #[serde(default)]
+    pub similarity: Option<f32>,
+    /// 類似グループ内で残す候補か
+    #[serde(default)]
+    pub similar_best: bool,
+    /// Live Photo・モーションフォトの種類
+    #[serde(default)]
+    pub pair_kind: Option<PairKind>,
+    /// 同じベース名で出力する主ファイル（Live Photoの動画では静止画）
+    #[serde(default)]
+    pub companion_of: Option<PathBuf>,
+    /// Live Photoの識別子（静止画のMakerNoteと動画のメタデータで共通）
+    #[serde(default)]
+    pub content_identifier: Option<String>,
+    /// モーションフォトに埋め込まれた動画の位置
+    #[serde(default)]
+    pub embedded_video: Option<EmbeddedVideo>,
+    /// 一緒に出力するサイドカーファイル（XMPなど）
+    #[serde(default)]
+    pub sidecars: Vec<PathBuf>,
     /// 処理ログ
     pub logs: Vec<LogEntry>,
 }
 
+fn default_selected() -> bool {
+    true
+}
+
 /// 処理結果
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProcessResult {
     pub success: bool,
     pub total_files: usize,
     pub processed_files: usize,
+    /// 選択解除または重複のためスキップしたファイル数
+    pub skipped_files: usize,
+    /// 内容が同一と判定されたファイル数
+    pub duplicate_files: usize,
     pub media: Vec<MediaInfo>,
     pub errors: Vec<String>,
+    /// ドライラン時の操作計画（通常実行時はNone）
+    pub plan: Option<OperationPlan>,
+    /// この実行のジャーナルファイル（取り消し用、書き込みがなかった場合はNone）
+    pub journal_path: Option<PathBuf>,
+    /// 途中でキャンセルされたか（trueの場合、結果は途中までの分）
+    pub cancelled: bool,
+    /// バーストの扱いで1枚だけ残したため出力しなかったファイル
+    #[serde(default)]
+    pub skipped_burst_frames: Vec<SkippedBurstFrame>,
+}
+
+/// バーストグループから出力しなかった1ファイル
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkippedBurstFrame {
+    /// 出力しなかったファイル
+    pub path: PathBuf,
+    /// 代わりに出力した同じグループの写真
+    pub kept: PathBuf,
+    /// バーストグループID
+    pub burst_group_id: usize,
+}
+
+/// ドライランで予測された1ファイル分の操作
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedOperation {
+    pub source: PathBuf,
+    /// 衝突解決後の最終的な出力パス
+    pub target_path: PathBuf,
+    /// 使用される転送方法
+    pub transfer_mode: TransferMode,
+    /// 名前衝突で付与される連番（衝突なしはNone）
+    pub conflict_counter: Option<usize>,
+    /// 適用される回転・反転（変換なしはNone）
+    pub rotation: Option<Orientation>,
+    /// バックアップ先（バックアップなしはNone）
+    pub backup_path: Option<PathBuf>,
+    /// モーションフォトから抽出する動画の出力パス（抽出しない場合はNone）
+    #[serde(default)]
+    pub embedded_video_path: Option<PathBuf>,
+    /// 一緒に転送するサイドカーの出力パス
+    #[serde(default)]
+    pub sidecar_paths: Vec<PathBuf>,
+}
+
+/// ドライランの操作計画
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperationPlan {
+    /// 新たに作成されるディレクトリ（親から順）
+    pub directories: Vec<PathBuf>,
+    /// ファイルごとの操作
+    pub operations: Vec<PlannedOperation>,
 }
 
 /// ログエントリを追加するヘルパー
@@ -149,7 +411,7 @@ fn is_image_file(extension: &str) -> bool {
     matches!(
         extension,
         "jpg" | "jpeg" | "png" | "gif" | "bmp" | "heic" | "heif" | "webp" | "tiff" | "tif"
-    )
+    ) || raw::is_raw_extension(extension)
 }
 
 /// 動画拡張子のチェック
@@ -162,52 +424,50 @@ fn is_video_file(extension: &str) -> bool {
 
 /// EXIF情報の詳細
 #[derive(Debug, Clone)]
+#[derive(Default)]
 struct ExifInfo {
-    date: Option<DateTime<Local>>,
+    date: Option<NaiveDateTime>, // タイムゾーンなしの壁時計の時刻
     subsec: Option<u32>, // ミリ秒（0-999）
     timezone: Option<String>, // タイムゾーンオフセット（例："+09:00"）
     orientation: Option<u32>,
     width: Option<u32>,
     height: Option<u32>,
+    make: Option<String>,
+    model: Option<String>,
+    content_identifier: Option<String>, // Live Photoの識別子（AppleのMakerNote）
+    burst_id: Option<String>, // バーストの識別子（AppleのMakerNote）
+    sequence_number: Option<u32>, // 連写中の通し番号（Canon・SonyのMakerNote）
 }
 
 /// EXIF情報を取得
 fn get_exif_info(path: &Path) -> Result<ExifInfo> {
-    let file = fs::File::open(path)?;
-    let mut bufreader = std::io::BufReader::new(&file);
-
-    let exifreader = Reader::new();
-    let exif = match exifreader.read_from_container(&mut bufreader) {
-        Ok(exif) => exif,
-        Err(_) => return Ok(ExifInfo {
-            date: None,
-            subsec: None,
-            timezone: None,
-            orientation: None,
-            width: None,
-            height: None,
-        }),
-    };
+    let exifs = if raw::is_raw_extension(&lowercase_extension(path)) {
+        match raw::read_raw_exif(path) {
+            Ok(exifs) => exifs,
+            Err(_) => return Ok(ExifInfo::default()),
+        }
+    } else {
+        let file = fs::File::open(path)?;
+        let mut bufreader = std::io::BufReader::new(&file);
 
-    let mut info = ExifInfo {
-        date: None,
-        subsec: None,
-        timezone: None,
-        orientation: None,
-        width: None,
-        height: None,
+        let exifreader = Reader::new();
+        match exifreader.read_from_container(&mut bufreader) {
+            Ok(exif) => vec![exif],
+            Err(_) => return Ok(ExifInfo::default()),
+        }
     };
+    // CR3のように複数のTIFF構造に分かれている場合は先に見つかった値を使う
+    let get_field = |tag: Tag| exifs.iter().find_map(|exif| exif.get_field(tag, In::PRIMARY));
+
+    let mut info = ExifInfo::default();
 
     // DateTimeOriginal (撮影日時) を取得
-    if let Some(field) = exif.get_field(Tag::DateTimeOriginal, In::PRIMARY) {
+    if let Some(field) = get_field(Tag::DateTimeOriginal) {
         if let exif::Value::Ascii(ref vec) = field.value {
             if let Some(datetime) = vec.first() {
                 let datetime_str = String::from_utf8_lossy(datetime);
                 if let Ok(naive) = NaiveDateTime::parse_from_str(&datetime_str, "%Y:%m:%d %H:%M:%S") {
-                    info.date = Some(DateTime::from_naive_utc_and_offset(
-                        naive,
-                        *Local::now().offset(),
-                    ));
+                    info.date = Some(naive);
                 }
             }
         }
@@ -215,49 +475,28 @@ fn get_exif_info(path: &Path) -> Result<ExifInfo> {
 
     // DateTime も試す（DateTimeOriginalがない場合）
     if info.date.is_none() {
-        if let Some(field) = exif.get_field(Tag::DateTime, In::PRIMARY) {
+        if let Some(field) = get_field(Tag::DateTime) {
             if let exif::Value::Ascii(ref vec) = field.value {
                 if let Some(datetime) = vec.first() {
                     let datetime_str = String::from_utf8_lossy(datetime);
                     if let Ok(naive) = NaiveDateTime::parse_from_str(&datetime_str, "%Y:%m:%d %H:%M:%S") {
-                        info.date = Some(DateTime::from_naive_utc_and_offset(
-                            naive,
-                            *Local::now().offset(),
-                        ));
+                        info.date = Some(naive);
                     }
                 }
             }
         }
     }
 
-    // SubSecTimeOriginal (ミリ秒) を取得
-    if let Some(field) = exif.get_field(Tag::SubSecTimeOriginal, In::PRIMARY) {
-        if let exif::Value::Ascii(ref vec) = field.value {
-            if let Some(subsec_bytes) = vec.first() {
-                let subsec_str = String::from_utf8_lossy(subsec_bytes);
-                if let Ok(subsec) = subsec_str.trim().parse::<u32>() {
-                    info.subsec = Some(subsec);
-                }
-            }
-        }
-    }
-
-    // SubSecTime も試す（SubSecTimeOriginalがない場合）
-    if info.subsec.is_none() {
-        if let Some(field) = exif.get_field(Tag::SubSecTime, In::PRIMARY) {
-            if let exif::Value::Ascii(ref vec) = field.value {
-                if let Some(subsec_bytes) = vec.first() {
-                    let subsec_str = String::from_utf8_lossy(subsec_bytes);
-                    if let Ok(subsec) = subsec_str.trim().parse::<u32>() {
-                        info.subsec = Some(subsec);
-                    }
-                }
-            }
+    // SubSecTimeOriginal (ミリ秒) を取得、ない場合は SubSecTime
+    info.subsec = [Tag::SubSecTimeOriginal, Tag::SubSecTime].into_iter().find_map(|tag| {
+        match get_field(tag)?.value {
+            exif::Value::Ascii(ref vec) => parse_subsec_millis(&String::from_utf8_lossy(vec.first()?)),
+            _ => None,
         }
-    }
+    });
 
     // OffsetTimeOriginal (タイムゾーンオフセット) を取得
-    if let Some(field) = exif.get_field(Tag::OffsetTimeOriginal, In::PRIMARY) {
+    if let Some(field) = get_field(Tag::OffsetTimeOriginal) {
         if let exif::Value::Ascii(ref vec) = field.value {
             if let Some(offset_bytes) = vec.first() {
                 let offset_str = String::from_utf8_lossy(offset_bytes).trim().to_string();
@@ -270,7 +509,7 @@ fn get_exif_info(path: &Path) -> Result<ExifInfo> {
 
     // OffsetTime も試す（OffsetTimeOriginalがない場合）
     if info.timezone.is_none() {
-        if let Some(field) = exif.get_field(Tag::OffsetTime, In::PRIMARY) {
+        if let Some(field) = get_field(Tag::OffsetTime) {
             if let exif::Value::Ascii(ref vec) = field.value {
                 if let Some(offset_bytes) = vec.first() {
                     let offset_str = String::from_utf8_lossy(offset_bytes).trim().to_string();
@@ -283,7 +522,7 @@ fn get_exif_info(path: &Path) -> Result<ExifInfo> {
     }
 
     // Orientation を取得
-    if let Some(field) = exif.get_field(Tag::Orientation, In::PRIMARY) {
+    if let Some(field) = get_field(Tag::Orientation) {
         if let exif::Value::Short(ref vec) = field.value {
             if let Some(&orientation) = vec.first() {
                 info.orientation = Some(orientation as u32);
@@ -292,7 +531,7 @@ fn get_exif_info(path: &Path) -> Result<ExifInfo> {
     }
 
     // 画像サイズを取得
-    if let Some(field) = exif.get_field(Tag::PixelXDimension, In::PRIMARY) {
+    if let Some(field) = get_field(Tag::PixelXDimension) {
         if let exif::Value::Long(ref vec) = field.value {
             if let Some(&width) = vec.first() {
                 info.width = Some(width);
@@ -300,7 +539,7 @@ fn get_exif_info(path: &Path) -> Result<ExifInfo> {
         }
     }
 
-    if let Some(field) = exif.get_field(Tag::PixelYDimension, In::PRIMARY) {
+    if let Some(field) = get_field(Tag::PixelYDimension) {
         if let exif::Value::Long(ref vec) = field.value {
             if let Some(&height) = vec.first() {
                 info.height = Some(height);
@@ -308,11 +547,37 @@ fn get_exif_info(path: &Path) -> Result<ExifInfo> {
         }
     }
 
+    // カメラのメーカー・機種名を取得
+    let ascii = |tag: Tag| {
+        get_field(tag).and_then(|field| match field.value {
+            exif::Value::Ascii(ref vec) => vec
+                .first()
+                .map(|bytes| String::from_utf8_lossy(bytes).trim_matches(|c: char| c == '\0' || c.is_whitespace()).to_string())
+                .filter(|s| !s.is_empty()),
+            _ => None,
+        })
+    };
+    info.make = ascii(Tag::Make);
+    info.model = ascii(Tag::Model);
+
+    // Live Photo・バーストの識別子（MakerNote、値の位置はTIFF構造内のオフセット）
+    let maker_note = exifs.iter().find_map(|exif| match exif.get_field(Tag::MakerNote, In::PRIMARY)?.value {
+        exif::Value::Undefined(_, offset) => Some(maker_note::parse(info.make.as_deref(), exif.buf(), offset as usize)),
+        _ => None,
+    });
+    if let Some(maker_note) = maker_note {
+        info.content_identifier = maker_note.content_identifier;
+        info.burst_id = maker_note.burst_id;
+        info.sequence_number = maker_note.sequence_number;
+    }
+
     Ok(info)
 }
 
 /// ファイル名から日付を抽出
-fn extract_date_from_filename(filename: &str) -> Option<DateTime<Local>> {
+///
+/// ファイル名の時刻はタイムゾーンを持たない壁時計の時刻として返します。
+fn extract_date_from_filename(filename: &str) -> Option<NaiveDateTime> {
     use regex::Regex;
 
     // パターン1: YYYYMMDD_HHMMSS (最も一般的)
@@ -329,7 +594,7 @@ fn extract_date_from_filename(filename: &str) -> Option<DateTime<Local>> {
         if let Some(naive) = chrono::NaiveDate::from_ymd_opt(year, month, day)
             .and_then(|d| d.and_hms_opt(hour, minute, second))
         {
-            return Local.from_local_datetime(&naive).single();
+            return Some(naive);
         }
     }
 
@@ -347,7 +612,7 @@ fn extract_date_from_filename(filename: &str) -> Option<DateTime<Local>> {
         if let Some(naive) = chrono::NaiveDate::from_ymd_opt(year, month, day)
             .and_then(|d| d.and_hms_opt(hour, minute, second))
         {
-            return Local.from_local_datetime(&naive).single();
+            return Some(naive);
         }
     }
 
@@ -362,7 +627,7 @@ fn extract_date_from_filename(filename: &str) -> Option<DateTime<Local>> {
         if let Some(naive) = chrono::NaiveDate::from_ymd_opt(year, month, day)
             .and_then(|d| d.and_hms_opt(0, 0, 0))
         {
-            return Some(DateTime::from_naive_utc_and_offset(naive, *Local::now().offset()));
+            return Some(naive);
         }
     }
 
@@ -370,42 +635,242 @@ fn extract_date_from_filename(filename: &str) -> Option<DateTime<Local>> {
 }
 
 /// ファイルの作成日時を取得
-fn get_file_created_date(path: &Path) -> Result<DateTime<Local>> {
+fn get_file_created_date(path: &Path) -> Result<DateTime<Utc>> {
     let metadata = fs::metadata(path)?;
     let created = metadata.created()?;
     Ok(DateTime::from(created))
 }
 
 /// ファイルの変更日時を取得
-fn get_file_modified_date(path: &Path) -> Result<DateTime<Local>> {
+fn get_file_modified_date(path: &Path) -> Result<DateTime<Utc>> {
     let metadata = fs::metadata(path)?;
     let modified = metadata.modified()?;
     Ok(DateTime::from(modified))
 }
 
-/// 日時からファイル名を生成（YYYY-MM-DD_HH-mm-ss[-mmm]形式）
-fn format_filename(date: &DateTime<Local>, subsec: Option<u32>, extension: &str) -> String {
-    if let Some(ms) = subsec {
-        // ミリ秒がある場合は3桁で追加
-        format!("{}-{:03}.{}", date.format("%Y-%m-%d_%H-%M-%S"), ms, extension)
-    } else {
-        // ミリ秒がない場合は秒まで
-        format!("{}.{}", date.format("%Y-%m-%d_%H-%M-%S"), extension)
+/// MediaInfoからテンプレートに埋め込む値を用意して処理（日付がない場合はNone）
+fn with_template_context<R>(
+    item: &MediaInfo,
+    counter: Option<usize>,
+    f: impl FnOnce(&TemplateContext) -> R,
+) -> Option<R> {
+    let extension = lowercase_extension(&item.original_path);
+    let original_stem = item
+        .original_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("");
+    let context = TemplateContext {
+        date: item.date_taken?,
+        subsec: item.subsec_time,
+        camera_make: item.camera_make.as_deref(),
+        camera_model: item.camera_model.as_deref(),
+        media_type: match item.media_type {
+            MediaType::Photo => "photo",
+            MediaType::Video => "video",
+        },
+        original_stem,
+        burst_index: item.burst_index,
+        extension: &extension,
+        counter,
+    };
+    Some(f(&context))
+}
+
+/// ファイル名テンプレートから新しいファイル名を生成（`{counter}` は1から）
+fn render_new_name(item: &MediaInfo, template: &PathTemplate) -> Option<String> {
+    with_template_context(item, Some(1), |context| template.render(context))
+}
+
+/// 日付の取得元が壁時計の時刻か絶対時刻か
+///
+/// 写真のEXIF日時とファイル名は撮影地の壁時計の時刻、動画のQuickTime日時とファイルのタイムスタンプは絶対時刻です。
+/// 動画でもAVIのIDITのようにタイムゾーンを持たない日時は壁時計の時刻として扱います。
+fn time_kind(item: &MediaInfo) -> TimeKind {
+    match (item.media_type, item.date_source) {
+        (MediaType::Photo, DateSource::Exif) | (_, DateSource::FileName) => TimeKind::WallClock,
+        (MediaType::Video, DateSource::Exif) if item.video_wall_clock => TimeKind::WallClock,
+        _ => TimeKind::Instant,
+    }
+}
+
+/// 行ごとのユーザー選択（日付ソース・TZオフセット・ファイル名）をMediaInfoに反映
+///
+/// date_taken と new_name を選択内容から再計算します。
+/// フロントエンドの New Name プレビューと同じ規則で計算するため、画面に表示された名前がそのまま出力されます。
+fn apply_user_overrides(item: &mut MediaInfo, templates: &Templates) {
+    let candidate = match item.date_source {
+        DateSource::Exif => item.exif_date,
+        DateSource::FileName => item.filename_date,
+        DateSource::FileCreated => item.file_created_date,
+        DateSource::FileModified => item.file_modified_date,
+        DateSource::None => None,
+    };
+    // 動画のメタデータ日時はexif_dateに入らないため、送られてきたdate_takenにフォールバック
+    let Some(mut date) = candidate.or(item.date_taken) else {
+        return;
+    };
+
+    // 行ごとのタイムゾーン指定（撮影地）を反映
+    let selection = item.timezone_offset.clone().unwrap_or_else(|| "none".to_string());
+    let kind = time_kind(item);
+    match timezone::apply_zone_override(date, kind, &selection, item.timezone.as_deref()) {
+        Ok(resolved) => date = resolved,
+        Err(message) => item.add_log(LogLevel::Warning, message),
+    }
+
+    // ミリ秒はEXIF日時にのみ付随する
+    if item.date_source != DateSource::Exif {
+        item.subsec_time = None;
+    }
+    item.date_taken = Some(date);
+
+    item.new_name = match item.custom_name.as_deref().map(str::trim) {
+        Some(name) if !name.is_empty() => name.to_string(),
+        _ => render_new_name(item, &templates.file_name).unwrap_or_default(),
+    };
+}
+
+/// バーストグループの扱い（`ProcessOptions::burst_policy`）を選択された行に反映
+///
+/// 1枚だけ残す場合は、グループ内の残りの写真とその関連ファイルの選択を外し、出力しなかったファイルを返します。
+/// 残した写真とグループ化を無視した写真は、ファイル名から連番（`_NN`）を除きます。
+fn apply_burst_policy(media: &mut [MediaInfo], policy: BurstPolicy, templates: &Templates) -> Vec<SkippedBurstFrame> {
+    let mut skipped = Vec::new();
+    match policy {
+        BurstPolicy::Suffix | BurstPolicy::Subfolder => return skipped,
+        BurstPolicy::Ignore => {
+            for item in media.iter_mut().filter(|m| m.selected && m.burst_group_id.is_some()) {
+                item.burst_group_id = None;
+                item.burst_best = false;
+                clear_burst_index(item, templates);
+            }
+            return skipped;
+        }
+        BurstPolicy::KeepBest | BurstPolicy::KeepFirst => {}
+    }
+
+    // グループごとに残す写真を選択された写真から決める（残す候補が無い・選択されていない場合は先頭）
+    let rank = |item: &MediaInfo| (!(policy == BurstPolicy::KeepBest && item.burst_best), item.burst_index);
+    let mut keepers: HashMap<usize, usize> = HashMap::new();
+    for (index, item) in media.iter().enumerate() {
+        let Some(group_id) = item.burst_group_id.filter(|_| item.selected) else {
+            continue;
+        };
+        let kept = keepers.entry(group_id).or_insert(index);
+        if rank(item) < rank(&media[*kept]) {
+            *kept = index;
+        }
+    }
+    let kept_paths: HashMap<usize, PathBuf> = keepers
+        .into_iter()
+        .map(|(group_id, index)| (group_id, media[index].original_path.clone()))
+        .collect();
+
+    let mut dropped: HashMap<PathBuf, (usize, PathBuf)> = HashMap::new();
+    for item in media.iter_mut() {
+        let Some(group_id) = item.burst_group_id.filter(|_| item.selected) else {
+            continue;
+        };
+        let kept = &kept_paths[&group_id];
+        if *kept == item.original_path {
+            clear_burst_index(item, templates);
+            continue;
+        }
+        item.selected = false;
+        item.add_log(LogLevel::Info, format!("Skipped: burst frame (kept {})", kept.display()));
+        dropped.insert(item.original_path.clone(), (group_id, kept.clone()));
+    }
+
+    // 出力しない写真の関連ファイル（Live Photoの動画、RAW+JPEGのRAWなど）も出力しない
+    for item in media.iter_mut() {
+        if item.selected {
+            let Some((group_id, kept)) = item.companion_of.as_ref().and_then(|p| dropped.get(p)) else {
+                continue;
+            };
+            item.selected = false;
+            item.add_log(LogLevel::Info, format!("Skipped: burst frame (kept {})", kept.display()));
+            skipped.push(SkippedBurstFrame {
+                path: item.original_path.clone(),
+                kept: kept.clone(),
+                burst_group_id: *group_id,
+            });
+        } else if let Some((group_id, kept)) = dropped.get(&item.original_path) {
+            skipped.push(SkippedBurstFrame {
+                path: item.original_path.clone(),
+                kept: kept.clone(),
+                burst_group_id: *group_id,
+            });
+        }
+    }
+    skipped
+}
+
+/// バースト内の連番を外し、ファイル名を付け直す（手動のファイル名はそのまま）
+fn clear_burst_index(item: &mut MediaInfo, templates: &Templates) {
+    item.burst_index = None;
+    if item.custom_name.as_deref().is_some_and(|n| !n.trim().is_empty()) {
+        return;
+    }
+    if let Some(new_name) = render_new_name(item, &templates.file_name) {
+        item.new_name = new_name;
     }
 }
 
+/// バーストグループごとのサブフォルダ名（`burst_<先頭の写真の撮影日時>`）
+fn burst_directories(media: &[MediaInfo]) -> HashMap<usize, String> {
+    let mut starts: HashMap<usize, (usize, DateTime<FixedOffset>)> = HashMap::new();
+    for item in media {
+        let (Some(group_id), Some(index), Some(date)) = (item.burst_group_id, item.burst_index, item.date_taken) else {
+            continue;
+        };
+        let start = starts.entry(group_id).or_insert((index, date));
+        if index < start.0 {
+            *start = (index, date);
+        }
+    }
+    starts
+        .into_iter()
+        .map(|(group_id, (_, date))| (group_id, format!("burst_{}", date.format("%Y-%m-%d_%H-%M-%S"))))
+        .collect()
+}
+
 /// 対象ディレクトリ内のメディアファイルをスキャン
 pub fn scan_media(input_dir: &Path, options: &ProcessOptions) -> Result<Vec<MediaInfo>> {
+    scan_media_with_control(input_dir, options, &RunControl::default())
+}
+
+/// 進捗通知とキャンセルに対応したスキャン
+///
+/// キャンセルされた場合は途中結果を捨ててエラーを返します。
+pub fn scan_media_with_control(
+    input_dir: &Path,
+    options: &ProcessOptions,
+    control: &RunControl,
+) -> Result<Vec<MediaInfo>> {
+    let templates = options.templates()?;
+    let resolver = options.timezone_resolver()?;
+    let burst_config = options.burst_config()?;
     let files: Vec<_> = WalkDir::new(input_dir)
         .follow_links(false)
         .into_iter()
         .filter_map(|e| e.ok())
         .filter(|e| e.path().is_file())
         .collect();
+    let sidecar_files: Vec<PathBuf> = files
+        .iter()
+        .map(|e| e.path())
+        .filter(|p| sidecar::is_sidecar_extension(&lowercase_extension(p)))
+        .map(Path::to_path_buf)
+        .collect();
 
     let media = Arc::new(Mutex::new(Vec::new()));
+    let progress = control.phase(ProgressPhase::Scanning, files.len());
 
     let processor = |entry: &walkdir::DirEntry| {
+        if control.is_cancelled() {
+            return;
+        }
         let path = entry.path();
         let extension = path
             .extension()
@@ -422,30 +887,15 @@ pub fn scan_media(input_dir: &Path, options: &ProcessOptions) -> Result<Vec<Medi
         };
 
         if let Some(mtype) = media_type {
-            // 画像の場合はEXIF、動画の場合はQuickTimeメタデータを取得
+            // 画像の場合はEXIF、動画の場合はコンテナのメタデータを取得
             let (exif_info, video_meta) = match mtype {
                 MediaType::Photo => {
-                    let exif = get_exif_info(path).ok().unwrap_or(ExifInfo {
-                        date: None,
-                        subsec: None,
-                        timezone: None,
-                        orientation: None,
-                        width: None,
-                        height: None,
-                    });
+                    let exif = get_exif_info(path).ok().unwrap_or_default();
                     (exif, None)
                 }
                 MediaType::Video => {
                     let video = video_metadata::extract_video_metadata(path).ok();
-                    let empty_exif = ExifInfo {
-                        date: None,
-                        subsec: None,
-                        timezone: None,
-                        orientation: None,
-                        width: None,
-                        height: None,
-                    };
-                    (empty_exif, video)
+                    (ExifInfo::default(), video)
                 }
             };
 
@@ -455,19 +905,28 @@ pub fn scan_media(input_dir: &Path, options: &ProcessOptions) -> Result<Vec<Medi
                 .and_then(|n| n.to_str())
                 .unwrap_or("");
 
-            // 各候補の日付を取得
-            let exif_date = exif_info.date;
-            let video_date = video_meta.as_ref().map(|v| DateTime::<Local>::from(v.creation_time));
-            let filename_date = extract_date_from_filename(filename);
-            let file_created_date = get_file_created_date(path).ok();
-            let file_modified_date = get_file_modified_date(path).ok();
+            // 各候補の日付を撮影地のオフセット付きで解決
+            let exif_date = exif_info
+                .date
+                .map(|naive| resolver.exif_time(naive, exif_info.timezone.as_deref()));
+            // 撮影地の時刻（iPhoneのcreationdate、AVIのIDITなど）は写真のEXIFと同じく壁時計の時刻、
+            // QuickTimeのmvhdなどは絶対時刻
+            let video_wall_clock = video_meta.as_ref().is_some_and(|v| v.local_time.is_some());
+            let video_date = video_meta.as_ref().and_then(|v| match (v.local_time, v.creation_time) {
+                (Some(local), _) => Some(resolver.exif_time(local, v.timezone.as_deref())),
+                (None, Some(instant)) => Some(resolver.instant(&instant)),
+                (None, None) => None,
+            });
+            let filename_date = extract_date_from_filename(filename).map(|naive| resolver.wall_clock(naive));
+            let file_created_date = get_file_created_date(path).ok().map(|d| resolver.instant(&d));
+            let file_modified_date = get_file_modified_date(path).ok().map(|d| resolver.instant(&d));
 
             // 日付を決定（優先順位: EXIF/QuickTime > ファイル名 > ファイル作成日時 > ファイル変更日時）
             let (date_taken, date_source, subsec) = if let Some(exif_date) = exif_date {
                 (Some(exif_date), DateSource::Exif, exif_info.subsec)
             } else if let Some(video_date) = video_date {
-                // 動画のQuickTimeメタデータ
-                (Some(video_date), DateSource::Exif, None) // ExifとしてマークするがQuickTimeデータ
+                // 動画のコンテナのメタデータ
+                (Some(video_date), DateSource::Exif, None) // Exifとしてマークするがコンテナのデータ
             } else if let Some(filename_date) = filename_date {
                 (Some(filename_date), DateSource::FileName, None)
             } else if let Some(created_date) = file_created_date {
@@ -479,10 +938,25 @@ pub fn scan_media(input_dir: &Path, options: &ProcessOptions) -> Result<Vec<Medi
             };
 
             if let Some(date) = date_taken {
-                let new_name = format_filename(&date, subsec, &extension);
+                // 類似写真検出用の知覚ハッシュ
+                let perceptual_hash = if options.detect_similar && mtype == MediaType::Photo {
+                    similarity::dhash_file(path).ok().map(|h| format!("{:016x}", h))
+                } else {
+                    None
+                };
+                // EXIFに向きの情報が無い写真は画像の内容から推定
+                let detected_orientation = (options.detect_orientation
+                    && mtype == MediaType::Photo
+                    && exif_info.orientation.is_none())
+                .then(|| orientation::detect_orientation_file(path).ok())
+                .flatten();
+                // モーションフォトの末尾に埋め込まれた動画
+                let embedded_video = (mtype == MediaType::Photo && matches!(extension.as_str(), "jpg" | "jpeg"))
+                    .then(|| live_photo::find_motion_photo_video(path).ok().flatten())
+                    .flatten();
                 let file_size = fs::metadata(path).ok().map(|m| m.len()).unwrap_or(0);
 
-                let info = MediaInfo {
+                let mut info = MediaInfo {
                     original_path: path.to_path_buf(),
                     file_name: path
                         .file_name()
@@ -493,7 +967,10 @@ pub fn scan_media(input_dir: &Path, options: &ProcessOptions) -> Result<Vec<Medi
                     date_taken: Some(date),
                     subsec_time: subsec,
                     timezone: if date_source == DateSource::Exif {
-                        exif_info.timezone.clone()
+                        exif_info
+                            .timezone
+                            .clone()
+                            .or_else(|| video_meta.as_ref()?.timezone.clone())
                     } else {
                         None
                     },
@@ -502,24 +979,61 @@ pub fn scan_media(input_dir: &Path, options: &ProcessOptions) -> Result<Vec<Medi
                     filename_date,
                     file_created_date,
                     file_modified_date,
-                    new_name,
+                    new_name: String::new(), // テンプレートから下で生成
                     new_path: PathBuf::new(),
                     file_size,
                     burst_group_id: None,
                     burst_index: None,
+                    burst_best: false,
+                    burst_id: exif_info.burst_id.clone().or_else(|| burst::filename_burst_id(filename)),
+                    sequence_number: exif_info.sequence_number,
                     date_source,
+                    video_wall_clock: video_wall_clock && date_source == DateSource::Exif,
                     exif_orientation: exif_info.orientation,
                     rotation_applied: false, // スキャン時はまだ回転していない
                     timezone_offset: None, // ユーザー未選択（フロントエンドで設定）
                     rotation_mode: None, // ユーザー未選択（フロントエンドで設定）
-                    width: video_meta.as_ref().map(|v| v.width).or(exif_info.width),
-                    height: video_meta.as_ref().map(|v| v.height).or(exif_info.height),
+                    suggested_orientation: detected_orientation
+                        .as_ref()
+                        .filter(|d| d.needs_correction)
+                        .map(|d| d.orientation.exif_value() as u32),
+                    orientation_confidence: detected_orientation.as_ref().map(|d| d.confidence),
+                    width: video_meta.as_ref().map(|v| v.width).filter(|&w| w > 0).or(exif_info.width),
+                    height: video_meta.as_ref().map(|v| v.height).filter(|&h| h > 0).or(exif_info.height),
+                    video_rotation: video_meta.as_ref().map(|v| v.rotation),
+                    video_codec: video_meta.as_ref().and_then(|v| v.codec.clone()),
+                    frame_rate: video_meta.as_ref().and_then(|v| v.frame_rate),
+                    bitrate: video_meta.as_ref().and_then(|v| v.bitrate),
+                    camera_make: exif_info.make.clone().or_else(|| video_meta.as_ref()?.make.clone()),
+                    camera_model: exif_info.model.clone().or_else(|| video_meta.as_ref()?.model.clone()),
+                    latitude: video_meta.as_ref().and_then(|v| v.latitude),
+                    longitude: video_meta.as_ref().and_then(|v| v.longitude),
+                    selected: true,
+                    custom_name: None,
+                    content_hash: None,
+                    duplicate_status: DuplicateStatus::Unique,
+                    duplicate_of: None,
+                    perceptual_hash,
+                    similar_group_id: None,
+                    similarity: None,
+                    similar_best: false,
+                    pair_kind: embedded_video.map(|_| PairKind::MotionPhoto),
+                    companion_of: None, // Live Photoの組は全ファイルのスキャン後に検出
+                    content_identifier: exif_info
+                        .content_identifier
+                        .clone()
+                        .or_else(|| video_meta.as_ref()?.content_identifier.clone()),
+                    embedded_video,
+                    sidecars: Vec::new(), // 全ファイルのスキャン後に割り当て
                     logs: Vec::new(), // ログは空で初期化
                 };
+                info.new_name = render_new_name(&info, &templates.file_name).unwrap_or_default();
 
                 media.lock().unwrap().push(info);
             }
         }
+
+        progress.tick(path);
     };
 
     if options.parallel {
@@ -528,14 +1042,49 @@ pub fn scan_media(input_dir: &Path, options: &ProcessOptions) -> Result<Vec<Medi
         files.iter().for_each(processor);
     }
 
+    if control.is_cancelled() {
+        anyhow::bail!("Operation cancelled");
+    }
+
     let mut result = Arc::try_unwrap(media)
         .map(|mutex| mutex.into_inner().unwrap())
         .unwrap_or_else(|arc| arc.lock().unwrap().clone());
-
-    // バースト検出を実行
-    let dates: Vec<Option<DateTime<Local>>> = result.iter().map(|m| m.date_taken).collect();
-    let burst_config = BurstDetectorConfig::default();
-    let burst_groups = detect_burst_groups(&dates, &burst_config);
+    // 並列スキャンで順不同になるため、パス順に並べて結果を毎回同じにする
+    result.sort_by(|a, b| a.original_path.cmp(&b.original_path));
+
+    // Live Photoの静止画と動画、同時に記録したRAWとJPEGを組にし、サイドカーを割り当てる
+    link_live_photos(&mut result);
+    link_raw_jpeg_pairs(&mut result);
+    attach_sidecars(&mut result, &sidecar_files);
+
+    // バースト検出を実行（機器ごとに撮影時刻順で検出、Live Photoの動画などの関連ファイルは主ファイルと同時刻のため除く）
+    let candidates: Vec<BurstCandidate> = result
+        .iter()
+        .map(|m| BurstCandidate {
+            date: m.date_taken.filter(|_| m.companion_of.is_none()),
+            subsec_millis: m.subsec_time,
+            source: burst_source(m),
+            burst_id: m.burst_id.clone(),
+            sequence_number: m.sequence_number,
+        })
+        .collect();
+    let mut burst_groups = detect_burst_groups_per_source(&candidates, burst_config);
+
+    // グループ内で残す候補を選ぶ（画像をデコードするため並列に評価）
+    if burst_config.select_best {
+        let indices: Vec<usize> = burst_groups
+            .iter()
+            .flat_map(|group| group.photo_indices.iter().copied())
+            .filter(|&i| result[i].media_type == MediaType::Photo)
+            .collect();
+        let score = |&i: &usize| (i, burst::frame_score(&result[i].original_path).ok());
+        let scores: HashMap<usize, Option<f64>> = if options.parallel {
+            indices.par_iter().map(score).collect()
+        } else {
+            indices.iter().map(score).collect()
+        };
+        burst::mark_best_frames(&mut burst_groups, |i| scores.get(&i).copied().flatten());
+    }
 
     // バースト情報をMediaInfoに反映
     for group in &burst_groups {
@@ -543,22 +1092,36 @@ pub fn scan_media(input_dir: &Path, options: &ProcessOptions) -> Result<Vec<Medi
             if let Some(media_info) = result.get_mut(photo_idx) {
                 media_info.burst_group_id = Some(group.id);
                 media_info.burst_index = Some(idx + 1); // 1始まり
+                media_info.burst_best = group.best_index == Some(photo_idx);
 
                 // ファイル名に連番を追加
-                if let Some(date) = media_info.date_taken {
-                    let extension = media_info.original_path
-                        .extension()
-                        .and_then(|e| e.to_str())
-                        .unwrap_or("jpg");
-
-                    // ベースファイル名を生成（拡張子なし）
-                    let base_name = if let Some(ms) = media_info.subsec_time {
-                        format!("{}-{:03}", date.format("%Y-%m-%d_%H-%M-%S"), ms)
-                    } else {
-                        date.format("%Y-%m-%d_%H-%M-%S").to_string()
-                    };
-
-                    media_info.new_name = format!("{}_{:02}.{}", base_name, idx + 1, extension);
+                if let Some(new_name) = render_new_name(media_info, &templates.file_name) {
+                    media_info.new_name = new_name;
+                }
+            }
+        }
+    }
+    sync_companion_names(&mut result);
+
+    // 類似写真のグループ化
+    if options.detect_similar {
+        let hashes: Vec<Option<u64>> = result
+            .iter()
+            .map(|m| m.perceptual_hash.as_deref().and_then(|h| u64::from_str_radix(h, 16).ok()))
+            .collect();
+        // 解像度が高いものを残す候補にする
+        let ranks: Vec<u64> = result
+            .iter()
+            .map(|m| u64::from(m.width.unwrap_or(0)) * u64::from(m.height.unwrap_or(0)))
+            .collect();
+        let similar_groups = similarity::detect_similar_groups(&hashes, &ranks, &SimilarityConfig::default());
+
+        for group in &similar_groups {
+            for (&photo_idx, &score) in group.photo_indices.iter().zip(&group.similarities) {
+                if let Some(media_info) = result.get_mut(photo_idx) {
+                    media_info.similar_group_id = Some(group.id);
+                    media_info.similarity = Some(score);
+                    media_info.similar_best = photo_idx == group.best_index;
                 }
             }
         }
@@ -567,200 +1130,1758 @@ pub fn scan_media(input_dir: &Path, options: &ProcessOptions) -> Result<Vec<Medi
     Ok(result)
 }
 
-/// YYYY/YYYY-MM/YYYY-MM-DD の階層構造を作成
-fn create_date_hierarchy(output_dir: &Path, date: &DateTime<Local>) -> Result<PathBuf> {
-    let year = date.format("%Y").to_string();
-    let year_month = date.format("%Y-%m").to_string();
-    let year_month_day = date.format("%Y-%m-%d").to_string();
+/// EXIFの秒未満の値（小数点以下の桁、"5" は0.5秒）をミリ秒に変換
+fn parse_subsec_millis(value: &str) -> Option<u32> {
+    let digits = value.trim();
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    format!("{:0<3}", &digits[..digits.len().min(3)]).parse().ok()
+}
 
-    let target_dir = output_dir
-        .join(&year)
-        .join(&year_month)
-        .join(&year_month_day);
-    fs::create_dir_all(&target_dir)?;
+/// バースト検出で撮影した機器を区別するキー（機種が分からない場合は元のフォルダ）
+fn burst_source(item: &MediaInfo) -> String {
+    match (&item.camera_make, &item.camera_model) {
+        (None, None) => item
+            .original_path
+            .parent()
+            .map(|dir| dir.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        (make, model) => format!(
+            "{} {}",
+            make.as_deref().unwrap_or(""),
+            model.as_deref().unwrap_or("")
+        ),
+    }
+}
 
-    Ok(target_dir)
+/// Live Photoの静止画と動画を検出し、動画を静止画の関連ファイルとして記録
+fn link_live_photos(media: &mut [MediaInfo]) {
+    let candidates: Vec<PairCandidate> = media
+        .iter()
+        .map(|m| PairCandidate {
+            path: &m.original_path,
+            is_video: m.media_type == MediaType::Video,
+            content_identifier: m.content_identifier.as_deref(),
+        })
+        .collect();
+    let pairs = live_photo::detect_live_photo_pairs(&candidates);
+
+    for pair in pairs {
+        let photo_path = media[pair.photo_index].original_path.clone();
+        media[pair.photo_index].pair_kind = Some(PairKind::LivePhoto);
+        let video = &mut media[pair.video_index];
+        video.pair_kind = Some(PairKind::LivePhoto);
+        video.companion_of = Some(photo_path);
+    }
 }
 
-/// バックアップを作成
-fn create_backup(original_path: &Path, backup_dir: &Path) -> Result<()> {
-    if let Some(file_name) = original_path.file_name() {
-        let backup_path = backup_dir.join(file_name);
+/// 同じ名前のRAWとJPEGを検出し、RAWをJPEGの関連ファイルとして記録
+fn link_raw_jpeg_pairs(media: &mut [MediaInfo]) {
+    let indices: Vec<usize> = media
+        .iter()
+        .enumerate()
+        .filter(|(_, m)| m.media_type == MediaType::Photo && m.companion_of.is_none() && m.pair_kind.is_none())
+        .map(|(index, _)| index)
+        .collect();
+    let paths: Vec<&Path> = indices.iter().map(|&i| media[i].original_path.as_path()).collect();
+    let pairs = raw::detect_raw_jpeg_pairs(&paths);
+
+    for pair in pairs {
+        let (jpeg_index, raw_index) = (indices[pair.jpeg_index], indices[pair.raw_index]);
+        let jpeg_path = media[jpeg_index].original_path.clone();
+        media[jpeg_index].pair_kind = Some(PairKind::RawJpeg);
+        let raw = &mut media[raw_index];
+        raw.pair_kind = Some(PairKind::RawJpeg);
+        raw.companion_of = Some(jpeg_path);
+    }
+}
 
-        // バックアップディレクトリが存在しない場合は作成
-        fs::create_dir_all(backup_dir)?;
+/// サイドカーを持ち主のメディアファイルに割り当てる（RAW+JPEGでは名前が同じサイドカーをRAWに付ける）
+fn attach_sidecars(media: &mut [MediaInfo], sidecar_files: &[PathBuf]) {
+    if sidecar_files.is_empty() {
+        return;
+    }
+    let mut owners: Vec<(usize, &Path)> = media
+        .iter()
+        .enumerate()
+        .map(|(index, m)| (index, m.original_path.as_path()))
+        .collect();
+    owners.sort_by_key(|(_, path)| !raw::is_raw_extension(&lowercase_extension(path)));
+
+    for (index, sidecars) in sidecar::match_sidecars(&owners, sidecar_files) {
+        media[index].sidecars = sidecars;
+    }
+}
+
+/// 小文字の拡張子
+fn lowercase_extension(path: &Path) -> String {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase()
+}
+
+/// 関連ファイルの名前を主ファイルの名前に揃える（拡張子だけ自分のもの）
+fn sync_companion_names(media: &mut [MediaInfo]) {
+    let primary_names: HashMap<PathBuf, String> = media
+        .iter()
+        .filter(|m| m.companion_of.is_none() && m.pair_kind.is_some())
+        .map(|m| (m.original_path.clone(), m.new_name.clone()))
+        .collect();
+
+    for item in media.iter_mut() {
+        if let Some(name) = item.companion_of.as_ref().and_then(|p| primary_names.get(p)) {
+            item.new_name = Path::new(name)
+                .with_extension(lowercase_extension(&item.original_path))
+                .to_string_lossy()
+                .into_owned();
+        }
+    }
+}
+
+/// 重複時のファイル名を生成（"name.jpg" → "name_01.jpg"）
+fn conflict_name(new_name: &str, counter: usize) -> String {
+    let path = Path::new(new_name);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or(new_name);
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) => format!("{}_{:02}.{}", stem, counter, ext),
+        None => format!("{}_{:02}", stem, counter),
+    }
+}
+
+/// create_dir_all で新たに作成されるディレクトリ（まだ存在しない祖先）を列挙
+fn missing_directories(target_dir: &Path) -> Vec<PathBuf> {
+    target_dir
+        .ancestors()
+        .take_while(|dir| !dir.as_os_str().is_empty() && !dir.exists())
+        .map(Path::to_path_buf)
+        .collect()
+}
+
+/// バックアップ先のパス（同じ実行の別ファイルや既存のバックアップと重なる場合は連番を付ける）
+fn backup_path_for(original_path: &Path, backup_dir: &Path, claimed: &mut HashSet<PathBuf>) -> Option<PathBuf> {
+    let name = original_path.file_name()?.to_string_lossy().into_owned();
+    let mut backup_path = backup_dir.join(&name);
+    let mut counter = 0;
+    while backup_path.exists() || claimed.contains(&backup_path) {
+        counter += 1;
+        backup_path = backup_dir.join(conflict_name(&name, counter));
+    }
+    claimed.insert(backup_path.clone());
+    Some(backup_path)
+}
 
-        // 既存のバックアップがある場合は上書き
-        fs::copy(original_path, backup_path)?;
+/// バックアップを作成
+fn create_backup(original_path: &Path, backup_path: &Path) -> Result<()> {
+    // バックアップディレクトリが存在しない場合は作成
+    if let Some(backup_dir) = backup_path.parent() {
+        fs::create_dir_all(backup_dir)?;
     }
+    fs::copy(original_path, backup_path)?;
     Ok(())
 }
 
-/// メディアファイルをリネームして階層構造にコピー
-pub fn process_media(input_dir: &Path, output_dir: &Path, options: &ProcessOptions) -> Result<ProcessResult> {
-    let mut media = scan_media(input_dir, options)?;
-    let total_files = media.len();
+/// rotation_mode から適用する向きの変換を取得（写真以外・RAW・回転不要はNormal）
+///
+/// `rotation_skip_reason` が回転を見送るファイルもNormalになります。
+fn rotation_transform(item: &MediaInfo, strategy: RotationStrategy) -> Orientation {
+    if rotation_skip_reason(item, strategy).is_some() {
+        return Orientation::Normal;
+    }
+    requested_transform(item, strategy)
+}
 
-    let errors = Arc::new(Mutex::new(Vec::new()));
-    let success_count = Arc::new(Mutex::new(0_usize));
+/// モーションフォトの回転を見送ったときの警告
+const MOTION_PHOTO_ROTATION_SKIPPED: &str =
+    "Rotation skipped to keep the embedded motion photo video; use the metadata rotation strategy instead";
+
+/// HEIFのメタデータ回転を見送ったときの警告
+const HEIF_METADATA_ROTATION_SKIPPED: &str =
+    "Rotation skipped: HEIF viewers ignore EXIF orientation (irot/imir), so the metadata strategy cannot rotate this file";
+
+/// 画素の回転でメタデータを引き継げない形式の回転を見送ったときの警告
+const PIXEL_ROTATION_UNSUPPORTED: &str =
+    "Rotation skipped: only JPEG and PNG can be rotated without losing metadata; use the metadata rotation strategy instead";
+
+/// 回転が必要だが見送るファイルの場合、その理由（警告として記録する）を返す
+///
+/// - 画素の回転でEXIF・GPS・ICCなどを引き継げるのはJPEGとPNGのみ（HEICはデコードもできない）
+/// - 画素の回転はモーションフォトの末尾の動画を失わせる
+/// - HEIFのビューアーはEXIF Orientationではなくirot/imirボックスで向きを決める
+fn rotation_skip_reason(item: &MediaInfo, strategy: RotationStrategy) -> Option<&'static str> {
+    if requested_transform(item, strategy).is_identity() {
+        return None;
+    }
+    let extension = lowercase_extension(&item.original_path);
+    match strategy {
+        RotationStrategy::Pixels if !matches!(extension.as_str(), "jpg" | "jpeg" | "png") => {
+            Some(PIXEL_ROTATION_UNSUPPORTED)
+        }
+        RotationStrategy::Pixels if item.embedded_video.is_some() => Some(MOTION_PHOTO_ROTATION_SKIPPED),
+        RotationStrategy::Metadata if matches!(extension.as_str(), "heic" | "heif") => {
+            Some(HEIF_METADATA_ROTATION_SKIPPED)
+        }
+        _ => None,
+    }
+}
 
-    let processor = |item: &mut MediaInfo| {
-        if let Some(date) = item.date_taken {
-            item.add_log(LogLevel::Info, format!("Processing started: {}", item.file_name));
-
-            // バックアップ作成
-            if let Some(ref backup_dir) = options.backup_dir {
-                if let Err(e) = create_backup(&item.original_path, backup_dir) {
-                    let msg = format!("Failed to backup {}: {}", item.original_path.display(), e);
-                    item.add_log(LogLevel::Error, &msg);
-                    errors.lock().unwrap().push(msg);
-                    return;
-                } else {
-                    item.add_log(LogLevel::Info, "Backup created successfully");
+/// rotation_mode が指定する変換（回転を見送るファイルも考慮しない）
+fn requested_transform(item: &MediaInfo, strategy: RotationStrategy) -> Orientation {
+    if item.media_type != MediaType::Photo || raw::is_raw_extension(&lowercase_extension(&item.original_path)) {
+        return Orientation::Normal;
+    }
+
+    match item.rotation_mode.as_deref().unwrap_or("none") {
+        // EXIFの向きはビューアーが解釈するため、メタデータ方式では書き換える必要がない
+        "exif" if strategy == RotationStrategy::Metadata => Orientation::Normal,
+        // EXIF orientationから変換を取得（反転を含む1〜8すべてに対応）
+        "exif" => match item.exif_orientation.map(Orientation::from) {
+            Some(Orientation::Unknown) | None => Orientation::Normal,
+            Some(transform) => transform,
+        },
+        mode => Orientation::from_mode(mode).unwrap_or(Orientation::Normal),
+    }
+}
+
+/// 出力先の解決結果
+struct ResolvedTarget {
+    target_dir: PathBuf,
+    target_path: PathBuf,
+    /// 名前衝突で付与した連番
+    counter: Option<usize>,
+    /// 同一内容のファイルが既に出力先に存在し、そのまま使う
+    reuse_existing: bool,
+    /// モーションフォトから抽出する動画の出力パス
+    embedded_video_path: Option<PathBuf>,
+    /// サイドカーごとの (元のパス, 出力パス)
+    sidecars: Vec<(PathBuf, PathBuf)>,
+    /// バックアップ先（バックアップなしはNone）
+    backup_path: Option<PathBuf>,
+}
+
+/// 関連ファイルの出力パス（主ファイルの出力パスの拡張子を自分のものに変える）
+fn companion_target(item: &MediaInfo, primary_target: &Path) -> PathBuf {
+    primary_target.with_extension(lowercase_extension(&item.original_path))
+}
+
+/// サイドカーごとの (元のパス, 出力パス)
+fn sidecar_targets(item: &MediaInfo, target_path: &Path) -> Vec<(PathBuf, PathBuf)> {
+    let targets = sidecar::sidecar_targets(&item.original_path, &item.sidecars, target_path);
+    item.sidecars.iter().cloned().zip(targets).collect()
+}
+
+/// 全ファイルの出力先を順番に決定
+///
+/// 並列コピーの前に決めておくことで、同じ実行内での名前の取り合いを防ぎ、
+/// ドライランと実際の処理で同じ連番が付くようにします。
+/// Live Photoの動画やRAW+JPEGのRAWなどの関連ファイルは主ファイルと同じ名前（拡張子だけ異なる）にし、
+/// 主ファイルの名前を決める際に関連ファイル・サイドカーの名前も空いているか確認します。
+/// バーストをサブフォルダにまとめる場合は、テンプレートで決まるディレクトリの下に `burst_<開始時刻>` を作ります。
+fn resolve_targets(
+    media: &[MediaInfo],
+    output_dir: &Path,
+    options: &ProcessOptions,
+    templates: &Templates,
+) -> Vec<Option<ResolvedTarget>> {
+    let duplicate_policy = options.duplicate_policy;
+    let extract_motion_photo_video = options.extract_motion_photo_video;
+    let burst_dirs = match options.burst_policy {
+        BurstPolicy::Subfolder => burst_directories(media),
+        _ => HashMap::new(),
+    };
+    let mut claimed = HashSet::new();
+    let is_skipped = |item: &MediaInfo| {
+        !item.selected
+            || (item.duplicate_status != DuplicateStatus::Unique && duplicate_policy == DuplicatePolicy::Skip)
+    };
+
+    // 関連ファイルの主ファイル（元のリスト内のインデックス）
+    let index_of: HashMap<&Path, usize> = media
+        .iter()
+        .enumerate()
+        .map(|(index, item)| (item.original_path.as_path(), index))
+        .collect();
+    let primary_of: Vec<Option<usize>> = media
+        .iter()
+        .map(|item| item.companion_of.as_deref().and_then(|path| index_of.get(path).copied()))
+        .collect();
+    let mut companions = vec![Vec::new(); media.len()];
+    for (index, (item, primary)) in media.iter().zip(&primary_of).enumerate() {
+        if let Some(primary) = primary.filter(|_| !is_skipped(item)) {
+            companions[primary].push(index);
+        }
+    }
+
+    // 出力パスから、一緒に出力するファイル（関連ファイル・サイドカー・抽出する動画）のパスを求める
+    let related_paths = |index: usize, target_path: &Path| {
+        let item = &media[index];
+        let mut paths: Vec<PathBuf> = sidecar_targets(item, target_path).into_iter().map(|(_, t)| t).collect();
+        if extract_motion_photo_video && item.embedded_video.is_some() {
+            paths.push(target_path.with_extension("mp4"));
+        }
+        for &companion in &companions[index] {
+            let companion_path = companion_target(&media[companion], target_path);
+            paths.extend(sidecar_targets(&media[companion], &companion_path).into_iter().map(|(_, t)| t));
+            paths.push(companion_path);
+        }
+        paths
+    };
+
+    let mut targets: Vec<Option<ResolvedTarget>> = Vec::with_capacity(media.len());
+    for (index, (item, primary)) in media.iter().zip(&primary_of).enumerate() {
+        if primary.is_some() || is_skipped(item) {
+            targets.push(None);
+            continue;
+        }
+        let related = |target_path: &Path| related_paths(index, target_path);
+        let burst_dir = item.burst_group_id.and_then(|id| burst_dirs.get(&id)).map(String::as_str);
+        let target = resolve_target(item, output_dir, options, templates, burst_dir, &related, &mut claimed)
+            .map(|mut target| {
+                if !target.reuse_existing {
+                    target.sidecars = sidecar_targets(item, &target.target_path);
+                    if extract_motion_photo_video && item.embedded_video.is_some() {
+                        target.embedded_video_path = Some(target.target_path.with_extension("mp4"));
+                    }
                 }
+                target
+            });
+        targets.push(target);
+    }
+
+    // 関連ファイルは主ファイルの出力先に揃える（主ファイルを出力しない場合は単独で決める）
+    for (index, primary) in primary_of.iter().enumerate() {
+        let Some(primary) = *primary else {
+            continue;
+        };
+        let item = &media[index];
+        if is_skipped(item) {
+            continue;
+        }
+        targets[index] = match targets[primary].as_ref().filter(|t| !t.reuse_existing) {
+            Some(primary_target) => {
+                let target_path = companion_target(item, &primary_target.target_path);
+                claimed.insert(target_path.clone());
+                Some(ResolvedTarget {
+                    target_dir: primary_target.target_dir.clone(),
+                    sidecars: sidecar_targets(item, &target_path),
+                    target_path,
+                    counter: primary_target.counter,
+                    reuse_existing: false,
+                    embedded_video_path: None,
+                    backup_path: None,
+                })
+            }
+            None => {
+                let related = |target_path: &Path| sidecar_targets(item, target_path).into_iter().map(|(_, t)| t).collect();
+                resolve_target(item, output_dir, options, templates, None, &related, &mut claimed)
+                    .map(|mut target| {
+                        if !target.reuse_existing {
+                            target.sidecars = sidecar_targets(item, &target.target_path);
+                        }
+                        target
+                    })
+            }
+        };
+    }
+
+    // バックアップ名は同じ名前の別の入力ファイルや既存のバックアップと重ならないようにする
+    if let Some(backup_dir) = &options.backup_dir {
+        let mut claimed_backups = HashSet::new();
+        for (item, target) in media.iter().zip(targets.iter_mut()) {
+            if let Some(target) = target.as_mut().filter(|t| !t.reuse_existing) {
+                target.backup_path = backup_path_for(&item.original_path, backup_dir, &mut claimed_backups);
             }
+        }
+    }
 
-            // 出力ディレクトリ作成
-            let target_dir = match create_date_hierarchy(output_dir, &date) {
-                Ok(dir) => {
-                    item.add_log(LogLevel::Info, format!("Created directory: {}", dir.display()));
-                    dir
-                },
-                Err(e) => {
-                    let msg = format!("Failed to create directory for {}: {}", item.original_path.display(), e);
-                    item.add_log(LogLevel::Error, &msg);
-                    errors.lock().unwrap().push(msg);
-                    return;
-                }
-            };
+    targets
+}
+
+/// 1ファイル分の出力先を決定し、使う名前を claimed に登録
+///
+/// `related_paths` が返す、一緒に出力するファイルのパスも空いている連番を選びます。
+/// `burst_dir` はバーストのサブフォルダ名です（テンプレートで決まるディレクトリの下に作る）。
+fn resolve_target(
+    item: &MediaInfo,
+    output_dir: &Path,
+    options: &ProcessOptions,
+    templates: &Templates,
+    burst_dir: Option<&str>,
+    related_paths: &dyn Fn(&Path) -> Vec<PathBuf>,
+    claimed: &mut HashSet<PathBuf>,
+) -> Option<ResolvedTarget> {
+    let uses_counter = templates.file_name.contains(template::Placeholder::Counter);
+    let mut target_dir = with_template_context(item, None, |context| {
+        templates.directory.render_directory(output_dir, context)
+    })?;
+    if let Some(burst_dir) = burst_dir {
+        target_dir.push(burst_dir);
+    }
+    let has_custom_name = item.custom_name.as_deref().is_some_and(|n| !n.trim().is_empty());
+    let name_for = |counter: usize| {
+        if uses_counter && !has_custom_name {
+            // テンプレートの {counter} を1から順に増やす
+            with_template_context(item, Some(counter + 1), |context| templates.file_name.render(context))
+                .unwrap_or_else(|| item.new_name.clone())
+        } else if counter == 0 {
+            item.new_name.clone()
+        } else {
+            conflict_name(&item.new_name, counter)
+        }
+    };
+    let mut target_path = target_dir.join(name_for(0));
+
+    // リンク指定で、同一内容のファイルが既に同じ名前で存在する場合は何もしない
+    if options.duplicate_policy == DuplicatePolicy::Link
+        && item.duplicate_status == DuplicateStatus::InOutput
+        && item.duplicate_of.as_ref() == Some(&target_path)
+        && rotation_transform(item, options.rotation_strategy).is_identity()
+    {
+        return Some(ResolvedTarget {
+            target_dir,
+            target_path,
+            counter: None,
+            reuse_existing: true,
+            embedded_video_path: None,
+            sidecars: Vec::new(),
+            backup_path: None,
+        });
+    }
 
-            let mut target_path = target_dir.join(&item.new_name);
+    // 重複ファイル名の処理（連番追加）
+    let paths_for = |target_path: &Path| {
+        let mut paths = related_paths(target_path);
+        paths.push(target_path.to_path_buf());
+        paths
+    };
+    let mut counter = 0;
+    while paths_for(&target_path).iter().any(|p| p.exists() || claimed.contains(p)) {
+        counter += 1;
+        target_path = target_dir.join(name_for(counter));
+    }
+    claimed.extend(paths_for(&target_path));
+
+    Some(ResolvedTarget {
+        target_dir,
+        target_path,
+        counter: (counter > 0).then_some(counter),
+        reuse_existing: false,
+        embedded_video_path: None,
+        sidecars: Vec::new(),
+        backup_path: None,
+    })
+}
 
-            // 重複ファイル名の処理（連番追加）
-            let mut counter = 1;
-            while target_path.exists() {
-                let extension = item
-                    .original_path
-                    .extension()
-                    .and_then(|e| e.to_str())
-                    .unwrap_or("");
+/// 画像を回転・反転して上書き保存し、EXIF Orientationをリセット
+fn rotate_image_file(item: &mut MediaInfo, target_path: &Path, transform: Orientation) {
+    item.add_log(LogLevel::Info, format!("Applying rotation: {}", transform));
 
-                // ベースファイル名を生成（ミリ秒を含む場合と含まない場合）
-                let base_name = if let Some(ms) = item.subsec_time {
-                    format!("{}-{:03}", date.format("%Y-%m-%d_%H-%M-%S"), ms)
-                } else {
-                    date.format("%Y-%m-%d_%H-%M-%S").to_string()
-                };
+    // 回転して上書き保存（EXIF/ICCなどのメタデータは元のファイルから引き継ぐ）
+    if let Err(e) = orientation::rotate_image_file(target_path, transform) {
+        item.add_log(LogLevel::Error, format!("Failed to rotate image: {:#}", e));
+        return;
+    }
+    item.add_log(LogLevel::Info, "Image rotated and saved successfully");
+    item.rotation_applied = true;
+
+    // EXIF Orientationを1にリセット
+    if let Err(e) = orientation::reset_exif_orientation(target_path, transform) {
+        item.add_log(LogLevel::Warning, format!("Failed to reset EXIF orientation: {}", e));
+    } else {
+        item.add_log(LogLevel::Info, "EXIF orientation reset to Normal (1)");
+    }
+}
+
+/// EXIF Orientationだけを書き換えて向きを補正（画素はデコード・再エンコードしない）
+fn rotate_by_metadata(item: &mut MediaInfo, target_path: &Path, transform: Orientation) {
+    item.add_log(LogLevel::Info, format!("Applying rotation via EXIF orientation: {}", transform));
+
+    match orientation::compose_exif_orientation(target_path, transform) {
+        Ok(composed) => {
+            item.add_log(
+                LogLevel::Info,
+                format!("EXIF orientation set to {} ({})", composed.exif_value(), composed),
+            );
+            item.rotation_applied = true;
+        }
+        Err(e) => item.add_log(LogLevel::Error, format!("Failed to update EXIF orientation: {:#}", e)),
+    }
+}
 
-                let new_name = format!("{}_{:02}.{}", base_name, counter, extension);
-                target_path = target_dir.join(&new_name);
-                counter += 1;
+/// 転送方法に応じてファイルを出力先へ配置し、実際に使った方法を返す
+///
+/// ハードリンクは元ファイルと内容を共有するため、回転が必要なファイルはコピーに切り替えます。
+/// 移動は別デバイス間の場合、コピー後にハッシュで検証してから元ファイルを削除します。
+fn transfer_file(source: &Path, target: &Path, mode: TransferMode, needs_rotation: bool) -> Result<TransferMode> {
+    match mode {
+        TransferMode::Copy => {
+            copy_new_file(source, target)?;
+            Ok(TransferMode::Copy)
+        }
+        TransferMode::HardLink if needs_rotation => {
+            copy_new_file(source, target)?;
+            Ok(TransferMode::Copy)
+        }
+        TransferMode::HardLink => {
+            fs::hard_link(source, target)?;
+            Ok(TransferMode::HardLink)
+        }
+        TransferMode::Move => {
+            if move_within_device(source, target)? {
+                return Ok(TransferMode::Move);
             }
 
-            if counter > 1 {
-                item.add_log(LogLevel::Warning, format!("File name conflict detected, using counter: {}", counter - 1));
+            // 別デバイス間：コピー → 検証 → 元ファイル削除
+            copy_new_file(source, target)?;
+            if let Err(e) = verify_copy(source, target) {
+                let _ = fs::remove_file(target);
+                return Err(e);
             }
+            fs::remove_file(source)?;
+            Ok(TransferMode::Move)
+        }
+    }
+}
 
-            // ファイルをコピー
-            match fs::copy(&item.original_path, &target_path) {
-                Ok(_) => {
-                    item.new_path = target_path.clone();
-                    item.add_log(LogLevel::Info, format!("File copied successfully to: {}", target_path.display()));
-
-                    // 画像回転処理（rotation_modeに基づく）
-                    if item.media_type == MediaType::Photo {
-                        let rotation_mode = item.rotation_mode.as_deref().unwrap_or("none");
-
-                        if rotation_mode != "none" {
-                            // 回転角度を計算
-                            let degrees = match rotation_mode {
-                                "exif" => {
-                                    // EXIF orientationから角度を取得
-                                    if let Some(ori) = item.exif_orientation {
-                                        match ori {
-                                            1 => 0,
-                                            3 => 180,
-                                            6 => 90,
-                                            8 => 270,
-                                            _ => 0,
-                                        }
-                                    } else {
-                                        0
-                                    }
-                                }
-                                "90" => 90,
-                                "180" => 180,
-                                "270" => 270,
-                                _ => 0,
-                            };
-
-                            if degrees != 0 {
-                                item.add_log(LogLevel::Info, format!("Applying rotation: {}°", degrees));
-
-                                // 画像を開く
-                                match image::open(&target_path) {
-                                    Ok(img) => {
-                                        // 回転適用
-                                        let rotated = match degrees {
-                                            90 => img.rotate90(),
-                                            180 => img.rotate180(),
-                                            270 => img.rotate270(),
-                                            _ => img,
-                                        };
-
-                                        // 上書き保存
-                                        match rotated.save(&target_path) {
-                                            Ok(_) => {
-                                                item.add_log(LogLevel::Info, "Image rotated and saved successfully");
-                                                item.rotation_applied = true;
-
-                                                // EXIF Orientationを1にリセット
-                                                if let Err(e) = orientation::reset_exif_orientation(&target_path) {
-                                                    item.add_log(LogLevel::Warning, format!("Failed to reset EXIF orientation: {}", e));
-                                                } else {
-                                                    item.add_log(LogLevel::Info, "EXIF orientation reset to Normal (1)");
-                                                }
-                                            }
-                                            Err(e) => {
-                                                item.add_log(LogLevel::Error, format!("Failed to save rotated image: {}", e));
-                                            }
-                                        }
-                                    }
-                                    Err(e) => {
-                                        item.add_log(LogLevel::Error, format!("Failed to open image for rotation: {}", e));
-                                    }
-                                }
-                            }
-                        }
-                    }
+/// 同じファイルシステム内で移動し、別デバイス間で移動できなかった場合は false を返す
+///
+/// 出力先を決めてから転送するまでに同じ名前のファイルが作られても上書きしないよう、
+/// ハードリンクを作ってから元ファイルを削除します（ハードリンクに対応しないファイルシステムは直前に確認してリネーム）。
+fn move_within_device(source: &Path, target: &Path) -> Result<bool> {
+    match fs::hard_link(source, target) {
+        Ok(()) => {
+            if let Err(e) = fs::remove_file(source) {
+                let _ = fs::remove_file(target);
+                return Err(e.into());
+            }
+            Ok(true)
+        }
+        Err(e) if e.kind() == ErrorKind::CrossesDevices => Ok(false),
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => Err(e.into()),
+        Err(_) => {
+            if target.exists() {
+                anyhow::bail!("Target already exists: {}", target.display());
+            }
+            match fs::rename(source, target) {
+                Ok(()) => Ok(true),
+                Err(e) if e.kind() == ErrorKind::CrossesDevices => Ok(false),
+                Err(e) => Err(e.into()),
+            }
+        }
+    }
+}
 
-                    *success_count.lock().unwrap() += 1;
-                }
-                Err(e) => {
-                    let msg = format!("Failed to copy {}: {}", item.original_path.display(), e);
-                    item.add_log(LogLevel::Error, &msg);
-                    errors.lock().unwrap().push(msg);
+/// 出力先に新しいファイルとしてコピー（既に存在する場合は上書きせずエラー）
+fn copy_new_file(source: &Path, target: &Path) -> io::Result<()> {
+    let mut reader = fs::File::open(source)?;
+    let mut writer = fs::OpenOptions::new().write(true).create_new(true).open(target)?;
+    let copied = io::copy(&mut reader, &mut writer).and_then(|_| {
+        writer.set_permissions(reader.metadata()?.permissions())
+    });
+    if copied.is_err() {
+        drop(writer);
+        let _ = fs::remove_file(target);
+    }
+    copied
+}
+
+/// コピー先が元ファイルと同一内容か検証
+fn verify_copy(source: &Path, target: &Path) -> Result<()> {
+    let source_len = fs::metadata(source)?.len();
+    let target_len = fs::metadata(target)?.len();
+    if source_len != target_len {
+        anyhow::bail!("Size mismatch after copy ({} != {} bytes)", source_len, target_len);
+    }
+    if journal::hash_file(source)? != journal::hash_file(target)? {
+        anyhow::bail!("Hash mismatch after copy");
+    }
+    Ok(())
+}
+
+/// 転送方法のログ表記
+fn transfer_verb(mode: TransferMode) -> &'static str {
+    match mode {
+        TransferMode::Copy => "copied",
+        TransferMode::Move => "moved",
+        TransferMode::HardLink => "hard-linked",
+    }
+}
+
+/// メディアファイルをリネームして階層構造にコピー
+///
+/// 入力ディレクトリを再スキャンし、スキャン結果をそのまま計画として処理します。
+pub fn process_media(input_dir: &Path, output_dir: &Path, options: &ProcessOptions) -> Result<ProcessResult> {
+    process_media_with_control(input_dir, output_dir, options, &RunControl::default())
+}
+
+/// 進捗通知とキャンセルに対応した process_media
+pub fn process_media_with_control(
+    input_dir: &Path,
+    output_dir: &Path,
+    options: &ProcessOptions,
+    control: &RunControl,
+) -> Result<ProcessResult> {
+    let mut media = scan_media_with_control(input_dir, options, control)?;
+
+    // 行ごとの回転方法を選ぶ画面が無いため、自動修正する場合はEXIFの向きに従って回転する
+    if options.auto_correct_orientation {
+        for item in media.iter_mut().filter(|m| m.media_type == MediaType::Photo && m.rotation_mode.is_none()) {
+            item.rotation_mode = Some("exif".to_string());
+        }
+    }
+
+    process_plan_with_control(media, output_dir, options, control)
+}
+
+/// スキャン済みのメディアリスト（計画）をそのまま処理
+///
+/// 行ごとの日付ソース・TZオフセット・回転方法・手動ファイル名・選択状態を尊重し、
+/// 渡された行だけを処理します。選択解除された行はスキップされます。
+/// `options.dry_run` の場合はディスクに書き込まず、操作計画を `ProcessResult::plan` に返します。
+pub fn process_plan(media: Vec<MediaInfo>, output_dir: &Path, options: &ProcessOptions) -> Result<ProcessResult> {
+    process_plan_with_control(media, output_dir, options, &RunControl::default())
+}
+
+/// 進捗通知とキャンセルに対応した process_plan
+///
+/// キャンセルされた場合は未処理のファイルを残したまま、それまでの結果（ジャーナルを含む）を
+/// `cancelled: true` の `ProcessResult` として返します。
+pub fn process_plan_with_control(
+    mut media: Vec<MediaInfo>,
+    output_dir: &Path,
+    options: &ProcessOptions,
+    control: &RunControl,
+) -> Result<ProcessResult> {
+    let total_files = media.len();
+    let mut skipped_files = 0;
+    // テンプレートは何も書き込む前に検証
+    let templates = options.templates()?;
+
+    for item in media.iter_mut() {
+        if item.selected {
+            apply_user_overrides(item, &templates);
+        } else {
+            item.add_log(LogLevel::Info, "Skipped: deselected by user");
+            skipped_files += 1;
+        }
+    }
+    let skipped_burst_frames = apply_burst_policy(&mut media, options.burst_policy, &templates);
+    skipped_files += skipped_burst_frames.len();
+    sync_companion_names(&mut media);
+
+    // 重複検出（入力内・既存の出力ディレクトリ）
+    duplicate::detect_duplicates(&mut media, output_dir, options.parallel, control);
+    if control.is_cancelled() {
+        anyhow::bail!("Operation cancelled");
+    }
+    let mut duplicate_files = 0;
+    for item in media.iter_mut() {
+        let Some(duplicate_of) = item.duplicate_of.clone() else {
+            continue;
+        };
+        duplicate_files += 1;
+        item.add_log(
+            LogLevel::Warning,
+            format!("Identical content already exists: {}", duplicate_of.display()),
+        );
+        if options.duplicate_policy == DuplicatePolicy::Skip {
+            item.add_log(LogLevel::Info, "Skipped: duplicate file");
+            skipped_files += 1;
+        }
+    }
+
+    let targets = resolve_targets(&media, output_dir, options, &templates);
+
+    if options.dry_run {
+        let plan = build_operation_plan(&mut media, &targets, options);
+        let processed_files = plan.operations.len();
+        return Ok(ProcessResult {
+            success: processed_files > 0,
+            total_files,
+            processed_files,
+            skipped_files,
+            duplicate_files,
+            media,
+            errors: Vec::new(),
+            plan: Some(plan),
+            journal_path: None,
+            cancelled: false,
+            skipped_burst_frames,
+        });
+    }
+
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let success_count = Arc::new(Mutex::new(0_usize));
+    // ジャーナルは書き込む前に作成し、ファイルごとに完了した時点で追記する（途中で強制終了しても取り消せるように）
+    let journal = Mutex::new(JournalWriter::create(&Journal::new(output_dir))?);
+
+    let record = |item: &mut MediaInfo, outcome: std::result::Result<Vec<JournalEntry>, String>| match outcome {
+        Ok(entries) => {
+            let mut journal = journal.lock().unwrap();
+            for entry in &entries {
+                if let Err(e) = journal.append(entry) {
+                    errors.lock().unwrap().push(format!("Failed to write journal: {}", e));
                 }
             }
+            *success_count.lock().unwrap() += 1;
+        }
+        Err(msg) => {
+            item.add_log(LogLevel::Error, &msg);
+            errors.lock().unwrap().push(msg);
+        }
+    };
+
+    // 入力内の重複をリンクする場合は、元になるファイルの出力先が決まってから処理する
+    let is_deferred_link = |item: &MediaInfo| {
+        options.duplicate_policy == DuplicatePolicy::Link && item.duplicate_status == DuplicateStatus::InInput
+    };
+
+    let pending = targets
+        .iter()
+        .filter(|t| t.as_ref().is_some_and(|t| !t.reuse_existing))
+        .count();
+    let progress = control.phase(ProgressPhase::Processing, pending);
+
+    let processor = |(item, target): (&mut MediaInfo, &Option<ResolvedTarget>)| {
+        let Some(target) = target else {
+            return;
+        };
+        if is_deferred_link(item) || control.is_cancelled() {
+            return;
+        }
+        // 出力ディレクトリ内の既存ファイルへのリンク
+        let link_source = match (options.duplicate_policy, item.duplicate_status) {
+            (DuplicatePolicy::Link, DuplicateStatus::InOutput) => item.duplicate_of.clone(),
+            _ => None,
+        };
+        let outcome = process_item(item, target, link_source.as_deref(), options);
+        record(item, outcome);
+        if !target.reuse_existing {
+            progress.tick(&item.original_path);
         }
     };
 
     if options.parallel {
-        media.par_iter_mut().for_each(processor);
+        media.par_iter_mut().zip(targets.par_iter()).for_each(processor);
     } else {
-        media.iter_mut().for_each(processor);
+        media.iter_mut().zip(targets.iter()).for_each(processor);
+    }
+
+    // 入力内の重複：先に処理された同一ファイル（回転なし）へのハードリンクを作成
+    let produced: HashMap<PathBuf, PathBuf> = media
+        .iter()
+        .filter(|m| !m.new_path.as_os_str().is_empty() && !m.rotation_applied)
+        .map(|m| (m.original_path.clone(), m.new_path.clone()))
+        .collect();
+    for (item, target) in media.iter_mut().zip(targets.iter()) {
+        let Some(target) = target else {
+            continue;
+        };
+        if !is_deferred_link(item) || control.is_cancelled() {
+            continue;
+        }
+        let link_source = item.duplicate_of.as_ref().and_then(|first| produced.get(first)).cloned();
+        let outcome = process_item(item, target, link_source.as_deref(), options);
+        record(item, outcome);
+        progress.tick(&item.original_path);
+    }
+
+    let cancelled = control.is_cancelled();
+    if cancelled {
+        for (item, _) in media.iter_mut().zip(targets.iter()).filter(|(_, t)| t.is_some()) {
+            if item.new_path.as_os_str().is_empty() {
+                item.add_log(LogLevel::Warning, "Not processed: operation cancelled");
+            }
+        }
     }
 
     let processed_files = *success_count.lock().unwrap();
-    let errors_vec = Arc::try_unwrap(errors)
+    let mut errors_vec = Arc::try_unwrap(errors)
         .map(|mutex| mutex.into_inner().unwrap())
         .unwrap_or_else(|arc| arc.lock().unwrap().clone());
 
+    // ジャーナルを閉じる（何も書き込まなかった場合は削除）
+    let journal_path = match journal.into_inner().unwrap().finish() {
+        Ok(path) => path,
+        Err(e) => {
+            errors_vec.push(format!("Failed to write journal: {}", e));
+            None
+        }
+    };
+
     Ok(ProcessResult {
         success: processed_files > 0,
         total_files,
         processed_files,
+        skipped_files,
+        duplicate_files,
         media,
         errors: errors_vec,
+        plan: None,
+        journal_path,
+        cancelled,
+        skipped_burst_frames,
     })
 }
+
+/// 1ファイル分の処理（バックアップ・転送・回転）を実行し、ジャーナルエントリを返す
+///
+/// `link_source` が指定された場合は、内容が同一のそのファイルへのハードリンクを作成します。
+/// モーションフォトの動画を抽出した場合やサイドカーを転送した場合は、それらのエントリも返します。
+/// エラー時はログ用のメッセージを返します。
+fn process_item(
+    item: &mut MediaInfo,
+    target: &ResolvedTarget,
+    link_source: Option<&Path>,
+    options: &ProcessOptions,
+) -> std::result::Result<Vec<JournalEntry>, String> {
+    item.add_log(LogLevel::Info, format!("Processing started: {}", item.file_name));
+
+    // 同一内容のファイルが既に同じ名前で存在する
+    if target.reuse_existing {
+        item.new_path = target.target_path.clone();
+        item.add_log(LogLevel::Info, format!("Reusing identical file: {}", target.target_path.display()));
+        return Ok(Vec::new());
+    }
+
+    // バックアップ作成
+    let backup_path = target.backup_path.as_ref();
+    if let Some(backup_path) = backup_path {
+        if let Err(e) = create_backup(&item.original_path, backup_path) {
+            return Err(format!("Failed to backup {}: {}", item.original_path.display(), e));
+        }
+        item.add_log(LogLevel::Info, format!("Backup created: {}", backup_path.display()));
+    }
+
+    // 出力ディレクトリ作成
+    if let Err(e) = fs::create_dir_all(&target.target_dir) {
+        return Err(format!("Failed to create directory for {}: {}", item.original_path.display(), e));
+    }
+    item.add_log(LogLevel::Info, format!("Created directory: {}", target.target_dir.display()));
+
+    if let Some(counter) = target.counter {
+        item.add_log(LogLevel::Warning, format!("File name conflict detected, using counter: {}", counter));
+    }
+
+    // ファイルを転送（コピー/移動/ハードリンク）
+    // 回転する場合はリンク元まで書き換わるため、重複へのリンクは行わない
+    let target_path = &target.target_path;
+    let transform = rotation_transform(item, options.rotation_strategy);
+    let rotates = !transform.is_identity();
+    if let Some(reason) = rotation_skip_reason(item, options.rotation_strategy) {
+        item.add_log(LogLevel::Warning, reason);
+    }
+    let transferred = match link_source.filter(|_| !rotates) {
+        Some(source) => fs::hard_link(source, target_path)
+            .map(|_| TransferMode::HardLink)
+            .map_err(anyhow::Error::from),
+        None => transfer_file(&item.original_path, target_path, options.transfer_mode, rotates),
+    };
+    let used_mode = transferred
+        .map_err(|e| format!("Failed to transfer {}: {}", item.original_path.display(), e))?;
+
+    item.new_path = target_path.clone();
+    if options.transfer_mode == TransferMode::HardLink && used_mode != TransferMode::HardLink {
+        item.add_log(LogLevel::Warning, "Hard link not used because the image will be rotated");
+    }
+    item.add_log(
+        LogLevel::Info,
+        format!("File {} successfully to: {}", transfer_verb(used_mode), target_path.display()),
+    );
+
+    // モーションフォトの動画を抽出（回転で画像を書き換えると末尾の動画が失われるため先に行う）
+    let extracted = match (item.embedded_video, target.embedded_video_path.as_ref()) {
+        (Some(video), Some(video_path)) => match live_photo::extract_embedded_video(target_path, video, video_path) {
+            Ok(()) => {
+                item.add_log(LogLevel::Info, format!("Embedded video extracted to: {}", video_path.display()));
+                Some(video_path)
+            }
+            Err(e) => {
+                item.add_log(LogLevel::Warning, format!("Failed to extract embedded video: {}", e));
+                None
+            }
+        },
+        _ => None,
+    };
+
+    // 画像回転処理（rotation_modeに基づく）
+    if rotates {
+        match options.rotation_strategy {
+            RotationStrategy::Pixels => rotate_image_file(item, target_path, transform),
+            RotationStrategy::Metadata => rotate_by_metadata(item, target_path, transform),
+        }
+    }
+
+    // サイドカーを同じ名前で転送（失敗してもメディアファイル自体の処理は成功とする）
+    let mut transferred_sidecars = Vec::new();
+    for (sidecar, sidecar_target) in &target.sidecars {
+        match transfer_file(sidecar, sidecar_target, options.transfer_mode, false) {
+            Ok(mode) => {
+                item.add_log(
+                    LogLevel::Info,
+                    format!("Sidecar {} to: {}", transfer_verb(mode), sidecar_target.display()),
+                );
+                transferred_sidecars.push((sidecar, sidecar_target, mode));
+            }
+            Err(e) => item.add_log(
+                LogLevel::Warning,
+                format!("Failed to transfer sidecar {}: {}", sidecar.display(), e),
+            ),
+        }
+    }
+
+    // ジャーナルに記録（取り消し時に出力ファイルが変更されていないか確認するためハッシュを保存）
+    let mut entries = Vec::new();
+    let original_path = item.original_path.clone();
+    let outputs = std::iter::once((&original_path, target_path, used_mode, false))
+        .chain(extracted.map(|path| (&original_path, path, TransferMode::Copy, true)))
+        .chain(
+            transferred_sidecars
+                .into_iter()
+                .map(|(source, destination, mode)| (source, destination, mode, false)),
+        );
+    for (source, destination, mode, generated) in outputs {
+        let is_main = destination == target_path;
+        match journal::hash_file(destination) {
+            Ok(hash) => entries.push(JournalEntry {
+                source: source.clone(),
+                destination: destination.clone(),
+                hash,
+                rotation_applied: item.rotation_applied && is_main,
+                backup_path: backup_path.cloned().filter(|_| is_main),
+                generated,
+                transfer_mode: Some(mode),
+            }),
+            Err(e) => item.add_log(LogLevel::Warning, format!("Failed to hash output for journal: {}", e)),
+        }
+    }
+    Ok(entries)
+}
+
+/// ドライラン：ディスクに触れずに操作計画を組み立てる
+fn build_operation_plan(
+    media: &mut [MediaInfo],
+    targets: &[Option<ResolvedTarget>],
+    options: &ProcessOptions,
+) -> OperationPlan {
+    let mut directories = BTreeSet::new();
+    let mut operations = Vec::new();
+
+    for (item, target) in media.iter_mut().zip(targets) {
+        let Some(target) = target else {
+            continue;
+        };
+        if target.reuse_existing {
+            item.add_log(LogLevel::Info, format!("Dry run: would reuse identical file {}", target.target_path.display()));
+            continue;
+        }
+
+        directories.extend(missing_directories(&target.target_dir));
+
+        let transform = rotation_transform(item, options.rotation_strategy);
+        let rotates = !transform.is_identity();
+        if let Some(reason) = rotation_skip_reason(item, options.rotation_strategy) {
+            item.add_log(LogLevel::Warning, reason);
+        }
+        let backup_path = target.backup_path.clone();
+
+        let links_duplicate = options.duplicate_policy == DuplicatePolicy::Link
+            && item.duplicate_status != DuplicateStatus::Unique;
+        let mode = match options.transfer_mode {
+            _ if links_duplicate && !rotates => TransferMode::HardLink,
+            TransferMode::HardLink if rotates => TransferMode::Copy,
+            mode => mode,
+        };
+        item.add_log(
+            LogLevel::Info,
+            format!("Dry run: would be {} to {}", transfer_verb(mode), target.target_path.display()),
+        );
+        if let Some(counter) = target.counter {
+            item.add_log(LogLevel::Warning, format!("File name conflict detected, would use counter: {}", counter));
+        }
+        if let Some(video_path) = &target.embedded_video_path {
+            item.add_log(
+                LogLevel::Info,
+                format!("Dry run: would extract embedded video to {}", video_path.display()),
+            );
+        }
+        for (_, sidecar_target) in &target.sidecars {
+            item.add_log(
+                LogLevel::Info,
+                format!("Dry run: would transfer sidecar to {}", sidecar_target.display()),
+            );
+        }
+        if rotates {
+            item.add_log(LogLevel::Info, format!("Dry run: would rotate {}", transform));
+        }
+
+        operations.push(PlannedOperation {
+            source: item.original_path.clone(),
+            target_path: target.target_path.clone(),
+            transfer_mode: mode,
+            conflict_counter: target.counter,
+            rotation: rotates.then_some(transform),
+            backup_path,
+            embedded_video_path: target.embedded_video_path.clone(),
+            sidecar_paths: target.sidecars.iter().map(|(_, path)| path.clone()).collect(),
+        });
+    }
+
+    OperationPlan {
+        directories: directories.into_iter().collect(),
+        operations,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn jst(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<FixedOffset> {
+        FixedOffset::east_opt(9 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(year, month, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    fn sample_media(path: &Path, date: DateTime<FixedOffset>) -> MediaInfo {
+        MediaInfo {
+            original_path: path.to_path_buf(),
+            file_name: path.file_name().unwrap().to_string_lossy().to_string(),
+            media_type: MediaType::Photo,
+            date_taken: Some(date),
+            subsec_time: None,
+            timezone: None,
+            exif_date: Some(date),
+            filename_date: None,
+            file_created_date: None,
+            file_modified_date: None,
+            new_name: String::new(),
+            new_path: PathBuf::new(),
+            file_size: 0,
+            burst_group_id: None,
+            burst_index: None,
+            burst_best: false,
+            burst_id: None,
+            sequence_number: None,
+            date_source: DateSource::Exif,
+            video_wall_clock: false,
+            exif_orientation: None,
+            rotation_applied: false,
+            timezone_offset: None,
+            rotation_mode: None,
+            suggested_orientation: None,
+            orientation_confidence: None,
+            width: None,
+            height: None,
+            video_rotation: None,
+            video_codec: None,
+            frame_rate: None,
+            bitrate: None,
+            camera_make: None,
+            camera_model: None,
+            latitude: None,
+            longitude: None,
+            selected: true,
+            custom_name: None,
+            content_hash: None,
+            duplicate_status: DuplicateStatus::Unique,
+            duplicate_of: None,
+            perceptual_hash: None,
+            similar_group_id: None,
+            similarity: None,
+            similar_best: false,
+            pair_kind: None,
+            companion_of: None,
+            content_identifier: None,
+            embedded_video: None,
+            sidecars: Vec::new(),
+            logs: Vec::new(),
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("photo_returns_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_conflict_name() {
+        assert_eq!(conflict_name("2025-01-15_10-30-00.jpg", 1), "2025-01-15_10-30-00_01.jpg");
+        assert_eq!(conflict_name("custom", 2), "custom_02");
+    }
+
+    #[test]
+    fn test_parse_subsec_millis() {
+        assert_eq!(parse_subsec_millis("5"), Some(500));
+        assert_eq!(parse_subsec_millis("045"), Some(45));
+        assert_eq!(parse_subsec_millis("123456 "), Some(123));
+        assert_eq!(parse_subsec_millis(""), None);
+    }
+
+    #[test]
+    fn test_apply_user_overrides() {
+        let date = jst(2025, 1, 15, 10, 30);
+        let mut item = sample_media(Path::new("/in/IMG_0001.JPG"), date);
+
+        // EXIF日時（壁時計）は時刻をそのままに撮影地のオフセットを付け替える
+        item.timezone_offset = Some("Europe/Paris".to_string());
+        apply_user_overrides(&mut item, &Templates::default());
+        assert_eq!(item.new_name, "2025-01-15_10-30-00.jpg");
+        assert_eq!(item.date_taken.unwrap().to_rfc3339(), "2025-01-15T10:30:00+01:00");
+
+        // ファイルのタイムスタンプ（絶対時刻）は撮影地の時刻に変換
+        item.date_source = DateSource::FileModified;
+        item.file_modified_date = Some(date);
+        item.timezone_offset = Some("+02:00".to_string());
+        apply_user_overrides(&mut item, &Templates::default());
+        assert_eq!(item.new_name, "2025-01-15_03-30-00.jpg");
+
+        item.timezone_offset = Some("+99:00".to_string());
+        apply_user_overrides(&mut item, &Templates::default());
+        assert_eq!(item.new_name, "2025-01-15_10-30-00.jpg");
+        assert!(item.logs.iter().any(|l| l.message.contains("Invalid timezone")));
+
+        item.timezone_offset = None;
+        item.custom_name = Some("trip.jpg".to_string());
+        apply_user_overrides(&mut item, &Templates::default());
+        assert_eq!(item.new_name, "trip.jpg");
+
+        // 動画でも撮影地の時刻（iPhoneのcreationdateなど）は壁時計の時刻として扱う
+        let mut video = sample_media(Path::new("/in/IMG_0002.MOV"), date);
+        video.media_type = MediaType::Video;
+        video.exif_date = None;
+        video.video_wall_clock = true;
+        video.timezone_offset = Some("+02:00".to_string());
+        apply_user_overrides(&mut video, &Templates::default());
+        assert_eq!(video.new_name, "2025-01-15_10-30-00.mov");
+
+        // QuickTimeのmvhd（絶対時刻）は変換する
+        video.video_wall_clock = false;
+        video.date_taken = Some(date);
+        apply_user_overrides(&mut video, &Templates::default());
+        assert_eq!(video.new_name, "2025-01-15_03-30-00.mov");
+    }
+
+    #[test]
+    fn test_transfer_file_modes() {
+        let root = temp_dir("transfer");
+        let source = root.join("a.jpg");
+        fs::write(&source, b"photo").unwrap();
+
+        let linked = root.join("linked.jpg");
+        assert_eq!(transfer_file(&source, &linked, TransferMode::HardLink, false).unwrap(), TransferMode::HardLink);
+        // 回転が必要な場合はハードリンクせずコピー
+        let copied = root.join("copied.jpg");
+        assert_eq!(transfer_file(&source, &copied, TransferMode::HardLink, true).unwrap(), TransferMode::Copy);
+
+        // 転送までの間に作られた同じ名前のファイルは上書きしない
+        let existing = root.join("existing.jpg");
+        fs::write(&existing, b"other").unwrap();
+        for mode in [TransferMode::Copy, TransferMode::Move] {
+            assert!(transfer_file(&source, &existing, mode, false).is_err());
+        }
+        assert_eq!(fs::read(&existing).unwrap(), b"other");
+
+        let moved = root.join("moved.jpg");
+        assert_eq!(transfer_file(&source, &moved, TransferMode::Move, false).unwrap(), TransferMode::Move);
+        assert!(!source.exists());
+        assert_eq!(fs::read(&moved).unwrap(), b"photo");
+        assert_eq!(fs::read(&linked).unwrap(), b"photo");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_duplicate_policies() {
+        let root = temp_dir("duplicates");
+        let input = root.join("input");
+        let output = root.join("output");
+        fs::create_dir_all(&input).unwrap();
+
+        let date = jst(2025, 1, 15, 10, 30);
+        let day_dir = output.join("2025").join("2025-01").join("2025-01-15");
+        fs::create_dir_all(&day_dir).unwrap();
+        fs::write(day_dir.join("2025-01-15_10-30-00.jpg"), b"same").unwrap();
+
+        let already_imported = input.join("a.jpg");
+        let first = input.join("b.jpg");
+        let second = input.join("c.jpg");
+        fs::write(&already_imported, b"same").unwrap();
+        fs::write(&first, b"new").unwrap();
+        fs::write(&second, b"new").unwrap();
+
+        let plan = || {
+            vec![
+                sample_media(&already_imported, date),
+                sample_media(&first, date + chrono::Duration::seconds(1)),
+                sample_media(&second, date + chrono::Duration::seconds(2)),
+            ]
+        };
+
+        let skip = ProcessOptions {
+            parallel: false,
+            duplicate_policy: DuplicatePolicy::Skip,
+            ..Default::default()
+        };
+        let result = process_plan(plan(), &output, &skip).unwrap();
+        assert_eq!(result.duplicate_files, 2);
+        assert_eq!(result.processed_files, 1);
+        assert_eq!(result.media[0].duplicate_status, DuplicateStatus::InOutput);
+        assert_eq!(result.media[2].duplicate_status, DuplicateStatus::InInput);
+        assert_eq!(result.media[2].duplicate_of, Some(first.clone()));
+        undo_output(&result);
+
+        let link = ProcessOptions {
+            parallel: true,
+            duplicate_policy: DuplicatePolicy::Link,
+            ..Default::default()
+        };
+        let result = process_plan(plan(), &output, &link).unwrap();
+        assert_eq!(result.processed_files, 3);
+        // 既に同じ名前で存在するファイルはそのまま使う
+        assert_eq!(result.media[0].new_path, day_dir.join("2025-01-15_10-30-00.jpg"));
+        assert_eq!(fs::read(&result.media[2].new_path).unwrap(), b"new");
+        assert!(!day_dir.join("2025-01-15_10-30-00_01.jpg").exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_burst_policies() {
+        let root = temp_dir("burst_policies");
+        let input = root.join("input");
+        let output = root.join("output");
+        fs::create_dir_all(&input).unwrap();
+
+        let date = jst(2025, 1, 15, 10, 30);
+        let paths: Vec<PathBuf> = ["a.jpg", "b.jpg", "c.jpg", "c.dng"].iter().map(|n| input.join(n)).collect();
+        for (i, path) in paths.iter().enumerate() {
+            fs::write(path, [i as u8]).unwrap();
+        }
+        let plan = || {
+            let mut media: Vec<MediaInfo> = paths.iter().map(|p| sample_media(p, date)).collect();
+            media[2].date_taken = Some(date + chrono::Duration::seconds(1));
+            media[2].exif_date = media[2].date_taken;
+            for (i, item) in media.iter_mut().take(3).enumerate() {
+                item.burst_group_id = Some(1);
+                item.burst_index = Some(i + 1);
+            }
+            media[1].burst_best = true;
+            media[2].pair_kind = Some(PairKind::RawJpeg);
+            media[3].pair_kind = Some(PairKind::RawJpeg);
+            media[3].companion_of = Some(paths[2].clone());
+            media
+        };
+        let run = |burst_policy| {
+            let options = ProcessOptions {
+                parallel: false,
+                dry_run: true,
+                burst_policy,
+                ..Default::default()
+            };
+            process_plan(plan(), &output, &options).unwrap()
+        };
+        let targets = |result: &ProcessResult| -> Vec<PathBuf> {
+            result.plan.as_ref().unwrap().operations.iter().map(|op| op.target_path.clone()).collect()
+        };
+        let day_dir = output.join("2025").join("2025-01").join("2025-01-15");
+
+        // サブフォルダにまとめ、関連ファイルも同じフォルダに置く
+        let result = run(BurstPolicy::Subfolder);
+        let burst_dir = day_dir.join("burst_2025-01-15_10-30-00");
+        assert_eq!(
+            targets(&result),
+            vec![
+                burst_dir.join("2025-01-15_10-30-00_01.jpg"),
+                burst_dir.join("2025-01-15_10-30-00_02.jpg"),
+                burst_dir.join("2025-01-15_10-30-01_03.jpg"),
+                burst_dir.join("2025-01-15_10-30-01_03.dng"),
+            ]
+        );
+
+        // 残す候補だけを連番なしで出力し、残りは関連ファイルも含めて一覧する
+        let result = run(BurstPolicy::KeepBest);
+        assert_eq!(targets(&result), vec![day_dir.join("2025-01-15_10-30-00.jpg")]);
+        assert_eq!(result.skipped_files, 3);
+        let skipped: Vec<&PathBuf> = result.skipped_burst_frames.iter().map(|f| &f.path).collect();
+        assert_eq!(skipped, vec![&paths[0], &paths[2], &paths[3]]);
+        assert!(result.skipped_burst_frames.iter().all(|f| f.kept == paths[1]));
+
+        // ユーザーが選択を外した写真は残す対象にしない
+        let options = ProcessOptions {
+            parallel: false,
+            dry_run: true,
+            burst_policy: BurstPolicy::KeepFirst,
+            ..Default::default()
+        };
+        let mut media = plan();
+        media[0].selected = false;
+        let result = process_plan(media, &output, &options).unwrap();
+        assert_eq!(result.media[1].new_name, "2025-01-15_10-30-00.jpg");
+        assert_eq!(result.skipped_files, 3);
+        assert_eq!(result.skipped_burst_frames.len(), 2);
+
+        // グループ化を無視すると通常の写真と同じ名前になる
+        let result = run(BurstPolicy::Ignore);
+        assert_eq!(targets(&result)[0], day_dir.join("2025-01-15_10-30-00.jpg"));
+        assert_eq!(targets(&result)[1], day_dir.join("2025-01-15_10-30-00_01.jpg"));
+        assert!(result.media.iter().all(|m| m.burst_group_id.is_none()));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_backup_names_are_unique() {
+        let root = temp_dir("backup_names");
+        let output = root.join("output");
+        let backup = root.join("backup");
+        let date = jst(2025, 1, 15, 10, 30);
+
+        // 別のフォルダにある同じ名前の入力ファイル
+        let first = root.join("card1").join("IMG_0001.JPG");
+        let second = root.join("card2").join("IMG_0001.JPG");
+        for (path, body) in [(&first, b"first"), (&second, b"other")] {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, body).unwrap();
+        }
+        fs::create_dir_all(&backup).unwrap();
+        fs::write(backup.join("IMG_0001.JPG"), b"earlier run").unwrap();
+
+        let options = ProcessOptions {
+            parallel: true,
+            backup_dir: Some(backup.clone()),
+            ..Default::default()
+        };
+        let media = vec![sample_media(&first, date), sample_media(&second, date)];
+        let result = process_plan(media, &output, &options).unwrap();
+        assert_eq!(result.processed_files, 2);
+
+        assert_eq!(fs::read(backup.join("IMG_0001.JPG")).unwrap(), b"earlier run");
+        assert_eq!(fs::read(backup.join("IMG_0001_01.JPG")).unwrap(), b"first");
+        assert_eq!(fs::read(backup.join("IMG_0001_02.JPG")).unwrap(), b"other");
+        let journal = Journal::read(result.journal_path.as_ref().unwrap()).unwrap();
+        let mut backups: Vec<_> = journal.entries.iter().filter_map(|e| e.backup_path.clone()).collect();
+        backups.sort();
+        assert_eq!(backups, vec![backup.join("IMG_0001_01.JPG"), backup.join("IMG_0001_02.JPG")]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    fn undo_output(result: &ProcessResult) {
+        if let Some(path) = &result.journal_path {
+            journal::undo_run(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_dry_run_plan() {
+        let root = temp_dir("dry_run");
+        let input = root.join("input");
+        let output = root.join("output");
+        fs::create_dir_all(&input).unwrap();
+
+        let date = jst(2025, 1, 15, 10, 30);
+        let existing_dir = output.join("2025").join("2025-01").join("2025-01-15");
+        fs::create_dir_all(&existing_dir).unwrap();
+        fs::write(existing_dir.join("2025-01-15_10-30-00.jpg"), b"existing").unwrap();
+
+        let first = input.join("a.jpg");
+        let second = input.join("b.jpg");
+        fs::write(&first, b"a").unwrap();
+        fs::write(&second, b"b").unwrap();
+
+        let mut deselected = sample_media(&input.join("c.jpg"), date);
+        deselected.selected = false;
+        let mut other_day = sample_media(&input.join("d.jpg"), date + chrono::Duration::days(40));
+        other_day.rotation_mode = Some("90".to_string());
+
+        let plan = vec![sample_media(&first, date), sample_media(&second, date), deselected, other_day];
+        let options = ProcessOptions {
+            parallel: false,
+            dry_run: true,
+            ..Default::default()
+        };
+        let result = process_plan(plan, &output, &options).unwrap();
+        let plan = result.plan.unwrap();
+
+        assert_eq!(result.skipped_files, 1);
+        assert_eq!(plan.operations.len(), 3);
+        assert_eq!(plan.operations[0].target_path, existing_dir.join("2025-01-15_10-30-00_01.jpg"));
+        assert_eq!(plan.operations[1].target_path, existing_dir.join("2025-01-15_10-30-00_02.jpg"));
+        assert_eq!(plan.operations[2].rotation, Some(Orientation::Rotate90CW));
+        assert_eq!(
+            plan.directories,
+            vec![output.join("2025").join("2025-02"), output.join("2025").join("2025-02").join("2025-02-24")]
+        );
+        // ディスクには何も書き込まれない
+        assert!(!output.join("2025").join("2025-02").exists());
+        assert!(result.media.iter().all(|m| m.new_path.as_os_str().is_empty()));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_metadata_rotation_strategy() {
+        let root = temp_dir("metadata_rotation");
+        let output = root.join("output");
+        fs::create_dir_all(&root).unwrap();
+
+        let source = root.join("photo.jpg");
+        image::DynamicImage::new_rgb8(8, 4).save(&source).unwrap();
+        let original = fs::read(&source).unwrap();
+
+        let mut item = sample_media(&source, jst(2025, 1, 15, 10, 30));
+        item.rotation_mode = Some("90".to_string());
+        let mut exif_item = sample_media(&root.join("exif.jpg"), jst(2025, 1, 15, 11, 0));
+        exif_item.rotation_mode = Some("exif".to_string());
+        exif_item.exif_orientation = Some(6);
+        fs::write(&exif_item.original_path, &original).unwrap();
+        let mut heif_item = sample_media(&root.join("photo.heic"), jst(2025, 1, 15, 12, 0));
+        heif_item.rotation_mode = Some("90".to_string());
+        fs::write(&heif_item.original_path, b"heif").unwrap();
+
+        let options = ProcessOptions {
+            parallel: false,
+            rotation_strategy: RotationStrategy::Metadata,
+            ..Default::default()
+        };
+        let result = process_plan(vec![item, exif_item, heif_item], &output, &options).unwrap();
+        assert_eq!(result.processed_files, 3);
+
+        // 画素はそのままで、EXIF Orientationだけが書き換わる
+        let rotated = &result.media[0];
+        assert!(rotated.rotation_applied);
+        assert_eq!(image::open(&rotated.new_path).unwrap().width(), 8);
+        let info = orientation::get_orientation(&rotated.new_path).unwrap();
+        assert_eq!(info.orientation, Orientation::Rotate90CW);
+
+        // EXIFの向きはビューアーに任せるため、ファイルは変更しない
+        let untouched = &result.media[1];
+        assert!(!untouched.rotation_applied);
+        assert_eq!(fs::read(&untouched.new_path).unwrap(), original);
+
+        // HEIFはEXIFの向きが表示に使われないため、回転を見送って警告を残す
+        let heif = &result.media[2];
+        assert!(!heif.rotation_applied);
+        assert_eq!(fs::read(&heif.new_path).unwrap(), b"heif");
+        assert!(heif.logs.iter().any(|l| l.message == HEIF_METADATA_ROTATION_SKIPPED));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_rotation_skip_reason() {
+        let date = jst(2025, 1, 15, 10, 30);
+        let skip_reason = |name: &str, strategy| {
+            let mut item = sample_media(Path::new(name), date);
+            item.rotation_mode = Some("90".to_string());
+            rotation_skip_reason(&item, strategy)
+        };
+
+        assert_eq!(skip_reason("/in/a.jpg", RotationStrategy::Pixels), None);
+        assert_eq!(skip_reason("/in/a.PNG", RotationStrategy::Pixels), None);
+        for name in ["/in/a.heic", "/in/a.tif", "/in/a.webp"] {
+            assert_eq!(skip_reason(name, RotationStrategy::Pixels), Some(PIXEL_ROTATION_UNSUPPORTED));
+        }
+        assert_eq!(skip_reason("/in/a.tif", RotationStrategy::Metadata), None);
+        assert_eq!(skip_reason("/in/a.heic", RotationStrategy::Metadata), Some(HEIF_METADATA_ROTATION_SKIPPED));
+    }
+
+    #[test]
+    fn test_process_media_auto_orientation() {
+        let root = temp_dir("auto_orientation");
+        let input = root.join("input");
+        fs::create_dir_all(&input).unwrap();
+        let source = input.join("photo.jpg");
+        image::DynamicImage::new_rgb8(8, 4).save(&source).unwrap();
+        orientation::write_exif_orientation(&source, 6, None).unwrap();
+
+        // 自動修正しない場合はそのままコピー
+        let options = ProcessOptions {
+            parallel: false,
+            ..Default::default()
+        };
+        let result = process_media(&input, &root.join("kept"), &options).unwrap();
+        assert!(!result.media[0].rotation_applied);
+
+        // 自動修正する場合はEXIFの向きに従って画素を回転する
+        let options = ProcessOptions {
+            auto_correct_orientation: true,
+            ..options
+        };
+        let result = process_media(&input, &root.join("rotated"), &options).unwrap();
+        assert!(result.media[0].rotation_applied);
+        assert_eq!(image::open(&result.media[0].new_path).unwrap().width(), 4);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_custom_templates() {
+        let root = temp_dir("templates");
+        let input = root.join("input");
+        let output = root.join("output");
+        fs::create_dir_all(&input).unwrap();
+
+        let date = jst(2025, 1, 15, 10, 30);
+        let first = input.join("IMG_0001.JPG");
+        let second = input.join("IMG_0002.mov");
+        fs::write(&first, b"a").unwrap();
+        fs::write(&second, b"b").unwrap();
+
+        let mut photo = sample_media(&first, date);
+        photo.camera_model = Some("Pixel 8".to_string());
+        let mut video = sample_media(&second, date);
+        video.media_type = MediaType::Video;
+
+        let options = ProcessOptions {
+            parallel: false,
+            dry_run: true,
+            directory_template: "{media_type}/{camera_model}/{YYYY}{MM}".to_string(),
+            file_name_template: "{YYYY}{MM}{DD}_{original_stem}_{counter:3}.{ext}".to_string(),
+            ..Default::default()
+        };
+        let result = process_plan(vec![photo, video], &output, &options).unwrap();
+        let plan = result.plan.unwrap();
+
+        assert_eq!(
+            plan.operations[0].target_path,
+            output.join("photo").join("Pixel 8").join("202501").join("20250115_IMG_0001_001.jpg")
+        );
+        assert_eq!(
+            plan.operations[1].target_path,
+            output.join("video").join("Unknown").join("202501").join("20250115_IMG_0002_001.mov")
+        );
+
+        // 衝突時は {counter} を増やす
+        let existing = output.join("photo").join("Pixel 8").join("202501");
+        fs::create_dir_all(&existing).unwrap();
+        fs::write(existing.join("20250115_IMG_0001_001.jpg"), b"other").unwrap();
+        let mut photo = sample_media(&first, date);
+        photo.camera_model = Some("Pixel 8".to_string());
+        let plan = process_plan(vec![photo], &output, &options).unwrap().plan.unwrap();
+        assert_eq!(plan.operations[0].target_path, existing.join("20250115_IMG_0001_002.jpg"));
+
+        // 不正なテンプレートは何もせずにエラー
+        let invalid = ProcessOptions {
+            file_name_template: "{YYYY}/{unknown}.{ext}".to_string(),
+            ..Default::default()
+        };
+        assert!(process_plan(vec![sample_media(&first, date)], &output, &invalid).is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_live_photo_shares_base_name() {
+        let root = temp_dir("live_photo");
+        let input = root.join("input");
+        let output = root.join("output");
+        fs::create_dir_all(&input).unwrap();
+
+        let date = jst(2025, 1, 15, 10, 30);
+        let photo_path = input.join("IMG_0001.HEIC");
+        let video_path = input.join("IMG_0001.MOV");
+        fs::write(&photo_path, b"photo").unwrap();
+        fs::write(&video_path, b"video").unwrap();
+
+        let mut media = vec![sample_media(&photo_path, date), sample_media(&video_path, date)];
+        media[1].media_type = MediaType::Video;
+        // 動画の日時が静止画とずれていても同じ名前にする
+        media[1].date_taken = Some(date + chrono::Duration::seconds(1));
+        media[1].exif_date = media[1].date_taken;
+        link_live_photos(&mut media);
+        assert_eq!(media[0].pair_kind, Some(PairKind::LivePhoto));
+        assert_eq!(media[1].companion_of, Some(photo_path.clone()));
+
+        // 動画の名前だけが衝突する場合も、静止画と同じ連番を使う
+        let day_dir = output.join("2025").join("2025-01").join("2025-01-15");
+        fs::create_dir_all(&day_dir).unwrap();
+        fs::write(day_dir.join("2025-01-15_10-30-00.mov"), b"other").unwrap();
+
+        let options = ProcessOptions {
+            parallel: false,
+            ..Default::default()
+        };
+        let result = process_plan(media, &output, &options).unwrap();
+        assert_eq!(result.processed_files, 2);
+        assert_eq!(result.media[0].new_path, day_dir.join("2025-01-15_10-30-00_01.heic"));
+        assert_eq!(result.media[1].new_path, day_dir.join("2025-01-15_10-30-00_01.mov"));
+        assert_eq!(result.media[1].new_name, "2025-01-15_10-30-00.mov");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_raw_jpeg_pair_with_sidecar() {
+        let root = temp_dir("raw_jpeg");
+        let input = root.join("input");
+        let output = root.join("output");
+        fs::create_dir_all(&input).unwrap();
+
+        let date = jst(2025, 1, 15, 10, 30);
+        let jpeg_path = input.join("DSC0001.JPG");
+        let raw_path = input.join("DSC0001.NEF");
+        let sidecar_path = input.join("DSC0001.xmp");
+        fs::write(&jpeg_path, b"jpeg").unwrap();
+        fs::write(&raw_path, b"raw").unwrap();
+        fs::write(&sidecar_path, b"xmp").unwrap();
+
+        let mut media = vec![sample_media(&jpeg_path, date), sample_media(&raw_path, date)];
+        link_raw_jpeg_pairs(&mut media);
+        attach_sidecars(&mut media, std::slice::from_ref(&sidecar_path));
+        assert_eq!(media[0].pair_kind, Some(PairKind::RawJpeg));
+        assert_eq!(media[1].companion_of, Some(jpeg_path.clone()));
+        assert_eq!(media[1].sidecars, vec![sidecar_path.clone()]);
+
+        // サイドカーの名前だけが衝突する場合も、全員が同じ連番を使う
+        let day_dir = output.join("2025").join("2025-01").join("2025-01-15");
+        fs::create_dir_all(&day_dir).unwrap();
+        fs::write(day_dir.join("2025-01-15_10-30-00.xmp"), b"other").unwrap();
+
+        let options = ProcessOptions {
+            parallel: false,
+            ..Default::default()
+        };
+        let result = process_plan(media, &output, &options).unwrap();
+        assert_eq!(result.processed_files, 2);
+        assert_eq!(result.media[0].new_path, day_dir.join("2025-01-15_10-30-00_01.jpg"));
+        assert_eq!(result.media[1].new_path, day_dir.join("2025-01-15_10-30-00_01.nef"));
+        assert_eq!(fs::read(day_dir.join("2025-01-15_10-30-00_01.xmp")).unwrap(), b"xmp");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_scan_and_move_sidecars() {
+        let root = temp_dir("sidecars");
+        let input = root.join("input");
+        let output = root.join("output");
+        fs::create_dir_all(&input).unwrap();
+        fs::write(input.join("GX010001.MP4"), b"video").unwrap();
+        fs::write(input.join("GL010001.LRV"), b"low resolution").unwrap();
+        fs::write(input.join("GX010001.THM"), b"thumbnail").unwrap();
+
+        let options = ProcessOptions {
+            parallel: false,
+            transfer_mode: TransferMode::Move,
+            ..Default::default()
+        };
+        // 不正なバースト検出の設定はスキャンせずにエラー
+        let invalid = ProcessOptions {
+            burst: BurstDetectorConfig {
+                max_interval_seconds: -1e300,
+                ..Default::default()
+            },
+            ..options.clone()
+        };
+        assert!(scan_media(&input, &invalid).is_err());
+
+        let media = scan_media(&input, &options).unwrap();
+        assert_eq!(media.len(), 1);
+        assert_eq!(media[0].sidecars.len(), 2);
+
+        // サイドカーも動画と同じ名前で移動し、取り消すと元の場所に戻る
+        let result = process_plan(media, &output, &options).unwrap();
+        let video_path = &result.media[0].new_path;
+        assert_eq!(fs::read(video_path.with_extension("lrv")).unwrap(), b"low resolution");
+        assert_eq!(fs::read(video_path.with_extension("thm")).unwrap(), b"thumbnail");
+        assert!(!input.join("GL010001.LRV").exists());
+
+        undo_output(&result);
+        assert!(input.join("GL010001.LRV").exists());
+        assert!(input.join("GX010001.THM").exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_extract_motion_photo_video() {
+        let root = temp_dir("motion_photo");
+        let output = root.join("output");
+        let source = root.join("PXL_0001.MP.jpg");
+        let video = [&[0, 0, 0, 8][..], b"ftyp"].concat();
+        fs::write(&source, [b"jpeg".as_slice(), &video].concat()).unwrap();
+
+        let mut item = sample_media(&source, jst(2025, 1, 15, 10, 30));
+        item.pair_kind = Some(PairKind::MotionPhoto);
+        item.embedded_video = Some(EmbeddedVideo { offset: 4, length: 8 });
+        let options = ProcessOptions {
+            parallel: false,
+            extract_motion_photo_video: true,
+            ..Default::default()
+        };
+        let result = process_plan(vec![item], &output, &options).unwrap();
+        let extracted = result.media[0].new_path.with_extension("mp4");
+        assert_eq!(fs::read(&extracted).unwrap(), video);
+
+        // 取り消すと抽出した動画は削除され、元のファイルは残る
+        undo_output(&result);
+        assert!(!extracted.exists());
+        assert!(source.exists());
+
+        // 画素の回転は末尾の動画を失わせるため行わず、警告を残す
+        let mut item = result.media[0].clone();
+        item.original_path = source.clone();
+        item.rotation_mode = Some("90".to_string());
+        let options = ProcessOptions { parallel: false, ..Default::default() };
+        let result = process_plan(vec![item], &output, &options).unwrap();
+        let media = &result.media[0];
+        assert!(!media.rotation_applied);
+        assert_eq!(fs::read(&media.new_path).unwrap(), fs::read(&source).unwrap());
+        assert!(media.logs.iter().any(|l| l.message == MOTION_PHOTO_ROTATION_SKIPPED));
+        undo_output(&result);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_process_plan_progress_and_cancel() {
+        use crate::progress::{CancellationToken, ProgressCallback, ProgressEvent};
+
+        let root = temp_dir("progress");
+        let input = root.join("input");
+        let output = root.join("output");
+        fs::create_dir_all(&input).unwrap();
+
+        let date = jst(2025, 1, 15, 10, 30);
+        let files: Vec<PathBuf> = (0..3).map(|i| input.join(format!("{}.jpg", i))).collect();
+        for (i, file) in files.iter().enumerate() {
+            fs::write(file, format!("photo-{}", i)).unwrap();
+        }
+        let plan = || files.iter().map(|f| sample_media(f, date)).collect::<Vec<_>>();
+        let options = ProcessOptions {
+            parallel: false,
+            ..Default::default()
+        };
+
+        // 2ファイル目の書き込み完了でキャンセル
+        let cancel = CancellationToken::new();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let (sink, token) = (events.clone(), cancel.clone());
+        let callback: ProgressCallback = Arc::new(move |e: &ProgressEvent| {
+            sink.lock().unwrap().push((e.phase, e.index, e.total));
+            if e.phase == ProgressPhase::Processing && e.index == 2 {
+                token.cancel();
+            }
+        });
+        let control = RunControl::new(cancel, Some(callback));
+
+        let result = process_plan_with_control(plan(), &output, &options, &control).unwrap();
+
+        assert!(result.cancelled);
+        assert_eq!(result.processed_files, 2);
+        assert!(result.journal_path.is_some());
+        assert!(result.media[2].new_path.as_os_str().is_empty());
+        let events = events.lock().unwrap();
+        assert_eq!(events.iter().filter(|e| e.0 == ProgressPhase::Hashing).count(), 3);
+        assert_eq!(events.last(), Some(&(ProgressPhase::Processing, 2, 3)));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,142 @@
+/// 進捗通知とキャンセル制御
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// 処理の段階
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProgressPhase {
+    /// メタデータの読み取り
+    Scanning,
+    /// 重複検出用のハッシュ計算
+    Hashing,
+    /// コピー・回転などの書き込み
+    Processing,
+}
+
+/// 1ファイル分の進捗イベント
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProgressEvent {
+    pub phase: ProgressPhase,
+    /// 完了したファイル数（1始まり）
+    pub index: usize,
+    pub total: usize,
+    pub current_path: PathBuf,
+}
+
+/// キャンセル要求を共有するトークン
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// キャンセルを要求
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// キャンセルが要求されたか
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// 新しい操作の開始前に状態をリセット
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+/// 進捗の通知先
+pub type ProgressCallback = Arc<dyn Fn(&ProgressEvent) + Send + Sync>;
+
+/// 実行中の操作の制御（進捗通知とキャンセル）
+#[derive(Clone, Default)]
+pub struct RunControl {
+    pub cancel: CancellationToken,
+    progress: Option<ProgressCallback>,
+}
+
+impl RunControl {
+    pub fn new(cancel: CancellationToken, progress: Option<ProgressCallback>) -> Self {
+        Self { cancel, progress }
+    }
+
+    /// キャンセルが要求されたか
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    /// 段階ごとの進捗カウンタを開始
+    pub fn phase(&self, phase: ProgressPhase, total: usize) -> PhaseProgress<'_> {
+        PhaseProgress {
+            control: self,
+            phase,
+            total,
+            done: AtomicUsize::new(0),
+        }
+    }
+}
+
+/// 1つの段階の進捗カウンタ（並列処理から呼び出し可能）
+pub struct PhaseProgress<'a> {
+    control: &'a RunControl,
+    phase: ProgressPhase,
+    total: usize,
+    done: AtomicUsize,
+}
+
+impl PhaseProgress<'_> {
+    /// 1ファイル完了を通知
+    pub fn tick(&self, path: &Path) {
+        let index = self.done.fetch_add(1, Ordering::SeqCst) + 1;
+        if let Some(callback) = &self.control.progress {
+            callback(&ProgressEvent {
+                phase: self.phase,
+                index,
+                total: self.total,
+                current_path: path.to_path_buf(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_phase_progress_reports_each_file() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        let callback: ProgressCallback = Arc::new(move |e: &ProgressEvent| sink.lock().unwrap().push(e.clone()));
+        let control = RunControl::new(CancellationToken::new(), Some(callback));
+
+        let progress = control.phase(ProgressPhase::Scanning, 2);
+        progress.tick(Path::new("a.jpg"));
+        progress.tick(Path::new("b.jpg"));
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].index, 2);
+        assert_eq!(events[1].total, 2);
+        assert_eq!(events[1].current_path, PathBuf::from("b.jpg"));
+    }
+
+    #[test]
+    fn test_cancellation_token() {
+        let token = CancellationToken::new();
+        let shared = token.clone();
+        assert!(!token.is_cancelled());
+
+        shared.cancel();
+        assert!(token.is_cancelled());
+
+        token.reset();
+        assert!(!shared.is_cancelled());
+    }
+}
@@ -0,0 +1,308 @@
+/// カメラRAWファイルのEXIF読み取りとRAW+JPEGの組の検出
+///
+/// CR2・NEF・ARW・DNG・PEFはTIFFそのもの、ORF・RW2はTIFFヘッダーの識別子だけが異なる構造です。
+/// CR3はISOBMFFのmoov内のCanon独自ボックス（CMT1〜CMT4）に、RAFは埋め込みJPEGにEXIFを持ちます。
+use anyhow::{Context, Result};
+use exif::{Exif, In, Reader, Tag};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::orientation::{iso_boxes, read_be};
+
+/// 対応するRAWの拡張子
+pub const RAW_EXTENSIONS: &[&str] = &["cr2", "cr3", "nef", "arw", "raf", "orf", "rw2", "dng", "pef"];
+
+/// RAWと組にするJPEGの拡張子
+const PAIRED_JPEG_EXTENSIONS: &[&str] = &["jpg", "jpeg"];
+
+/// EXIFを探すファイル先頭の範囲（本体の画像データは読まない）
+const MAX_HEADER_SIZE: u64 = 4 * 1024 * 1024;
+
+/// CR3のメタデータを格納するmoov内のuuidボックス
+const CANON_METADATA_UUID: [u8; 16] = [
+    0x85, 0xc0, 0xb6, 0x87, 0x82, 0x0f, 0x11, 0xe0, 0x81, 0x11, 0xf4, 0xce, 0x46, 0x2b, 0x6a, 0x48,
+];
+
+/// RAFの先頭
+const RAF_MAGIC: &[u8] = b"FUJIFILMCCD-RAW ";
+
+/// Panasonic RW2の埋め込みJPEG（JpgFromRaw）
+const TAG_JPEG_FROM_RAW: u16 = 0x002E;
+
+/// RAWの拡張子か（小文字で渡す）
+pub fn is_raw_extension(extension: &str) -> bool {
+    RAW_EXTENSIONS.contains(&extension)
+}
+
+/// RAWファイルからEXIFを読み取る
+///
+/// CR3は本体（CMT1）とExif IFD（CMT2）が別のTIFF構造のため、複数のExifを返します。
+/// 先に見つかった値を優先してください。
+pub fn read_raw_exif(path: &Path) -> Result<Vec<Exif>> {
+    let mut file = fs::File::open(path).context("Failed to open RAW file")?;
+    let mut head = Vec::new();
+    file.by_ref().take(MAX_HEADER_SIZE).read_to_end(&mut head)?;
+
+    if head.starts_with(RAF_MAGIC) {
+        return read_raf(&mut file);
+    }
+    if head.get(4..12) == Some(b"ftypcrx ") {
+        return read_cr3(&head);
+    }
+
+    let exif = read_tiff(normalize_tiff_header(head)?)?;
+    // RW2はIFD0に撮影日時が無く、埋め込みJPEGのEXIFに記録されている
+    if exif.get_field(Tag::DateTimeOriginal, In::PRIMARY).is_none() {
+        let embedded = exif
+            .get_field(Tag(exif::Context::Tiff, TAG_JPEG_FROM_RAW), In::PRIMARY)
+            .and_then(|field| match field.value {
+                exif::Value::Undefined(ref jpeg, _) => Reader::new().read_from_container(&mut Cursor::new(jpeg)).ok(),
+                _ => None,
+            });
+        if let Some(embedded) = embedded {
+            return Ok(vec![embedded, exif]);
+        }
+    }
+    Ok(vec![exif])
+}
+
+/// ORF（"IIRO"・"IIRS"・"MMOR"）・RW2（"IIU\0"）の識別子を標準のTIFF（42）に置き換える
+fn normalize_tiff_header(mut data: Vec<u8>) -> Result<Vec<u8>> {
+    match data.get(0..4) {
+        Some(b"II*\0") | Some(b"MM\0*") => {}
+        Some(b"IIRO") | Some(b"IIRS") | Some(b"IIU\0") => data[2..4].copy_from_slice(&[0x2A, 0x00]),
+        Some(b"MMOR") => data[2..4].copy_from_slice(&[0x00, 0x2A]),
+        _ => anyhow::bail!("Unsupported RAW format"),
+    }
+    Ok(data)
+}
+
+/// ファイル先頭だけを読んだTIFF構造を解析（範囲外を指すタグは読み飛ばす）
+fn read_tiff(data: Vec<u8>) -> Result<Exif> {
+    let mut reader = Reader::new();
+    reader.continue_on_error(true);
+    reader
+        .read_raw(data)
+        .or_else(|e| e.distill_partial_result(|_| {}))
+        .context("Failed to parse RAW metadata")
+}
+
+/// RAFの埋め込みJPEG（オフセット84・長さ88、ビッグエンディアン）からEXIFを読み取る
+fn read_raf<R: Read + Seek>(reader: &mut R) -> Result<Vec<Exif>> {
+    let mut header = [0u8; 92];
+    reader.seek(SeekFrom::Start(0))?;
+    reader.read_exact(&mut header)?;
+    let offset = read_be(&header, 84, 4).unwrap_or(0);
+    let length = read_be(&header, 88, 4).unwrap_or(0).min(MAX_HEADER_SIZE);
+
+    let mut jpeg = Vec::new();
+    reader.seek(SeekFrom::Start(offset))?;
+    reader.take(length).read_to_end(&mut jpeg)?;
+    let exif = Reader::new()
+        .read_from_container(&mut Cursor::new(jpeg))
+        .context("Failed to parse RAF metadata")?;
+    Ok(vec![exif])
+}
+
+/// CR3のmoov/uuid内のCMT1（IFD0）・CMT2（Exif IFD）からEXIFを読み取る
+fn read_cr3(data: &[u8]) -> Result<Vec<Exif>> {
+    let metadata = iso_boxes(data, 0..data.len())
+        .find(|(kind, _)| kind == b"moov")
+        .and_then(|(_, moov)| {
+            iso_boxes(data, moov).find(|(kind, body)| {
+                kind == b"uuid" && data.get(body.start..body.start + 16) == Some(&CANON_METADATA_UUID)
+            })
+        })
+        .map(|(_, body)| body.start + 16..body.end)
+        .context("Canon metadata box not found")?;
+
+    let mut exifs = Vec::new();
+    for (kind, body) in iso_boxes(data, metadata) {
+        let tiff = &data[body];
+        let exif = match &kind {
+            b"CMT1" => read_tiff(tiff.to_vec()),
+            b"CMT2" => exif_ifd_as_tiff(tiff).context("Invalid CMT2").and_then(read_tiff),
+            _ => continue,
+        };
+        exifs.push(exif?);
+    }
+    // 撮影日時などのExif IFDの値を優先
+    exifs.reverse();
+    Ok(exifs)
+}
+
+/// Exif IFDを先頭のIFDとするTIFF構造（CR3のCMT2）を、IFD0からExifIFDPointerで参照する形に組み替える
+///
+/// そのままではExif IFDのタグがIFD0のタグとして扱われ、DateTimeOriginalなどとして読めないためです。
+fn exif_ifd_as_tiff(data: &[u8]) -> Option<Vec<u8>> {
+    let little_endian = match data.get(0..2)? {
+        b"II" => true,
+        b"MM" => false,
+        _ => return None,
+    };
+    let u16_bytes = |v: u16| if little_endian { v.to_le_bytes() } else { v.to_be_bytes() };
+    let u32_bytes = |v: u32| if little_endian { v.to_le_bytes() } else { v.to_be_bytes() };
+    let exif_ifd: [u8; 4] = data.get(4..8)?.try_into().ok()?;
+
+    let mut tiff = data.to_vec();
+    if !tiff.len().is_multiple_of(2) {
+        tiff.push(0);
+    }
+    let ifd0 = u32::try_from(tiff.len()).ok()?;
+    tiff[4..8].copy_from_slice(&u32_bytes(ifd0));
+    // エントリ1件（ExifIFDPointer、LONG、1個、元のIFDの位置）と次のIFDなし
+    tiff.extend(u16_bytes(1));
+    tiff.extend(u16_bytes(0x8769));
+    tiff.extend(u16_bytes(4));
+    tiff.extend(u32_bytes(1));
+    tiff.extend(exif_ifd);
+    tiff.extend(u32_bytes(0));
+    Some(tiff)
+}
+
+/// RAW+JPEGの組（元のリスト内のインデックス）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawJpegPair {
+    pub jpeg_index: usize,
+    pub raw_index: usize,
+}
+
+/// 同時に記録されたRAWとJPEGの組を検出
+///
+/// 同じフォルダにある同じファイル名（拡張子を除く、大文字小文字を区別しない）のRAWとJPEGを組にします。
+/// 結果はJPEGのインデックス順です。
+pub fn detect_raw_jpeg_pairs(paths: &[&Path]) -> Vec<RawJpegPair> {
+    let extension_of = |path: &Path| {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("")
+            .to_lowercase()
+    };
+    let stem_key = |path: &Path| {
+        let stem = path.file_stem()?.to_str()?.to_lowercase();
+        Some((path.parent()?.to_path_buf(), stem))
+    };
+
+    let mut raws = HashMap::new();
+    for (index, path) in paths.iter().enumerate() {
+        if is_raw_extension(&extension_of(path)) {
+            if let Some(key) = stem_key(path) {
+                raws.entry(key).or_insert(index);
+            }
+        }
+    }
+
+    let mut pairs: Vec<RawJpegPair> = paths
+        .iter()
+        .enumerate()
+        .filter(|(_, path)| PAIRED_JPEG_EXTENSIONS.contains(&extension_of(path).as_str()))
+        .filter_map(|(jpeg_index, path)| {
+            let raw_index = raws.remove(&stem_key(path)?)?;
+            Some(RawJpegPair { jpeg_index, raw_index })
+        })
+        .collect();
+    pairs.sort_by_key(|pair| pair.jpeg_index);
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATE: &[u8] = b"2024:05:01 12:34:56\0";
+
+    /// ASCIIのタグ1つだけを持つリトルエンディアンのTIFF構造
+    fn tiff_with_ascii(magic: &[u8; 4], tag: u16, value: &[u8]) -> Vec<u8> {
+        let mut data = magic.to_vec();
+        data.extend(8u32.to_le_bytes());
+        data.extend(1u16.to_le_bytes());
+        data.extend(tag.to_le_bytes());
+        data.extend(2u16.to_le_bytes());
+        data.extend((value.len() as u32).to_le_bytes());
+        data.extend(26u32.to_le_bytes());
+        data.extend(0u32.to_le_bytes());
+        data.extend(value);
+        data
+    }
+
+    fn iso_box(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        [((body.len() + 8) as u32).to_be_bytes().as_slice(), kind, body].concat()
+    }
+
+    fn date_of(exifs: &[Exif]) -> Option<String> {
+        exifs
+            .iter()
+            .find_map(|exif| exif.get_field(Tag::DateTimeOriginal, In::PRIMARY).or(exif.get_field(Tag::DateTime, In::PRIMARY)))
+            .map(|field| field.display_value().to_string())
+    }
+
+    fn write_temp(name: &str, data: &[u8]) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("photo_returns_raw_{}_{}", std::process::id(), name));
+        fs::write(&path, data).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_read_orf_exif() {
+        let path = write_temp("test.orf", &tiff_with_ascii(b"IIRO", 0x0132, DATE));
+        let exifs = read_raw_exif(&path).unwrap();
+        assert_eq!(date_of(&exifs).as_deref(), Some("2024-05-01 12:34:56"));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_read_cr3_exif() {
+        let cmt1 = tiff_with_ascii(b"II*\0", 0x010F, b"Canon\0");
+        let cmt2 = tiff_with_ascii(b"II*\0", 0x9003, DATE);
+        let uuid = [CANON_METADATA_UUID.as_slice(), &iso_box(b"CMT1", &cmt1), &iso_box(b"CMT2", &cmt2)].concat();
+        let file = [iso_box(b"ftyp", b"crx \0\0\0\x01crx isom"), iso_box(b"moov", &iso_box(b"uuid", &uuid))].concat();
+        let path = write_temp("test.cr3", &file);
+
+        let exifs = read_raw_exif(&path).unwrap();
+        assert_eq!(
+            exifs[0].get_field(Tag::DateTimeOriginal, In::PRIMARY).map(|f| f.display_value().to_string()),
+            Some("2024-05-01 12:34:56".to_string())
+        );
+        assert!(exifs.iter().any(|e| e.get_field(Tag::Make, In::PRIMARY).is_some()));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_read_raf_exif() {
+        let tiff = tiff_with_ascii(b"II*\0", 0x0132, DATE);
+        let app1 = [b"Exif\0\0".as_slice(), &tiff].concat();
+        let jpeg = [&[0xFF, 0xD8, 0xFF, 0xE1][..], &((app1.len() + 2) as u16).to_be_bytes(), &app1, &[0xFF, 0xD9]].concat();
+        let mut raf = RAF_MAGIC.to_vec();
+        raf.resize(84, 0);
+        raf.extend(100u32.to_be_bytes());
+        raf.extend((jpeg.len() as u32).to_be_bytes());
+        raf.resize(100, 0);
+        raf.extend(&jpeg);
+        let path = write_temp("test.raf", &raf);
+
+        assert_eq!(date_of(&read_raw_exif(&path).unwrap()).as_deref(), Some("2024-05-01 12:34:56"));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_detect_raw_jpeg_pairs() {
+        let paths = [
+            Path::new("/in/DSC0001.ARW"),
+            Path::new("/in/DSC0001.JPG"),
+            Path::new("/in/DSC0002.JPG"),
+            Path::new("/in/other/DSC0003.NEF"),
+            Path::new("/in/DSC0003.jpg"),
+            Path::new("/in/IMG_0004.cr3"),
+            Path::new("/in/img_0004.jpeg"),
+        ];
+        assert_eq!(
+            detect_raw_jpeg_pairs(&paths),
+            vec![
+                RawJpegPair { jpeg_index: 1, raw_index: 0 },
+                RawJpegPair { jpeg_index: 6, raw_index: 5 },
+            ]
+        );
+    }
+}
@@ -0,0 +1,210 @@
+/// サイドカーファイル（メディアファイルに付随する編集情報などのファイル）の検出と出力名
+///
+/// - XMP: 現像ソフトの編集情報（`DSC0001.xmp` / `DSC0001.NEF.xmp`）
+/// - AAE: iPhoneの写真の編集情報（`IMG_1234.AAE`、iOS 16以降は `IMG_O1234.AAE`）
+/// - THM / LRV: GoProのサムネイル・低解像度動画（`GX010001.THM`、`GL010001.LRV`）
+/// - SRT: DJIのドローンの飛行ログ字幕（`DJI_0001.SRT`）
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// サイドカーの拡張子
+pub const SIDECAR_EXTENSIONS: &[&str] = &["xmp", "aae", "thm", "lrv", "srt"];
+
+/// GoProの動画のファイル名の接頭辞（HERO6以降、`GH`: H.264、`GX`: HEVC）
+const GOPRO_VIDEO_PREFIXES: &[&str] = &["gh", "gx"];
+
+/// サイドカーの拡張子か（小文字で渡す）
+pub fn is_sidecar_extension(extension: &str) -> bool {
+    SIDECAR_EXTENSIONS.contains(&extension)
+}
+
+/// (フォルダ, 小文字の名前) の照合キー
+fn key(dir: &Path, name: &str) -> (PathBuf, String) {
+    (dir.to_path_buf(), name.to_lowercase())
+}
+
+/// サイドカーの持ち主の名前の候補（小文字、優先順）
+///
+/// 拡張子を除いた名前そのものに加え、機種ごとに持ち主と名前が異なる形を元に戻した名前を返します。
+fn owner_names(sidecar: &Path) -> Vec<String> {
+    let Some(stem) = sidecar.file_stem().and_then(|s| s.to_str()) else {
+        return Vec::new();
+    };
+    let stem = stem.to_lowercase();
+    let extension = sidecar
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    let mut names = vec![stem.clone()];
+    match extension.as_str() {
+        // iOS 16以降の元の写真に対する編集情報（IMG_O1234.AAE → IMG_1234）
+        "aae" => {
+            if let Some(number) = stem.strip_prefix("img_o") {
+                names.push(format!("img_{}", number));
+            }
+        }
+        // GoProの低解像度動画は GL、動画は GH / GX（GL010001.LRV → GH010001 / GX010001）
+        "lrv" | "thm" => {
+            if let Some(rest) = stem.strip_prefix("gl") {
+                names.extend(GOPRO_VIDEO_PREFIXES.iter().map(|prefix| format!("{}{}", prefix, rest)));
+            }
+        }
+        _ => {}
+    }
+    names
+}
+
+/// サイドカーを持ち主のメディアファイルに割り当てる
+///
+/// 同じフォルダで、拡張子を除いた名前が持ち主の名前（`DSC0001.xmp`）か
+/// 持ち主のファイル名（`DSC0001.NEF.xmp`）と一致するものを割り当てます（機種ごとの名前の違いは [`owner_names`]）。
+/// 同じ名前の持ち主が複数ある場合は `owners` の先頭に近い1ファイルだけに割り当てます（RAW+JPEGではRAWを先に渡す）。
+/// 戻り値は `owners` の各インデックスに割り当てたサイドカーです。
+pub fn match_sidecars(owners: &[(usize, &Path)], sidecars: &[PathBuf]) -> HashMap<usize, Vec<PathBuf>> {
+    let mut by_name: HashMap<(PathBuf, String), usize> = HashMap::new();
+    for &(index, path) in owners {
+        let Some(dir) = path.parent() else {
+            continue;
+        };
+        // ファイル名全体での一致を、拡張子を除いた名前での一致より優先する
+        if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+            by_name.entry(key(dir, name)).or_insert(index);
+        }
+    }
+    for &(index, path) in owners {
+        if let (Some(dir), Some(stem)) = (path.parent(), path.file_stem().and_then(|s| s.to_str())) {
+            by_name.entry(key(dir, stem)).or_insert(index);
+        }
+    }
+
+    let mut matched: HashMap<usize, Vec<PathBuf>> = HashMap::new();
+    for sidecar in sidecars {
+        let Some(dir) = sidecar.parent() else {
+            continue;
+        };
+        let owner = owner_names(sidecar)
+            .iter()
+            .find_map(|name| by_name.get(&key(dir, name)).copied());
+        if let Some(owner) = owner {
+            matched.entry(owner).or_default().push(sidecar.clone());
+        }
+    }
+    for sidecars in matched.values_mut() {
+        sidecars.sort();
+    }
+    matched
+}
+
+/// サイドカーの出力パス（持ち主の出力名に揃え、拡張子は小文字）
+///
+/// `DSC0001.NEF.xmp` のように持ち主のファイル名全体に続く名前は、出力でも同じ形（`<新しい名前>.nef.xmp`）にします。
+pub fn sidecar_target(owner: &Path, sidecar: &Path, owner_target: &Path) -> PathBuf {
+    let extension = sidecar
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    let follows_file_name = match (sidecar.file_stem(), owner.file_name()) {
+        (Some(stem), Some(name)) => stem.to_string_lossy().eq_ignore_ascii_case(&name.to_string_lossy()),
+        _ => false,
+    };
+
+    if follows_file_name {
+        let mut name = owner_target.file_name().unwrap_or_default().to_os_string();
+        name.push(".");
+        name.push(&extension);
+        owner_target.with_file_name(name)
+    } else {
+        owner_target.with_extension(extension)
+    }
+}
+
+/// 1ファイル分のサイドカーの出力パス
+///
+/// `IMG_1234.AAE` と `IMG_O1234.AAE` のように出力名が重なる場合は、
+/// 2つ目以降に元の名前を付け加えて（`<新しい名前>_IMG_O1234.aae`）別のファイルにします。
+pub fn sidecar_targets(owner: &Path, sidecars: &[PathBuf], owner_target: &Path) -> Vec<PathBuf> {
+    let mut used = HashSet::new();
+    sidecars
+        .iter()
+        .map(|sidecar| {
+            let target = sidecar_target(owner, sidecar, owner_target);
+            if used.insert(target.clone()) {
+                return target;
+            }
+            let stem = target.file_stem().unwrap_or_default().to_string_lossy();
+            let original = sidecar.file_stem().unwrap_or_default().to_string_lossy();
+            let extension = target.extension().unwrap_or_default().to_string_lossy();
+            let renamed = target.with_file_name(format!("{}_{}.{}", stem, original, extension));
+            used.insert(renamed.clone());
+            renamed
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match_sidecars() {
+        let raw = Path::new("/in/DSC0001.NEF");
+        let jpeg = Path::new("/in/DSC0001.JPG");
+        let other = Path::new("/in/DSC0002.JPG");
+        let sidecars = vec![
+            PathBuf::from("/in/DSC0001.xmp"),
+            PathBuf::from("/in/DSC0001.JPG.xmp"),
+            PathBuf::from("/in/dsc0002.XMP"),
+            PathBuf::from("/in/other/DSC0002.xmp"),
+        ];
+        // RAWを先に渡すと、拡張子なしの名前のサイドカーはRAWに割り当てる
+        let matched = match_sidecars(&[(5, raw), (3, jpeg), (7, other)], &sidecars);
+        assert_eq!(matched[&5], vec![PathBuf::from("/in/DSC0001.xmp")]);
+        assert_eq!(matched[&3], vec![PathBuf::from("/in/DSC0001.JPG.xmp")]);
+        assert_eq!(matched[&7], vec![PathBuf::from("/in/dsc0002.XMP")]);
+    }
+
+    #[test]
+    fn test_match_device_sidecars() {
+        let iphone = Path::new("/in/IMG_1234.HEIC");
+        let gopro = Path::new("/in/GX010001.MP4");
+        let drone = Path::new("/in/DJI_0001.MP4");
+        let sidecars = vec![
+            PathBuf::from("/in/IMG_O1234.AAE"),
+            PathBuf::from("/in/GX010001.THM"),
+            PathBuf::from("/in/GL010001.LRV"),
+            PathBuf::from("/in/DJI_0001.SRT"),
+            PathBuf::from("/in/GL010002.LRV"),
+        ];
+        let matched = match_sidecars(&[(0, iphone), (1, gopro), (2, drone)], &sidecars);
+        assert_eq!(matched[&0], vec![PathBuf::from("/in/IMG_O1234.AAE")]);
+        assert_eq!(
+            matched[&1],
+            vec![PathBuf::from("/in/GL010001.LRV"), PathBuf::from("/in/GX010001.THM")]
+        );
+        assert_eq!(matched[&2], vec![PathBuf::from("/in/DJI_0001.SRT")]);
+        assert_eq!(matched.len(), 3);
+    }
+
+    #[test]
+    fn test_sidecar_target() {
+        let target = Path::new("/out/2025-01-15_10-30-00_01.nef");
+        assert_eq!(
+            sidecar_target(Path::new("/in/DSC0001.NEF"), Path::new("/in/DSC0001.XMP"), target),
+            PathBuf::from("/out/2025-01-15_10-30-00_01.xmp")
+        );
+        assert_eq!(
+            sidecar_target(Path::new("/in/DSC0001.NEF"), Path::new("/in/DSC0001.NEF.xmp"), target),
+            PathBuf::from("/out/2025-01-15_10-30-00_01.nef.xmp")
+        );
+
+        // 出力名が重なるサイドカーは元の名前を付け加える
+        let sidecars = vec![PathBuf::from("/in/IMG_1234.AAE"), PathBuf::from("/in/IMG_O1234.AAE")];
+        assert_eq!(
+            sidecar_targets(Path::new("/in/IMG_1234.HEIC"), &sidecars, Path::new("/out/a.heic")),
+            vec![PathBuf::from("/out/a.aae"), PathBuf::from("/out/a_IMG_O1234.aae")]
+        );
+    }
+}
@@ -0,0 +1,219 @@
+/// 知覚ハッシュ（dHash）による類似写真のグループ化機能
+use anyhow::Result;
+use image::codecs::jpeg::JpegDecoder;
+use image::imageops::FilterType;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::BufReader;
+use std::path::Path;
+
+/// 類似グループID
+pub type SimilarGroupId = usize;
+
+/// 類似写真のグループ
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimilarGroup {
+    /// グループID
+    pub id: SimilarGroupId,
+    /// グループ内の写真のインデックス（元のリスト内）
+    pub photo_indices: Vec<usize>,
+    /// 残す候補の写真のインデックス（元のリスト内）
+    pub best_index: usize,
+    /// 各写真の残す候補との類似度（0.0〜1.0、photo_indicesと同じ順）
+    pub similarities: Vec<f32>,
+    /// グループ内の写真枚数
+    pub count: usize,
+}
+
+/// 類似判定の設定
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimilarityConfig {
+    /// 類似とみなす最大ハミング距離（64ビット中）
+    pub max_distance: u32,
+}
+
+impl Default for SimilarityConfig {
+    fn default() -> Self {
+        Self {
+            max_distance: 10, // 64ビット中10ビット以内
+        }
+    }
+}
+
+/// 画像の差分ハッシュ（dHash、64ビット）を計算
+///
+/// 9x8のグレースケールに縮小し、横方向に隣り合う画素の明暗を1ビットずつ並べます。
+/// 再圧縮・リサイズされた画像でもほぼ同じ値になります。
+pub fn dhash(img: &DynamicImage) -> u64 {
+    let small = img.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+    let mut hash = 0u64;
+
+    for y in 0..8 {
+        for x in 0..8 {
+            let left = small.get_pixel(x, y)[0];
+            let right = small.get_pixel(x + 1, y)[0];
+            hash = (hash << 1) | u64::from(left > right);
+        }
+    }
+
+    hash
+}
+
+/// 画像ファイルのdHashを計算（JPEGは縮小デコードで高速化）
+pub fn dhash_file(path: &Path) -> Result<u64> {
+    Ok(dhash(&load_preview(path)?))
+}
+
+/// 解析用に画像を読み込む（JPEGはDCTスケーリングで64px程度に縮小してデコード）
+pub fn load_preview(path: &Path) -> Result<DynamicImage> {
+    load_scaled(path, 64)
+}
+
+/// 画像を読み込む（JPEGはDCTスケーリングで `size` px以上の最小の大きさに縮小してデコード）
+pub fn load_scaled(path: &Path, size: u16) -> Result<DynamicImage> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    let img = if matches!(extension.as_str(), "jpg" | "jpeg") {
+        let reader = BufReader::new(fs::File::open(path)?);
+        let mut decoder = JpegDecoder::new(reader)?;
+        decoder.scale(size, size)?;
+        DynamicImage::from_decoder(decoder)?
+    } else {
+        image::open(path)?
+    };
+
+    Ok(img)
+}
+
+/// 2つのハッシュのハミング距離
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// ハミング距離から類似度（0.0〜1.0）を計算
+pub fn similarity(a: u64, b: u64) -> f32 {
+    1.0 - hamming_distance(a, b) as f32 / 64.0
+}
+
+/// 知覚ハッシュに基づいて類似写真のグループを検出
+///
+/// # Arguments
+/// * `hashes` - 各写真のdHash（計算できなかった写真はNone）
+/// * `ranks` - 残す候補を選ぶための評価値（解像度など、大きいほど良い）
+/// * `config` - 類似判定の設定
+///
+/// # Returns
+/// 2枚以上の写真を含む類似グループのリスト
+pub fn detect_similar_groups(
+    hashes: &[Option<u64>],
+    ranks: &[u64],
+    config: &SimilarityConfig,
+) -> Vec<SimilarGroup> {
+    // Union-Findで距離がしきい値以内のペアを連結
+    let mut parent: Vec<usize> = (0..hashes.len()).collect();
+
+    fn find(parent: &mut [usize], i: usize) -> usize {
+        let mut root = i;
+        while parent[root] != root {
+            root = parent[root];
+        }
+        parent[i] = root;
+        root
+    }
+
+    for (i, a) in hashes.iter().enumerate() {
+        let Some(a) = *a else { continue };
+        for (j, b) in hashes.iter().enumerate().skip(i + 1) {
+            let Some(b) = *b else { continue };
+            if hamming_distance(a, b) <= config.max_distance {
+                let (ri, rj) = (find(&mut parent, i), find(&mut parent, j));
+                if ri != rj {
+                    parent[rj] = ri;
+                }
+            }
+        }
+    }
+
+    // 連結成分ごとにまとめる（元の順序を維持）
+    let mut members: Vec<Vec<usize>> = vec![Vec::new(); hashes.len()];
+    for (i, hash) in hashes.iter().enumerate() {
+        if hash.is_some() {
+            let root = find(&mut parent, i);
+            members[root].push(i);
+        }
+    }
+
+    let mut groups = Vec::new();
+    for photo_indices in members.into_iter().filter(|m| m.len() >= 2) {
+        let best_index = *photo_indices
+            .iter()
+            .max_by_key(|&&i| (ranks.get(i).copied().unwrap_or(0), std::cmp::Reverse(i)))
+            .unwrap();
+        let best_hash = hashes[best_index].unwrap();
+        let similarities = photo_indices
+            .iter()
+            .map(|&i| similarity(best_hash, hashes[i].unwrap()))
+            .collect();
+
+        groups.push(SimilarGroup {
+            id: groups.len(),
+            count: photo_indices.len(),
+            photo_indices,
+            best_index,
+            similarities,
+        });
+    }
+
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GenericImageView, Rgb, RgbImage};
+
+    fn gradient(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+            let v = ((x * 255 / width) ^ (y * 64 / height)) as u8;
+            Rgb([v, v, v])
+        }))
+    }
+
+    #[test]
+    fn test_dhash_is_stable_across_resize() {
+        let original = gradient(640, 480);
+        let resized = original.resize_exact(160, 120, FilterType::Triangle);
+        assert_eq!(resized.dimensions(), (160, 120));
+
+        let distance = hamming_distance(dhash(&original), dhash(&resized));
+        assert!(distance <= 4, "distance = {}", distance);
+
+        let flipped = original.fliph();
+        assert!(hamming_distance(dhash(&original), dhash(&flipped)) > 10);
+    }
+
+    #[test]
+    fn test_detect_similar_groups() {
+        let hashes = vec![
+            Some(0b1111_0000u64),  // 0 - グループ1
+            Some(0b1111_0001u64),  // 1 - グループ1（1ビット差）
+            Some(u64::MAX),        // 2 - 単独
+            None,                  // 3 - ハッシュなし
+            Some(0b1111_0011u64),  // 4 - グループ1（2ビット差）
+        ];
+        let ranks = vec![100, 300, 100, 0, 200];
+
+        let groups = detect_similar_groups(&hashes, &ranks, &SimilarityConfig::default());
+
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].photo_indices, vec![0, 1, 4]);
+        assert_eq!(groups[0].best_index, 1); // 最も評価値が高い
+        assert_eq!(groups[0].similarities[1], 1.0);
+        assert_eq!(groups[0].similarities[0], 1.0 - 1.0 / 64.0);
+    }
+}
@@ -0,0 +1,413 @@
+/// 出力先ディレクトリ・ファイル名のテンプレート
+///
+/// `{YYYY}-{MM}-{DD}_{HH}-{mm}-{ss}[-{SSS}][_{burst_index}].{ext}` のように、
+/// `{...}` にプレースホルダー、`[...]` に値がない場合は丸ごと省略する部分を書きます。
+use anyhow::{bail, Result};
+use chrono::{DateTime, Datelike, FixedOffset, Timelike};
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+
+/// デフォルトのディレクトリテンプレート（YYYY/YYYY-MM/YYYY-MM-DD）
+pub const DEFAULT_DIRECTORY_TEMPLATE: &str = "{YYYY}/{YYYY}-{MM}/{YYYY}-{MM}-{DD}";
+
+/// デフォルトのファイル名テンプレート（YYYY-MM-DD_HH-mm-ss[-mmm][_NN].ext）
+pub const DEFAULT_FILE_NAME_TEMPLATE: &str = "{YYYY}-{MM}-{DD}_{HH}-{mm}-{ss}[-{SSS}][_{burst_index}].{ext}";
+
+/// プレースホルダーの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Placeholder {
+    /// 年（4桁）
+    Year,
+    /// 年（下2桁）
+    ShortYear,
+    /// 月（2桁）
+    Month,
+    /// 日（2桁）
+    Day,
+    /// 時（24時間、2桁）
+    Hour,
+    /// 分（2桁）
+    Minute,
+    /// 秒（2桁）
+    Second,
+    /// ミリ秒（3桁、EXIFにある場合のみ）
+    Millisecond,
+    /// カメラのメーカー
+    CameraMake,
+    /// カメラの機種名
+    CameraModel,
+    /// "photo" / "video"
+    MediaType,
+    /// 元ファイル名（拡張子なし）
+    OriginalStem,
+    /// バーストグループ内の番号（連続撮影のみ）
+    BurstIndex,
+    /// 拡張子（小文字、ドットなし）
+    Extension,
+    /// 名前衝突時の連番（1から）
+    Counter,
+}
+
+impl Placeholder {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "YYYY" => Self::Year,
+            "YY" => Self::ShortYear,
+            "MM" => Self::Month,
+            "DD" => Self::Day,
+            "HH" => Self::Hour,
+            "mm" => Self::Minute,
+            "ss" => Self::Second,
+            "SSS" => Self::Millisecond,
+            "camera_make" => Self::CameraMake,
+            "camera_model" => Self::CameraModel,
+            "media_type" => Self::MediaType,
+            "original_stem" => Self::OriginalStem,
+            "burst_index" => Self::BurstIndex,
+            "ext" => Self::Extension,
+            "counter" => Self::Counter,
+            _ => return None,
+        })
+    }
+
+    /// 桁数指定（`{counter:3}`）に対応する数値か
+    fn is_numeric(self) -> bool {
+        matches!(self, Self::BurstIndex | Self::Counter)
+    }
+}
+
+/// テンプレートの構成要素
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Value { placeholder: Placeholder, width: Option<usize> },
+    /// `[...]`：中の値が1つでも欠けていれば丸ごと省略
+    Optional(Vec<Segment>),
+}
+
+/// テンプレートに埋め込む値
+#[derive(Debug, Clone)]
+pub struct TemplateContext<'a> {
+    /// 撮影日時（撮影地の壁時計の時刻で埋め込む）
+    pub date: DateTime<FixedOffset>,
+    pub subsec: Option<u32>,
+    pub camera_make: Option<&'a str>,
+    pub camera_model: Option<&'a str>,
+    pub media_type: &'a str,
+    pub original_stem: &'a str,
+    pub burst_index: Option<usize>,
+    pub extension: &'a str,
+    pub counter: Option<usize>,
+}
+
+impl TemplateContext<'_> {
+    /// プレースホルダーの値（値がない場合はNone）
+    fn value(&self, placeholder: Placeholder, width: Option<usize>) -> Option<String> {
+        let number = |n: usize, default_width: usize| format!("{:0w$}", n, w = width.unwrap_or(default_width));
+        let text = |s: Option<&str>| s.map(str::trim).filter(|s| !s.is_empty()).map(sanitize);
+
+        match placeholder {
+            Placeholder::Year => Some(format!("{:04}", self.date.year())),
+            Placeholder::ShortYear => Some(format!("{:02}", self.date.year().rem_euclid(100))),
+            Placeholder::Month => Some(format!("{:02}", self.date.month())),
+            Placeholder::Day => Some(format!("{:02}", self.date.day())),
+            Placeholder::Hour => Some(format!("{:02}", self.date.hour())),
+            Placeholder::Minute => Some(format!("{:02}", self.date.minute())),
+            Placeholder::Second => Some(format!("{:02}", self.date.second())),
+            Placeholder::Millisecond => self.subsec.map(|ms| format!("{:03}", ms)),
+            Placeholder::CameraMake => text(self.camera_make),
+            Placeholder::CameraModel => text(self.camera_model),
+            Placeholder::MediaType => text(Some(self.media_type)),
+            Placeholder::OriginalStem => text(Some(self.original_stem)),
+            Placeholder::BurstIndex => self.burst_index.map(|n| number(n, 2)),
+            Placeholder::Extension => text(Some(self.extension)),
+            Placeholder::Counter => self.counter.map(|n| number(n, 2)),
+        }
+    }
+}
+
+/// 値がない場合に `[...]` の外で使う文字列
+fn fallback(placeholder: Placeholder) -> &'static str {
+    match placeholder {
+        Placeholder::CameraMake | Placeholder::CameraModel => "Unknown",
+        _ => "",
+    }
+}
+
+/// ファイル名に使えない文字を置き換え
+fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+/// 解析済みのテンプレート
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathTemplate {
+    source: String,
+    segments: Vec<Segment>,
+}
+
+impl PathTemplate {
+    /// テンプレート文字列を解析
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut chars = source.chars();
+        let mut segments = Vec::new();
+        let mut optional: Option<Vec<Segment>> = None;
+        let mut literal = String::new();
+
+        fn flush(literal: &mut String, target: &mut Vec<Segment>) {
+            if !literal.is_empty() {
+                target.push(Segment::Literal(std::mem::take(literal)));
+            }
+        }
+
+        while let Some(c) = chars.next() {
+            let target = optional.as_mut().unwrap_or(&mut segments);
+            match c {
+                '{' => {
+                    flush(&mut literal, target);
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => bail!("Unclosed '{{' in template: {}", source),
+                        }
+                    }
+                    let (name, width) = match name.split_once(':') {
+                        Some((name, width)) => match width.parse::<usize>() {
+                            Ok(w) if (1..=9).contains(&w) => (name.to_string(), Some(w)),
+                            _ => bail!("Invalid width '{}' in template: {}", width, source),
+                        },
+                        None => (name, None),
+                    };
+                    let Some(placeholder) = Placeholder::parse(&name) else {
+                        bail!("Unknown placeholder '{{{}}}' in template: {}", name, source);
+                    };
+                    if width.is_some() && !placeholder.is_numeric() {
+                        bail!("Width is only supported for {{burst_index}} and {{counter}}: {}", source);
+                    }
+                    target.push(Segment::Value { placeholder, width });
+                }
+                '}' => bail!("Unexpected '}}' in template: {}", source),
+                '[' => {
+                    if optional.is_some() {
+                        bail!("Nested '[' is not supported in template: {}", source);
+                    }
+                    flush(&mut literal, &mut segments);
+                    optional = Some(Vec::new());
+                }
+                ']' => {
+                    let Some(mut inner) = optional.take() else {
+                        bail!("Unexpected ']' in template: {}", source);
+                    };
+                    flush(&mut literal, &mut inner);
+                    if !inner.iter().any(|s| matches!(s, Segment::Value { .. })) {
+                        bail!("Optional section without placeholder in template: {}", source);
+                    }
+                    segments.push(Segment::Optional(inner));
+                }
+                c => literal.push(c),
+            }
+        }
+
+        if optional.is_some() {
+            bail!("Unclosed '[' in template: {}", source);
+        }
+        flush(&mut literal, &mut segments);
+
+        Ok(Self {
+            source: source.to_string(),
+            segments,
+        })
+    }
+
+    /// ディレクトリ用テンプレートとして解析（"/" 区切り、相対パスのみ）
+    pub fn parse_directory(source: &str) -> Result<Self> {
+        let template = Self::parse(source)?;
+        if template.contains(Placeholder::Counter) {
+            bail!("{{counter}} can only be used in the file name template: {}", source);
+        }
+        let literal_path = Path::new(source);
+        if literal_path.is_absolute()
+            || source.starts_with('/')
+            || source.starts_with('\\')
+            || literal_path.components().any(|c| matches!(c, Component::ParentDir | Component::Prefix(_)))
+        {
+            bail!("Directory template must be a relative path inside the output directory: {}", source);
+        }
+        Ok(template)
+    }
+
+    /// ファイル名用テンプレートとして解析（区切り文字なし、拡張子必須）
+    pub fn parse_file_name(source: &str) -> Result<Self> {
+        let template = Self::parse(source)?;
+        if source.contains('/') || source.contains('\\') {
+            bail!("File name template must not contain path separators: {}", source);
+        }
+        if !template.contains(Placeholder::Extension) {
+            bail!("File name template must contain {{ext}}: {}", source);
+        }
+        if template.segments.iter().all(|s| !matches!(s, Segment::Value { .. })) {
+            bail!("File name template must contain a placeholder outside '[...]': {}", source);
+        }
+        Ok(template)
+    }
+
+    /// 元のテンプレート文字列
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// プレースホルダーを含むか（`[...]` 内も含む）
+    pub fn contains(&self, placeholder: Placeholder) -> bool {
+        self.segments.iter().any(|segment| match segment {
+            Segment::Value { placeholder: p, .. } => *p == placeholder,
+            Segment::Optional(inner) => inner
+                .iter()
+                .any(|s| matches!(s, Segment::Value { placeholder: p, .. } if *p == placeholder)),
+            Segment::Literal(_) => false,
+        })
+    }
+
+    /// 値を埋め込んで文字列を生成
+    pub fn render(&self, context: &TemplateContext) -> String {
+        let mut output = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) => output.push_str(text),
+                Segment::Value { placeholder, width } => {
+                    let value = context.value(*placeholder, *width);
+                    output.push_str(value.as_deref().unwrap_or(fallback(*placeholder)));
+                }
+                Segment::Optional(inner) => {
+                    let rendered: Option<String> = inner
+                        .iter()
+                        .map(|s| match s {
+                            Segment::Literal(text) => Some(text.clone()),
+                            Segment::Value { placeholder, width } => context.value(*placeholder, *width),
+                            Segment::Optional(_) => None,
+                        })
+                        .collect();
+                    output.push_str(rendered.as_deref().unwrap_or(""));
+                }
+            }
+        }
+        output
+    }
+
+    /// ディレクトリテンプレートから出力ディレクトリ以下のパスを生成（空の階層は詰める）
+    pub fn render_directory(&self, output_dir: &Path, context: &TemplateContext) -> PathBuf {
+        let rendered = self.render(context);
+        rendered
+            .split(['/', '\\'])
+            .map(str::trim)
+            .filter(|part| !part.is_empty() && *part != "." && *part != "..")
+            .fold(output_dir.to_path_buf(), |path, part| path.join(part))
+    }
+}
+
+/// ディレクトリ・ファイル名テンプレートの組
+#[derive(Debug, Clone)]
+pub struct Templates {
+    pub directory: PathTemplate,
+    pub file_name: PathTemplate,
+}
+
+impl Templates {
+    /// 両方のテンプレートを検証して解析
+    pub fn parse(directory: &str, file_name: &str) -> Result<Self> {
+        Ok(Self {
+            directory: PathTemplate::parse_directory(directory)?,
+            file_name: PathTemplate::parse_file_name(file_name)?,
+        })
+    }
+}
+
+impl Default for Templates {
+    fn default() -> Self {
+        Self::parse(DEFAULT_DIRECTORY_TEMPLATE, DEFAULT_FILE_NAME_TEMPLATE).expect("default templates are valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn context() -> TemplateContext<'static> {
+        TemplateContext {
+            date: FixedOffset::east_opt(9 * 3600)
+                .unwrap()
+                .with_ymd_and_hms(2025, 1, 15, 10, 30, 5)
+                .unwrap(),
+            subsec: None,
+            camera_make: Some("Canon"),
+            camera_model: Some("EOS R5 / Mark II"),
+            media_type: "photo",
+            original_stem: "IMG_0001",
+            burst_index: None,
+            extension: "jpg",
+            counter: None,
+        }
+    }
+
+    #[test]
+    fn test_default_templates_match_legacy_layout() {
+        let templates = Templates::default();
+        let mut ctx = context();
+
+        assert_eq!(
+            templates.directory.render_directory(Path::new("out"), &ctx),
+            Path::new("out").join("2025").join("2025-01").join("2025-01-15")
+        );
+        assert_eq!(templates.file_name.render(&ctx), "2025-01-15_10-30-05.jpg");
+
+        ctx.subsec = Some(42);
+        ctx.burst_index = Some(3);
+        assert_eq!(templates.file_name.render(&ctx), "2025-01-15_10-30-05-042_03.jpg");
+    }
+
+    #[test]
+    fn test_custom_placeholders() {
+        let mut ctx = context();
+        let dir = PathTemplate::parse_directory("{camera_model}/{YYYY}{MM}").unwrap();
+        assert_eq!(
+            dir.render_directory(Path::new("out"), &ctx),
+            Path::new("out").join("EOS R5 _ Mark II").join("202501")
+        );
+
+        ctx.camera_model = None;
+        assert_eq!(
+            dir.render_directory(Path::new("out"), &ctx),
+            Path::new("out").join("Unknown").join("202501")
+        );
+
+        let name = PathTemplate::parse_file_name("{YY}{MM}{DD}_{original_stem}[_{camera_model}]_{counter:3}.{ext}").unwrap();
+        ctx.counter = Some(7);
+        assert_eq!(name.render(&ctx), "250115_IMG_0001_007.jpg");
+        assert!(name.contains(Placeholder::Counter));
+    }
+
+    #[test]
+    fn test_invalid_templates() {
+        assert!(PathTemplate::parse("{YYYY").is_err());
+        assert!(PathTemplate::parse("{unknown}").is_err());
+        assert!(PathTemplate::parse("[{YYYY}").is_err());
+        assert!(PathTemplate::parse("[[{YYYY}]]").is_err());
+        assert!(PathTemplate::parse("[literal]").is_err());
+        assert!(PathTemplate::parse("{YYYY:3}").is_err());
+        assert!(PathTemplate::parse_directory("../{YYYY}").is_err());
+        assert!(PathTemplate::parse_directory("/{YYYY}").is_err());
+        assert!(PathTemplate::parse_directory("{YYYY}/{counter}").is_err());
+        assert!(PathTemplate::parse_file_name("{YYYY}/{DD}.{ext}").is_err());
+        assert!(PathTemplate::parse_file_name("{YYYY}{MM}{DD}").is_err());
+        assert!(PathTemplate::parse_file_name("photo.{ext}").is_ok());
+    }
+}
@@ -0,0 +1,376 @@
+/// EXIF（TIFF構造）のIFDを解析してタグを書き換える機能
+///
+/// JPEGのAPP1、PNGのeXIf、HEIFのExifアイテム、TIFFファイル本体はいずれも
+/// TIFFヘッダーから始まる同じ構造を持つため、このモジュールで共通に扱います。
+use anyhow::{bail, Context, Result};
+
+/// Orientation タグ番号
+pub const TAG_ORIENTATION: u16 = 0x0112;
+
+/// Exif IFDへのポインタのタグ番号
+const TAG_EXIF_IFD: u16 = 0x8769;
+
+/// PixelXDimension / PixelYDimension タグ番号（Exif IFD）
+const TAG_PIXEL_X_DIMENSION: u16 = 0xA002;
+const TAG_PIXEL_Y_DIMENSION: u16 = 0xA003;
+
+/// TIFFのSHORT型
+pub(crate) const TYPE_SHORT: u16 = 3;
+
+/// IFDエントリ1件のバイト数（タグ2 + 型2 + 個数4 + 値/オフセット4）
+pub(crate) const ENTRY_SIZE: usize = 12;
+
+/// 編集対象のIFD
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ifd {
+    /// IFD0（主画像）
+    Primary,
+    /// IFD1（サムネイル）
+    Thumbnail,
+}
+
+/// バイト列として保持したTIFF構造
+#[derive(Debug, Clone)]
+pub struct Tiff {
+    data: Vec<u8>,
+    little_endian: bool,
+}
+
+impl Tiff {
+    /// TIFFヘッダー（"II*\0" または "MM\0*"）から始まるバイト列を読み込む
+    pub fn parse(data: Vec<u8>) -> Result<Self> {
+        let little_endian = match data.get(0..4) {
+            Some(b"II\x2a\x00") => true,
+            Some(b"MM\x00\x2a") => false,
+            _ => bail!("Invalid TIFF header"),
+        };
+        if u32::try_from(data.len()).is_err() {
+            bail!("TIFF data is too large");
+        }
+
+        let tiff = Self { data, little_endian };
+        tiff.ifd_offset(Ifd::Primary)?.context("TIFF has no IFD0")?;
+        Ok(tiff)
+    }
+
+    /// Orientationタグだけを持つ最小のTIFF構造を作成
+    pub fn with_orientation(value: u16) -> Self {
+        let mut data = b"MM\x00\x2a\x00\x00\x00\x08".to_vec();
+        data.extend_from_slice(&1u16.to_be_bytes());
+        data.extend_from_slice(&TAG_ORIENTATION.to_be_bytes());
+        data.extend_from_slice(&TYPE_SHORT.to_be_bytes());
+        data.extend_from_slice(&1u32.to_be_bytes());
+        data.extend_from_slice(&value.to_be_bytes());
+        data.extend_from_slice(&[0, 0]);
+        data.extend_from_slice(&0u32.to_be_bytes());
+        Self { data, little_endian: false }
+    }
+
+    /// 編集後のバイト列を取得
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// 指定したIFDのOrientation値を取得
+    pub fn orientation(&self, ifd: Ifd) -> Option<u16> {
+        let offset = self.ifd_offset(ifd).ok()??;
+        let entry = self.find_entry(offset, TAG_ORIENTATION).ok()??;
+        match self.read_u16(entry + 2)? {
+            TYPE_SHORT => self.read_u16(entry + 8),
+            _ => None,
+        }
+    }
+
+    /// 指定したIFDにサムネイルなどのIFDが存在するか
+    pub fn has_ifd(&self, ifd: Ifd) -> bool {
+        matches!(self.ifd_offset(ifd), Ok(Some(_)))
+    }
+
+    /// 指定したIFDのOrientation値を書き換える（タグがなければ追加する）
+    ///
+    /// エントリを追加する場合はIFD全体を末尾へ移し、参照元のオフセットだけを付け替えます。
+    /// 他のデータは移動しないため、メーカーノートなどの絶対オフセットは壊れません。
+    pub fn set_orientation(&mut self, ifd: Ifd, value: u16) -> Result<()> {
+        let offset = self.ifd_offset(ifd)?.context("IFD not found")?;
+
+        if let Some(entry) = self.find_entry(offset, TAG_ORIENTATION)? {
+            // 型や個数が不正な場合もSHORT×1に揃える
+            self.write_u16(entry + 2, TYPE_SHORT);
+            self.write_u32(entry + 4, 1);
+            self.write_u32(entry + 8, 0);
+            self.write_u16(entry + 8, value);
+            return Ok(());
+        }
+
+        self.insert_entry(ifd, offset, TAG_ORIENTATION, value)
+    }
+
+    /// Exif IFDのPixelXDimensionとPixelYDimensionを入れ替える
+    ///
+    /// 90度単位の回転で幅と高さが入れ替わった画像に合わせるためのものです。
+    /// 値はエントリ内に収まるため、型・個数・値をエントリごと交換します。
+    /// Exif IFDやどちらかのタグが無い場合は何もしません。
+    pub fn swap_pixel_dimensions(&mut self) -> Result<()> {
+        let ifd0 = self.ifd_offset(Ifd::Primary)?.context("TIFF has no IFD0")?;
+        let Some(pointer) = self.find_entry(ifd0, TAG_EXIF_IFD)? else {
+            return Ok(());
+        };
+        let exif_ifd = self.read_u32(pointer + 8).context("Truncated Exif IFD pointer")? as usize;
+        let x = self.find_entry(exif_ifd, TAG_PIXEL_X_DIMENSION)?;
+        let y = self.find_entry(exif_ifd, TAG_PIXEL_Y_DIMENSION)?;
+        let (Some(x), Some(y)) = (x, y) else {
+            return Ok(());
+        };
+
+        let x_value = self.data[x + 2..x + ENTRY_SIZE].to_vec();
+        self.data.copy_within(y + 2..y + ENTRY_SIZE, x + 2);
+        self.data[y + 2..y + ENTRY_SIZE].copy_from_slice(&x_value);
+        Ok(())
+    }
+
+    /// IFDの開始位置を取得（IFD1が無い場合はNone）
+    fn ifd_offset(&self, ifd: Ifd) -> Result<Option<usize>> {
+        let ifd0 = self.read_u32(4).context("Truncated TIFF header")? as usize;
+        self.check_ifd(ifd0)?;
+        match ifd {
+            Ifd::Primary => Ok(Some(ifd0)),
+            Ifd::Thumbnail => {
+                let next = self.read_u32(self.next_pointer(ifd0)?).context("Truncated IFD0")? as usize;
+                if next == 0 || next == ifd0 {
+                    return Ok(None);
+                }
+                self.check_ifd(next)?;
+                Ok(Some(next))
+            }
+        }
+    }
+
+    /// IFDがデータ内に収まっているか検証
+    fn check_ifd(&self, offset: usize) -> Result<()> {
+        let count = self.read_u16(offset).context("IFD offset out of range")? as usize;
+        if offset + 2 + count * ENTRY_SIZE + 4 > self.data.len() {
+            bail!("Truncated IFD at offset {}", offset);
+        }
+        Ok(())
+    }
+
+    /// 次のIFDへのオフセットが格納されている位置
+    fn next_pointer(&self, offset: usize) -> Result<usize> {
+        let count = self.read_u16(offset).context("IFD offset out of range")? as usize;
+        Ok(offset + 2 + count * ENTRY_SIZE)
+    }
+
+    /// IFD内で指定タグのエントリ位置を探す
+    fn find_entry(&self, offset: usize, tag: u16) -> Result<Option<usize>> {
+        self.check_ifd(offset)?;
+        let count = self.read_u16(offset).unwrap_or(0) as usize;
+        Ok((0..count)
+            .map(|i| offset + 2 + i * ENTRY_SIZE)
+            .find(|&entry| self.read_u16(entry) == Some(tag)))
+    }
+
+    /// SHORT×1のエントリを追加したIFDを末尾に書き出し、参照元を付け替える
+    fn insert_entry(&mut self, ifd: Ifd, offset: usize, tag: u16, value: u16) -> Result<()> {
+        let count = self.read_u16(offset).unwrap_or(0) as usize;
+        let entries_start = offset + 2;
+        let entries_end = entries_start + count * ENTRY_SIZE;
+        let next_ifd = self.read_u32(entries_end).context("Truncated IFD")?;
+
+        // エントリはタグ番号順に並べる必要がある
+        let position = (0..count)
+            .find(|&i| self.read_u16(entries_start + i * ENTRY_SIZE).unwrap_or(0) > tag)
+            .unwrap_or(count);
+
+        let mut entry = [0u8; ENTRY_SIZE];
+        self.put_u16(&mut entry[0..2], tag);
+        self.put_u16(&mut entry[2..4], TYPE_SHORT);
+        self.put_u32(&mut entry[4..8], 1);
+        self.put_u16(&mut entry[8..10], value);
+
+        let split = entries_start + position * ENTRY_SIZE;
+        let mut relocated = Vec::with_capacity(2 + (count + 1) * ENTRY_SIZE + 4);
+        relocated.extend_from_slice(&[0, 0]);
+        self.put_u16(&mut relocated[0..2], (count + 1) as u16);
+        relocated.extend_from_slice(&self.data[entries_start..split]);
+        relocated.extend_from_slice(&entry);
+        relocated.extend_from_slice(&self.data[split..entries_end]);
+        relocated.extend_from_slice(&[0; 4]);
+        self.put_u32(&mut relocated[2 + (count + 1) * ENTRY_SIZE..], next_ifd);
+
+        // IFDはワード境界に置く
+        if self.data.len() % 2 == 1 {
+            self.data.push(0);
+        }
+        let new_offset = self.data.len();
+        self.data.extend_from_slice(&relocated);
+        let new_offset = u32::try_from(new_offset).context("TIFF data is too large")?;
+
+        let pointer = match ifd {
+            Ifd::Primary => 4,
+            Ifd::Thumbnail => {
+                let ifd0 = self.ifd_offset(Ifd::Primary)?.context("TIFF has no IFD0")?;
+                self.next_pointer(ifd0)?
+            }
+        };
+        self.write_u32(pointer, new_offset);
+        Ok(())
+    }
+
+    fn read_u16(&self, pos: usize) -> Option<u16> {
+        let bytes: [u8; 2] = self.data.get(pos..pos + 2)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    }
+
+    fn read_u32(&self, pos: usize) -> Option<u32> {
+        let bytes: [u8; 4] = self.data.get(pos..pos + 4)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    fn write_u16(&mut self, pos: usize, value: u16) {
+        let little_endian = self.little_endian;
+        put_u16(little_endian, &mut self.data[pos..pos + 2], value);
+    }
+
+    fn write_u32(&mut self, pos: usize, value: u32) {
+        let little_endian = self.little_endian;
+        put_u32(little_endian, &mut self.data[pos..pos + 4], value);
+    }
+
+    fn put_u16(&self, dst: &mut [u8], value: u16) {
+        put_u16(self.little_endian, dst, value);
+    }
+
+    fn put_u32(&self, dst: &mut [u8], value: u32) {
+        put_u32(self.little_endian, dst, value);
+    }
+}
+
+fn put_u16(little_endian: bool, dst: &mut [u8], value: u16) {
+    let bytes = if little_endian {
+        value.to_le_bytes()
+    } else {
+        value.to_be_bytes()
+    };
+    dst[..2].copy_from_slice(&bytes);
+}
+
+fn put_u32(little_endian: bool, dst: &mut [u8], value: u32) {
+    let bytes = if little_endian {
+        value.to_le_bytes()
+    } else {
+        value.to_be_bytes()
+    };
+    dst[..4].copy_from_slice(&bytes);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// IFD0（Make + Orientationなし）とIFD1を持つリトルエンディアンのTIFF
+    fn sample_tiff() -> Vec<u8> {
+        let mut data = b"II\x2a\x00\x08\x00\x00\x00".to_vec();
+        // IFD0: Make(0x010F, ASCII, 4, "Foo\0"), Orientationの値と同じバイト列を含む値
+        data.extend_from_slice(&1u16.to_le_bytes());
+        data.extend_from_slice(&0x010Fu16.to_le_bytes());
+        data.extend_from_slice(&2u16.to_le_bytes());
+        data.extend_from_slice(&4u32.to_le_bytes());
+        data.extend_from_slice(b"\x12\x01\x00\x00");
+        data.extend_from_slice(&26u32.to_le_bytes());
+        // IFD1（26バイト目）: ImageWidth(0x0100, SHORT, 1, 160)
+        data.extend_from_slice(&1u16.to_le_bytes());
+        data.extend_from_slice(&0x0100u16.to_le_bytes());
+        data.extend_from_slice(&TYPE_SHORT.to_le_bytes());
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&160u32.to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn test_insert_orientation_into_both_ifds() {
+        let original = sample_tiff();
+        let mut tiff = Tiff::parse(original.clone()).unwrap();
+        assert_eq!(tiff.orientation(Ifd::Primary), None);
+        assert!(tiff.has_ifd(Ifd::Thumbnail));
+
+        tiff.set_orientation(Ifd::Primary, 6).unwrap();
+        tiff.set_orientation(Ifd::Thumbnail, 8).unwrap();
+        assert_eq!(tiff.orientation(Ifd::Primary), Some(6));
+        assert_eq!(tiff.orientation(Ifd::Thumbnail), Some(8));
+
+        // 既存のデータ（Makeの値に含まれる0x0112）は書き換えない
+        let data = tiff.into_bytes();
+        assert_eq!(&data[18..22], b"\x12\x01\x00\x00");
+
+        // 追加済みのタグは同じ位置で更新される
+        let mut tiff = Tiff::parse(data).unwrap();
+        let len = tiff.clone().into_bytes().len();
+        tiff.set_orientation(Ifd::Primary, 1).unwrap();
+        assert_eq!(tiff.orientation(Ifd::Primary), Some(1));
+        assert_eq!(tiff.orientation(Ifd::Thumbnail), Some(8));
+        assert_eq!(tiff.into_bytes().len(), len);
+    }
+
+    #[test]
+    fn test_big_endian_and_invalid_data() {
+        let mut tiff = Tiff::with_orientation(3);
+        assert_eq!(tiff.orientation(Ifd::Primary), Some(3));
+        assert!(!tiff.has_ifd(Ifd::Thumbnail));
+        assert!(tiff.set_orientation(Ifd::Thumbnail, 1).is_err());
+
+        tiff.set_orientation(Ifd::Primary, 1).unwrap();
+        let data = tiff.into_bytes();
+        assert_eq!(&data[18..20], &[0, 1]);
+
+        assert!(Tiff::parse(b"Exif\0\0MM\0*".to_vec()).is_err());
+        assert!(Tiff::parse(b"II\x2a\x00\xff\x00\x00\x00".to_vec()).is_err());
+    }
+
+    #[test]
+    fn test_swap_pixel_dimensions() {
+        let mut data = b"II\x2a\x00\x08\x00\x00\x00".to_vec();
+        // IFD0: ExifIFDPointer(0x8769, LONG, 1, 26)
+        data.extend_from_slice(&1u16.to_le_bytes());
+        data.extend_from_slice(&TAG_EXIF_IFD.to_le_bytes());
+        data.extend_from_slice(&4u16.to_le_bytes());
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&26u32.to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+        // Exif IFD（26バイト目）: PixelXDimension(LONG, 4000), PixelYDimension(SHORT, 3000)
+        data.extend_from_slice(&2u16.to_le_bytes());
+        data.extend_from_slice(&TAG_PIXEL_X_DIMENSION.to_le_bytes());
+        data.extend_from_slice(&4u16.to_le_bytes());
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&4000u32.to_le_bytes());
+        data.extend_from_slice(&TAG_PIXEL_Y_DIMENSION.to_le_bytes());
+        data.extend_from_slice(&TYPE_SHORT.to_le_bytes());
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&3000u32.to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+
+        let mut tiff = Tiff::parse(data).unwrap();
+        tiff.swap_pixel_dimensions().unwrap();
+        let data = tiff.into_bytes();
+        // タグ番号はそのままで、型と値が入れ替わる
+        assert_eq!(&data[28..30], &TAG_PIXEL_X_DIMENSION.to_le_bytes());
+        assert_eq!(&data[30..32], &TYPE_SHORT.to_le_bytes());
+        assert_eq!(&data[36..40], &3000u32.to_le_bytes());
+        assert_eq!(&data[40..42], &TAG_PIXEL_Y_DIMENSION.to_le_bytes());
+        assert_eq!(&data[42..44], &4u16.to_le_bytes());
+        assert_eq!(&data[48..52], &4000u32.to_le_bytes());
+
+        // Exif IFDが無い場合は何もしない
+        let mut tiff = Tiff::parse(sample_tiff()).unwrap();
+        tiff.swap_pixel_dimensions().unwrap();
+        assert_eq!(tiff.into_bytes(), sample_tiff());
+    }
+}
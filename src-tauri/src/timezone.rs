@@ -0,0 +1,272 @@
+/// タイムゾーンの解決
+///
+/// 日時の情報源は2種類あります。
+/// - 壁時計の時刻（EXIF DateTimeOriginal、ファイル名）：どのタイムゾーンの時刻かを決めて解釈する
+/// - 絶対時刻（QuickTime作成日時、ファイルのタイムスタンプ）：どのタイムゾーンで表示するかを決めて変換する
+///
+/// どちらも撮影地のオフセット付きの `DateTime<FixedOffset>` にそろえ、
+/// ファイル名には撮影地の壁時計の時刻を使います。オフセットは日時ごとに求めるため、夏時間も正しく扱えます。
+use anyhow::{bail, Result};
+use chrono::{DateTime, FixedOffset, Local, LocalResult, NaiveDateTime, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// タイムゾーンの指定
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZoneSpec {
+    /// 実行環境のローカルタイム（夏時間を考慮）
+    Local,
+    /// 固定オフセット（"+09:00" など）
+    Fixed(FixedOffset),
+    /// IANAタイムゾーン（"Europe/Paris" など、夏時間を考慮）
+    Named(Tz),
+}
+
+impl ZoneSpec {
+    /// "+09:00" / "-0530" / "Z" / "UTC" / "local" / "Asia/Tokyo" 形式を解析
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if value.eq_ignore_ascii_case("local") {
+            return Some(Self::Local);
+        }
+        if value == "Z" {
+            return Some(Self::Fixed(Utc.fix()));
+        }
+        if let Some(offset) = parse_offset(value) {
+            return Some(Self::Fixed(offset));
+        }
+        value.parse::<Tz>().ok().map(Self::Named)
+    }
+
+    /// 秒単位のオフセットから作成
+    pub fn from_offset_seconds(seconds: i32) -> Option<Self> {
+        FixedOffset::east_opt(seconds).map(Self::Fixed)
+    }
+
+    /// 壁時計の時刻をこのタイムゾーンの時刻として解釈
+    ///
+    /// 夏時間の切り替えで2回ある時刻は早い方、存在しない時刻は切り替え後の時刻にします。
+    pub fn from_wall_clock(&self, naive: NaiveDateTime) -> DateTime<FixedOffset> {
+        match self {
+            Self::Local => resolve_local(Local.from_local_datetime(&naive), naive, |n| {
+                Local.from_local_datetime(&n)
+            }),
+            Self::Fixed(offset) => resolve_local(offset.from_local_datetime(&naive), naive, |n| {
+                offset.from_local_datetime(&n)
+            }),
+            Self::Named(tz) => resolve_local(tz.from_local_datetime(&naive), naive, |n| tz.from_local_datetime(&n)),
+        }
+    }
+
+    /// 絶対時刻をこのタイムゾーンの時刻に変換
+    pub fn from_instant<Z: TimeZone>(&self, instant: &DateTime<Z>) -> DateTime<FixedOffset> {
+        match self {
+            Self::Local => instant.with_timezone(&Local).fixed_offset(),
+            Self::Fixed(offset) => instant.with_timezone(offset),
+            Self::Named(tz) => instant.with_timezone(tz).fixed_offset(),
+        }
+    }
+}
+
+/// from_local_datetime の結果を1つに決める（存在しない時刻は1時間ずつ進めて探す）
+fn resolve_local<T: TimeZone>(
+    result: LocalResult<DateTime<T>>,
+    naive: NaiveDateTime,
+    retry: impl Fn(NaiveDateTime) -> LocalResult<DateTime<T>>,
+) -> DateTime<FixedOffset> {
+    let mut result = result;
+    let mut shifted = naive;
+    for _ in 0..3 {
+        match result {
+            LocalResult::Single(dt) | LocalResult::Ambiguous(dt, _) => return dt.fixed_offset(),
+            LocalResult::None => {
+                shifted += chrono::Duration::hours(1);
+                result = retry(shifted);
+            }
+        }
+    }
+    // 通常は到達しない：UTCとして扱う
+    Utc.from_utc_datetime(&naive).fixed_offset()
+}
+
+/// "+09:00" / "+0900" / "-05:30" / "+09" 形式のオフセットを解析
+pub fn parse_offset(value: &str) -> Option<FixedOffset> {
+    let value = value.trim();
+    let sign = match value.chars().next()? {
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+    let rest = &value[1..];
+    let (hours, minutes) = match rest.split_once(':') {
+        Some((h, m)) => (h, m),
+        None if rest.len() == 4 => rest.split_at(2),
+        None => (rest, "0"),
+    };
+    if hours.is_empty() || !hours.chars().all(|c| c.is_ascii_digit()) || !minutes.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let hours: i32 = hours.parse().ok()?;
+    let minutes: i32 = minutes.parse().ok()?;
+    if hours > 14 || minutes >= 60 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+/// 日時の情報源の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeKind {
+    /// タイムゾーンを持たない壁時計の時刻
+    WallClock,
+    /// 絶対時刻
+    Instant,
+}
+
+/// 実行全体のタイムゾーン設定に基づいて日時を解決
+#[derive(Debug, Clone, Copy)]
+pub struct TimezoneResolver {
+    /// 撮影地が分からない場合に使うタイムゾーン
+    home: ZoneSpec,
+}
+
+impl Default for TimezoneResolver {
+    fn default() -> Self {
+        Self { home: ZoneSpec::Local }
+    }
+}
+
+impl TimezoneResolver {
+    /// 実行全体の設定から作成（IANAタイムゾーン > 秒単位のオフセット > ローカルタイム）
+    pub fn new(timezone: Option<&str>, offset_seconds: Option<i32>) -> Result<Self> {
+        let home = match (timezone.map(str::trim).filter(|s| !s.is_empty()), offset_seconds) {
+            (Some(name), _) => match ZoneSpec::parse(name) {
+                Some(zone) => zone,
+                None => bail!("Unknown timezone: {}", name),
+            },
+            (None, Some(seconds)) => match ZoneSpec::from_offset_seconds(seconds) {
+                Some(zone) => zone,
+                None => bail!("Invalid timezone offset: {} seconds", seconds),
+            },
+            (None, None) => ZoneSpec::Local,
+        };
+        Ok(Self { home })
+    }
+
+    pub fn home(&self) -> ZoneSpec {
+        self.home
+    }
+
+    /// EXIFの壁時計の時刻を解決（OffsetTimeOriginalがあればそれを優先）
+    pub fn exif_time(&self, naive: NaiveDateTime, exif_offset: Option<&str>) -> DateTime<FixedOffset> {
+        match exif_offset.and_then(parse_offset) {
+            Some(offset) => ZoneSpec::Fixed(offset).from_wall_clock(naive),
+            None => self.home.from_wall_clock(naive),
+        }
+    }
+
+    /// ファイル名などの壁時計の時刻を解決
+    pub fn wall_clock(&self, naive: NaiveDateTime) -> DateTime<FixedOffset> {
+        self.home.from_wall_clock(naive)
+    }
+
+    /// 絶対時刻を撮影地の時刻に変換
+    pub fn instant<Z: TimeZone>(&self, instant: &DateTime<Z>) -> DateTime<FixedOffset> {
+        self.home.from_instant(instant)
+    }
+}
+
+/// 行ごとのタイムゾーン指定を日時に適用
+///
+/// `selection` は "none"（変更なし）/ "exif"（EXIFのオフセット）/ "+09:00" / "Europe/Paris" など。
+/// 壁時計の時刻は指定したタイムゾーンの時刻として解釈し直し、絶対時刻は指定したタイムゾーンに変換します。
+pub fn apply_zone_override(
+    date: DateTime<FixedOffset>,
+    kind: TimeKind,
+    selection: &str,
+    exif_offset: Option<&str>,
+) -> Result<DateTime<FixedOffset>, String> {
+    let zone = match selection.trim() {
+        "" | "none" => return Ok(date),
+        "exif" => match exif_offset.and_then(parse_offset) {
+            Some(offset) => ZoneSpec::Fixed(offset),
+            None => return Ok(date),
+        },
+        other => ZoneSpec::parse(other).ok_or_else(|| format!("Invalid timezone ignored: {}", other))?,
+    };
+
+    Ok(match kind {
+        TimeKind::WallClock => zone.from_wall_clock(date.naive_local()),
+        TimeKind::Instant => zone.from_instant(&date),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn naive(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, mo, d).unwrap().and_hms_opt(h, mi, 0).unwrap()
+    }
+
+    #[test]
+    fn test_parse_offset() {
+        assert_eq!(parse_offset("+09:00"), FixedOffset::east_opt(9 * 3600));
+        assert_eq!(parse_offset("-0530"), FixedOffset::east_opt(-(5 * 3600 + 30 * 60)));
+        assert_eq!(parse_offset("+05"), FixedOffset::east_opt(5 * 3600));
+        assert_eq!(parse_offset("09:00"), None);
+        assert_eq!(parse_offset("+15:00"), None);
+        assert_eq!(ZoneSpec::parse("Europe/Paris"), Some(ZoneSpec::Named(chrono_tz::Europe::Paris)));
+        assert_eq!(ZoneSpec::parse("Mars/Olympus"), None);
+    }
+
+    #[test]
+    fn test_named_zone_handles_dst() {
+        let paris = ZoneSpec::Named(chrono_tz::Europe::Paris);
+        let winter = paris.from_wall_clock(naive(2025, 1, 15, 10, 30));
+        let summer = paris.from_wall_clock(naive(2025, 7, 15, 10, 30));
+        assert_eq!(winter.offset().local_minus_utc(), 3600);
+        assert_eq!(summer.offset().local_minus_utc(), 7200);
+        assert_eq!(summer.naive_local(), naive(2025, 7, 15, 10, 30));
+
+        // 存在しない時刻（3月の切り替え）は切り替え後に進める
+        let gap = paris.from_wall_clock(naive(2025, 3, 30, 2, 30));
+        assert_eq!(gap.naive_local(), naive(2025, 3, 30, 3, 30));
+    }
+
+    #[test]
+    fn test_resolver_prefers_exif_offset() {
+        let resolver = TimezoneResolver::new(Some("Asia/Tokyo"), Some(-18000)).unwrap();
+        let with_exif = resolver.exif_time(naive(2025, 7, 15, 10, 30), Some("+02:00"));
+        assert_eq!(with_exif.to_rfc3339(), "2025-07-15T10:30:00+02:00");
+        let without = resolver.exif_time(naive(2025, 7, 15, 10, 30), None);
+        assert_eq!(without.to_rfc3339(), "2025-07-15T10:30:00+09:00");
+
+        let offset_only = TimezoneResolver::new(None, Some(-18000)).unwrap();
+        let instant = Utc.with_ymd_and_hms(2025, 7, 15, 12, 0, 0).unwrap();
+        assert_eq!(offset_only.instant(&instant).to_rfc3339(), "2025-07-15T07:00:00-05:00");
+
+        assert!(TimezoneResolver::new(Some("Nowhere/City"), None).is_err());
+    }
+
+    #[test]
+    fn test_apply_zone_override() {
+        let date = FixedOffset::east_opt(9 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2025, 7, 15, 10, 30, 0)
+            .unwrap();
+
+        // 壁時計：時刻はそのままでオフセットを付け替える
+        let wall = apply_zone_override(date, TimeKind::WallClock, "Europe/Paris", None).unwrap();
+        assert_eq!(wall.to_rfc3339(), "2025-07-15T10:30:00+02:00");
+
+        // 絶対時刻：指定したタイムゾーンに変換
+        let instant = apply_zone_override(date, TimeKind::Instant, "Europe/Paris", None).unwrap();
+        assert_eq!(instant.to_rfc3339(), "2025-07-15T03:30:00+02:00");
+
+        let exif = apply_zone_override(date, TimeKind::Instant, "exif", Some("-04:00")).unwrap();
+        assert_eq!(exif.to_rfc3339(), "2025-07-14T21:30:00-04:00");
+        assert_eq!(apply_zone_override(date, TimeKind::Instant, "none", None).unwrap(), date);
+        assert!(apply_zone_override(date, TimeKind::Instant, "+25:00", None).is_err());
+    }
+}
@@ -1,47 +1,1207 @@
+/// 動画ファイルのコンテナからメタデータを読み取る機能
+///
+/// 先頭のシグネチャでコンテナ形式を判定し、形式ごとのパーサーで作成日時・解像度・再生時間を取得します。
+/// 動画本体（mdat・Cluster・movi など）は読み飛ばし、ヘッダー部分だけを読み込みます。
 use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::ops::Range;
 use std::path::Path;
-use anyhow::{Result, Context};
-use chrono::{DateTime, Utc};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, FixedOffset, NaiveDateTime, Utc};
+
+use crate::orientation::{iso_boxes, read_be};
+
+/// QuickTime epoch (1904-01-01) からUnix epoch (1970-01-01) までの秒数
+const QUICKTIME_EPOCH_OFFSET: i64 = 2_082_844_800;
+
+/// Matroska の DateUTC の基準（2001-01-01T00:00:00Z）のUnix時刻
+const MATROSKA_EPOCH: i64 = 978_307_200;
+
+/// Windows FILETIME の基準（1601-01-01）からUnix epochまでの秒数
+const FILETIME_EPOCH_OFFSET: i64 = 11_644_473_600;
+
+/// 一度に読み込むヘッダー部分の上限（壊れたファイルで巨大なメモリを確保しないため）
+const MAX_HEADER_SIZE: u64 = 64 * 1024 * 1024;
+
+/// EBMLのID
+const EBML_HEADER: u32 = 0x1A45_DFA3;
+const MKV_SEGMENT: u32 = 0x1853_8067;
+const MKV_INFO: u32 = 0x1549_A966;
+const MKV_TRACKS: u32 = 0x1654_AE6B;
+const MKV_TIMESTAMP_SCALE: u32 = 0x2A_D7B1;
+const MKV_DURATION: u32 = 0x4489;
+const MKV_DATE_UTC: u32 = 0x4461;
+const MKV_WRITING_APP: u32 = 0x5741;
+const MKV_TRACK_ENTRY: u32 = 0xAE;
+const MKV_TRACK_TYPE: u32 = 0x83;
+const MKV_CODEC_ID: u32 = 0x86;
+const MKV_DEFAULT_DURATION: u32 = 0x23_E383;
+const MKV_VIDEO: u32 = 0xE0;
+const MKV_PIXEL_WIDTH: u32 = 0xB0;
+const MKV_PIXEL_HEIGHT: u32 = 0xBA;
+const MKV_DISPLAY_WIDTH: u32 = 0x54B0;
+const MKV_DISPLAY_HEIGHT: u32 = 0x54BA;
+const MKV_DISPLAY_UNIT: u32 = 0x54B2;
+
+/// ASFのオブジェクトGUID（バイト列での表現）
+const ASF_HEADER_OBJECT: [u8; 16] = [
+    0x30, 0x26, 0xB2, 0x75, 0x8E, 0x66, 0xCF, 0x11, 0xA6, 0xD9, 0x00, 0xAA, 0x00, 0x62, 0xCE, 0x6C,
+];
+const ASF_FILE_PROPERTIES_OBJECT: [u8; 16] = [
+    0xA1, 0xDC, 0xAB, 0x8C, 0x47, 0xA9, 0xCF, 0x11, 0x8E, 0xE4, 0x00, 0xC0, 0x0C, 0x20, 0x53, 0x65,
+];
+const ASF_STREAM_PROPERTIES_OBJECT: [u8; 16] = [
+    0x91, 0x07, 0xDC, 0xB7, 0xB7, 0xA9, 0xCF, 0x11, 0x8E, 0xE6, 0x00, 0xC0, 0x0C, 0x20, 0x53, 0x65,
+];
+const ASF_VIDEO_MEDIA: [u8; 16] = [
+    0xC0, 0xEF, 0x19, 0xBC, 0x4D, 0x5B, 0xCF, 0x11, 0xA8, 0xFD, 0x00, 0x80, 0x5F, 0x5C, 0x44, 0x2B,
+];
+
+/// メタデータの読み取り元のコンテナ形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VideoContainer {
+    /// MP4 / MOV / M4V / 3GP（QuickTimeのアトム構造）
+    QuickTime,
+    /// MKV / WebM
+    Matroska,
+    /// AVI（RIFF）
+    Avi,
+    /// WMV / ASF
+    Asf,
+}
+
+impl VideoContainer {
+    /// 先頭のバイト列からコンテナ形式を判定
+    fn detect(head: &[u8]) -> Option<Self> {
+        if head.starts_with(&EBML_HEADER.to_be_bytes()) {
+            Some(Self::Matroska)
+        } else if head.starts_with(b"RIFF") && head.get(8..12) == Some(b"AVI ") {
+            Some(Self::Avi)
+        } else if head.starts_with(&ASF_HEADER_OBJECT) {
+            Some(Self::Asf)
+        } else if matches!(
+            head.get(4..8),
+            Some(b"ftyp" | b"moov" | b"mdat" | b"wide" | b"free" | b"skip" | b"pnot")
+        ) {
+            Some(Self::QuickTime)
+        } else {
+            None
+        }
+    }
+}
+
+/// 動画のメタデータ
+#[derive(Debug, Clone)]
+pub struct VideoMetadata {
+    /// 読み取り元のコンテナ形式
+    pub source: VideoContainer,
+    /// 作成日時（絶対時刻、QuickTimeのmvhd・MatroskaのDateUTC・ASFのファイルプロパティ）
+    pub creation_time: Option<DateTime<Utc>>,
+    /// 撮影地の壁時計の時刻（AVIのIDIT・ICRD、iPhoneのcreationdate、©day）
+    pub local_time: Option<NaiveDateTime>,
+    /// 撮影地のタイムゾーンオフセット（例："+09:00"、local_timeと組で記録されている場合のみ）
+    pub timezone: Option<String>,
+    /// 撮影地の緯度・経度（度、ISO 6709）
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    /// 表示時の幅・高さ（回転を適用した後の向き）
+    pub width: u32,
+    pub height: u32,
+    pub duration_ms: u64,
+    /// 表示時に時計回りに回転する角度（0/90/180/270、QuickTimeのtkhdの行列）
+    pub rotation: u32,
+    /// 映像のコーデック（"avc1"・"hvc1"・"V_VP9"・"H264" など）
+    pub codec: Option<String>,
+    /// フレームレート（fps）
+    pub frame_rate: Option<f64>,
+    /// ビットレート（bps、映像トラックから求められない場合はファイル全体の平均）
+    pub bitrate: Option<u64>,
+    /// カメラのメーカー・機種名（QuickTimeの©mak・©mod）
+    pub make: Option<String>,
+    pub model: Option<String>,
+    /// 記録したソフトウェア（AVIのISFT、MatroskaのWritingApp、QuickTimeの©swr）
+    pub software: Option<String>,
+    /// Live Photoの静止画と共通の識別子（QuickTimeのcom.apple.quicktime.content.identifier）
+    pub content_identifier: Option<String>,
+}
+
+impl VideoMetadata {
+    fn new(source: VideoContainer) -> Self {
+        Self {
+            source,
+            creation_time: None,
+            local_time: None,
+            timezone: None,
+            latitude: None,
+            longitude: None,
+            width: 0,
+            height: 0,
+            duration_ms: 0,
+            rotation: 0,
+            codec: None,
+            frame_rate: None,
+            bitrate: None,
+            make: None,
+            model: None,
+            software: None,
+            content_identifier: None,
+        }
+    }
+}
 
 /// 動画ファイルからメタデータを抽出
 pub fn extract_video_metadata(path: &Path) -> Result<VideoMetadata> {
     let file = File::open(path).context("Failed to open video file")?;
-    let size = file.metadata()?.len();
-    let reader = std::io::BufReader::new(file);
+    read_video_metadata(&mut BufReader::new(file))
+}
+
+/// コンテナ形式を判定してメタデータを読み取る
+pub fn read_video_metadata<R: Read + Seek>(reader: &mut R) -> Result<VideoMetadata> {
+    let mut head = Vec::with_capacity(16);
+    reader.by_ref().take(16).read_to_end(&mut head)?;
+    reader.seek(SeekFrom::Start(0))?;
+
+    let mut metadata = match VideoContainer::detect(&head) {
+        Some(VideoContainer::QuickTime) => read_quicktime(reader).context("Failed to parse QuickTime file")?,
+        Some(VideoContainer::Matroska) => read_matroska(reader).context("Failed to parse Matroska file")?,
+        Some(VideoContainer::Avi) => read_avi(reader).context("Failed to parse AVI file")?,
+        Some(VideoContainer::Asf) => read_asf(reader).context("Failed to parse ASF file")?,
+        None => bail!("Unsupported video container"),
+    };
+
+    if metadata.bitrate.is_none() && metadata.duration_ms > 0 {
+        let file_size = reader.seek(SeekFrom::End(0))?;
+        metadata.bitrate = Some((file_size as u128 * 8 * 1000 / metadata.duration_ms as u128) as u64);
+    }
+    Ok(metadata)
+}
+
+/// 指定したバイト数を読み込む（上限を超える場合はエラー）
+fn read_bytes<R: Read>(reader: &mut R, len: u64) -> Result<Vec<u8>> {
+    if len > MAX_HEADER_SIZE {
+        bail!("Header is too large ({} bytes)", len);
+    }
+    let mut data = vec![0; len as usize];
+    reader.read_exact(&mut data).context("Truncated video header")?;
+    Ok(data)
+}
+
+/// リトルエンディアンの整数（0〜8バイト）を読み取る
+fn read_le(data: &[u8], pos: usize, size: usize) -> Option<u64> {
+    let bytes = data.get(pos..pos.checked_add(size)?)?;
+    Some(bytes.iter().rev().fold(0u64, |acc, &b| (acc << 8) | b as u64))
+}
+
+/// NUL終端・前後の空白を除いた文字列（空ならNone）
+fn text(bytes: &[u8]) -> Option<String> {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    let value = String::from_utf8_lossy(&bytes[..end]).trim().to_string();
+    (!value.is_empty()).then_some(value)
+}
+
+/// トップレベルのアトムを順に読み、moovだけを読み込んで解析
+fn read_quicktime<R: Read + Seek>(reader: &mut R) -> Result<VideoMetadata> {
+    let file_end = reader.seek(SeekFrom::End(0))?;
+    let mut pos = 0;
+
+    while pos + 8 <= file_end {
+        reader.seek(SeekFrom::Start(pos))?;
+        let header = read_bytes(reader, 8)?;
+        let kind: [u8; 4] = header[4..8].try_into()?;
+        let (header_len, size) = match read_be(&header, 0, 4).unwrap_or(0) {
+            0 => (8, file_end - pos),
+            1 => (16, read_be(&read_bytes(reader, 8)?, 0, 8).unwrap_or(0)),
+            size => (8, size),
+        };
+        if size < header_len || pos.checked_add(size).is_none_or(|end| end > file_end) {
+            bail!("Invalid atom size at offset {}", pos);
+        }
+        if &kind == b"moov" {
+            let moov = read_bytes(reader, size - header_len)?;
+            return parse_moov(&moov);
+        }
+        pos += size;
+    }
+
+    bail!("No moov atom found")
+}
+
+/// moovアトムの中身からメタデータを取得
+fn parse_moov(moov: &[u8]) -> Result<VideoMetadata> {
+    let mut metadata = VideoMetadata::new(VideoContainer::QuickTime);
+    let all = 0..moov.len();
+
+    let mvhd = child(moov, all.clone(), b"mvhd").context("No mvhd atom found")?;
+    let (creation_time, timescale, duration) = match moov.get(mvhd.start) {
+        Some(1) => (
+            read_be(moov, mvhd.start + 4, 8),
+            read_be(moov, mvhd.start + 20, 4),
+            read_be(moov, mvhd.start + 24, 8),
+        ),
+        _ => (
+            read_be(moov, mvhd.start + 4, 4),
+            read_be(moov, mvhd.start + 12, 4),
+            read_be(moov, mvhd.start + 16, 4),
+        ),
+    };
+    // 作成日時が0の場合は未設定（画面収録などで多い）
+    metadata.creation_time = creation_time
+        .filter(|&t| t > 0)
+        .and_then(|t| DateTime::from_timestamp(t as i64 - QUICKTIME_EPOCH_OFFSET, 0));
+    if let (Some(timescale), Some(duration)) = (timescale.filter(|&t| t > 0), duration) {
+        // 再生時間不明（全ビット1）は0とする
+        if duration != u32::MAX as u64 && duration != u64::MAX {
+            metadata.duration_ms = (duration as u128 * 1000 / timescale as u128) as u64;
+        }
+    }
+
+    // 最初の映像トラック（トラック1が音声のファイルも多いため、ハンドラーの種類で選ぶ）
+    if let Some(track) = iso_boxes(moov, all.clone())
+        .filter(|(kind, _)| kind == b"trak")
+        .find_map(|(_, trak)| video_track(moov, trak))
+    {
+        (metadata.width, metadata.height) = if track.rotation % 180 == 90 {
+            (track.height, track.width)
+        } else {
+            (track.width, track.height)
+        };
+        metadata.rotation = track.rotation;
+        metadata.codec = track.codec;
+        metadata.frame_rate = track.frame_rate;
+        metadata.bitrate = track.bitrate;
+    }
+
+    // moov/meta のキー付きメタデータ（iPhoneなど）を優先し、次にudta（Android・Samsungなど）を読む
+    if let Some(meta) = child(moov, all.clone(), b"meta") {
+        for (key, value) in metadata_items(moov, meta) {
+            apply_tag(&mut metadata, &key, value);
+        }
+    }
+    if let Some(udta) = child(moov, all, b"udta") {
+        for (kind, body) in iso_boxes(moov, udta) {
+            if &kind == b"meta" {
+                // iTunes形式（udta/meta/ilst）で書き込む機種もある
+                for (key, value) in metadata_items(moov, body) {
+                    apply_tag(&mut metadata, &key, value);
+                }
+            } else if let Some(value) = quicktime_text(&moov[body]) {
+                apply_tag(&mut metadata, &atom_name(kind), value);
+            }
+        }
+    }
+
+    Ok(metadata)
+}
+
+/// メタデータのキー（または©xxxアトム名）と値をVideoMetadataに反映（先に見つかった値を優先）
+fn apply_tag(metadata: &mut VideoMetadata, key: &str, value: String) {
+    match key {
+        "com.apple.quicktime.creationdate" | "\u{A9}day" if metadata.local_time.is_none() => {
+            match parse_quicktime_date(&value) {
+                Some(QuickTimeDate::Local(local, offset)) => {
+                    metadata.local_time = Some(local);
+                    metadata.timezone = offset.map(format_offset);
+                }
+                Some(QuickTimeDate::Instant(instant)) => {
+                    metadata.creation_time = metadata.creation_time.or(Some(instant));
+                }
+                None => {}
+            }
+        }
+        "com.apple.quicktime.location.ISO6709" | "\u{A9}xyz" if metadata.latitude.is_none() => {
+            if let Some((latitude, longitude)) = parse_iso6709(&value) {
+                metadata.latitude = Some(latitude);
+                metadata.longitude = Some(longitude);
+            }
+        }
+        "com.apple.quicktime.make" | "\u{A9}mak" => {
+            metadata.make.get_or_insert(value);
+        }
+        "com.apple.quicktime.model" | "\u{A9}mod" => {
+            metadata.model.get_or_insert(value);
+        }
+        "com.apple.quicktime.software" | "\u{A9}swr" => {
+            metadata.software.get_or_insert(value);
+        }
+        "com.apple.quicktime.content.identifier" => {
+            metadata.content_identifier.get_or_insert(value);
+        }
+        _ => {}
+    }
+}
+
+/// metaアトム内のilstを (キー, 文字列の値) として列挙
+///
+/// keysアトムがある場合（QuickTime形式）、ilstの子アトムの種類はキーの番号（1始まり）です。
+/// keysアトムが無い場合（iTunes形式）はアトム名（©dayなど）をキーとします。
+fn metadata_items(data: &[u8], meta: Range<usize>) -> Vec<(String, String)> {
+    // QuickTime形式のmetaはFullBoxではなく、直後にhdlrなどの子アトムが続く
+    let children = if data.get(meta.start + 4..meta.start + 8) == Some(b"hdlr") {
+        meta
+    } else {
+        meta.start + 4..meta.end
+    };
+
+    let keys: Vec<String> = child(data, children.clone(), b"keys")
+        .map(|keys| {
+            // version + flags + エントリ数の後に (サイズ・名前空間・キー) が並ぶ
+            iso_boxes(data, keys.start + 8..keys.end)
+                .map(|(_, key)| String::from_utf8_lossy(&data[key]).into_owned())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let Some(ilst) = child(data, children, b"ilst") else {
+        return Vec::new();
+    };
+    iso_boxes(data, ilst)
+        .filter_map(|(kind, item)| {
+            let key = if keys.is_empty() {
+                atom_name(kind)
+            } else {
+                keys.get((u32::from_be_bytes(kind) as usize).checked_sub(1)?)?.clone()
+            };
+            // dataアトム: 型（1 = UTF-8）+ ロケール + 値
+            let value = child(data, item, b"data")?;
+            if read_be(data, value.start, 4)? & 0x00FF_FFFF != 1 {
+                return None;
+            }
+            Some((key, text(data.get(value.start + 8..value.end)?)?))
+        })
+        .collect()
+}
+
+/// アトム名を文字列に変換（0xA9は©）
+fn atom_name(kind: [u8; 4]) -> String {
+    kind.iter().map(|&b| b as char).collect()
+}
+
+/// QuickTimeの日時文字列の解釈
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum QuickTimeDate {
+    /// 撮影地の時刻（オフセットがあれば付く）
+    Local(NaiveDateTime, Option<FixedOffset>),
+    /// UTC（末尾がZ）
+    Instant(DateTime<Utc>),
+}
+
+/// "2024-05-01T12:34:56+0900" や "2024-05-01T03:34:56Z" などの日時を解析
+fn parse_quicktime_date(value: &str) -> Option<QuickTimeDate> {
+    let value = value.trim();
+    if let Some(local) = value.strip_suffix('Z') {
+        let naive = parse_local_date(local)?;
+        return Some(QuickTimeDate::Instant(naive.and_utc()));
+    }
+    for format in ["%Y-%m-%dT%H:%M:%S%.f%z", "%Y-%m-%dT%H:%M:%S%z", "%Y-%m-%d %H:%M:%S%z"] {
+        if let Ok(date) = DateTime::parse_from_str(value, format) {
+            return Some(QuickTimeDate::Local(date.naive_local(), Some(*date.offset())));
+        }
+    }
+    parse_local_date(value).map(|naive| QuickTimeDate::Local(naive, None))
+}
+
+fn parse_local_date(value: &str) -> Option<NaiveDateTime> {
+    ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+}
+
+/// オフセットをEXIFのOffsetTimeOriginalと同じ "+09:00" 形式にする
+fn format_offset(offset: FixedOffset) -> String {
+    let seconds = offset.local_minus_utc();
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let minutes = seconds.abs() / 60;
+    format!("{}{:02}:{:02}", sign, minutes / 60, minutes % 60)
+}
+
+/// ISO 6709 の位置（"+35.6586+139.7454+040.000/" など）から緯度・経度を取得
+fn parse_iso6709(value: &str) -> Option<(f64, f64)> {
+    let value = value.trim().trim_end_matches('/');
+    let starts: Vec<usize> = value
+        .char_indices()
+        .filter(|&(_, c)| c == '+' || c == '-')
+        .map(|(i, _)| i)
+        .collect();
+    if starts.len() < 2 || starts[0] != 0 {
+        return None;
+    }
+    let number = |i: usize| value[starts[i]..starts.get(i + 1).copied().unwrap_or(value.len())].parse::<f64>().ok();
+    let (latitude, longitude) = (number(0)?, number(1)?);
+    ((-90.0..=90.0).contains(&latitude) && (-180.0..=180.0).contains(&longitude)).then_some((latitude, longitude))
+}
 
-    let mp4 = mp4::Mp4Reader::read_header(reader, size)
-        .context("Failed to parse MP4 file")?;
+/// 指定した種類の最初の子アトム
+fn child(data: &[u8], range: Range<usize>, kind: &[u8; 4]) -> Option<Range<usize>> {
+    iso_boxes(data, range).find(|(k, _)| k == kind).map(|(_, body)| body)
+}
 
-    // QuickTimeメタデータから作成日時を取得
-    let creation_time = mp4.moov.mvhd.creation_time;
+/// QuickTimeの映像トラックの情報
+#[derive(Debug, Clone, PartialEq)]
+struct VideoTrack {
+    /// 回転前の幅・高さ
+    width: u32,
+    height: u32,
+    rotation: u32,
+    codec: Option<String>,
+    frame_rate: Option<f64>,
+    bitrate: Option<u64>,
+}
 
-    // QuickTime epoch (1904-01-01) からUnix epoch (1970-01-01) への変換
-    // QuickTimeは1904年1月1日を基準とし、Unixは1970年1月1日を基準とする
-    const QUICKTIME_EPOCH_OFFSET: i64 = 2082844800; // 1904-01-01 to 1970-01-01
-    let unix_timestamp = creation_time as i64 - QUICKTIME_EPOCH_OFFSET;
+/// trakアトムが映像トラック（mdia/hdlrの種類が"vide"）であれば情報を取得
+fn video_track(data: &[u8], trak: Range<usize>) -> Option<VideoTrack> {
+    let mdia = child(data, trak.clone(), b"mdia")?;
+    let hdlr = child(data, mdia.clone(), b"hdlr")?;
+    if data.get(hdlr.start + 8..hdlr.start + 12)? != b"vide" {
+        return None;
+    }
 
-    let datetime = DateTime::from_timestamp(unix_timestamp, 0)
-        .context("Invalid timestamp in video metadata")?;
+    // tkhd: 幅・高さ（16.16固定小数点）の直前に3x3の変換行列
+    let tkhd = child(data, trak, b"tkhd")?;
+    let matrix = tkhd.start + if data.get(tkhd.start)? == &1 { 52 } else { 40 };
+    let width = (read_be(data, matrix + 36, 4)? >> 16) as u32;
+    let height = (read_be(data, matrix + 40, 4)? >> 16) as u32;
+    let a = read_be(data, matrix, 4)? as u32 as i32;
+    let b = read_be(data, matrix + 4, 4)? as u32 as i32;
+    let rotation = matrix_rotation(a, b);
 
-    // 動画の幅と高さを取得
-    let (width, height) = if let Some(track) = mp4.tracks().get(&1) {
-        (track.width() as u32, track.height() as u32)
+    // mdhd: トラックの時間単位
+    let mdhd = child(data, mdia.clone(), b"mdhd")?;
+    let (timescale, duration) = if data.get(mdhd.start)? == &1 {
+        (read_be(data, mdhd.start + 20, 4)?, read_be(data, mdhd.start + 24, 8)?)
     } else {
-        (0, 0)
+        (read_be(data, mdhd.start + 12, 4)?, read_be(data, mdhd.start + 16, 4)?)
     };
 
-    Ok(VideoMetadata {
-        creation_time: datetime,
+    let stbl = child(data, mdia, b"minf").and_then(|minf| child(data, minf, b"stbl"));
+    let mut track = VideoTrack {
         width,
         height,
-        duration_ms: mp4.duration().as_millis() as u64,
+        rotation,
+        codec: None,
+        frame_rate: None,
+        bitrate: None,
+    };
+    let Some(stbl) = stbl else {
+        return Some(track);
+    };
+
+    // stsd: 最初のサンプル記述の種類がコーデック
+    track.codec = child(data, stbl.clone(), b"stsd")
+        .and_then(|stsd| iso_boxes(data, stsd.start + 8..stsd.end).next())
+        .map(|(kind, _)| atom_name(kind).trim().to_string());
+
+    // stts: サンプル数と合計時間からフレームレート
+    let (frames, ticks) = child(data, stbl.clone(), b"stts")
+        .and_then(|stts| {
+            let count = read_be(data, stts.start + 4, 4)? as usize;
+            (0..count).try_fold((0u64, 0u64), |(frames, ticks), i| {
+                let entry = stts.start + 8 + i * 8;
+                let samples = read_be(data, entry, 4)?;
+                let delta = read_be(data, entry + 4, 4)?;
+                Some((frames + samples, ticks + samples * delta))
+            })
+        })
+        .unwrap_or((0, duration));
+    if frames > 0 && ticks > 0 && timescale > 0 {
+        track.frame_rate = Some(frames as f64 * timescale as f64 / ticks as f64);
+    }
+
+    // stsz: サンプルサイズの合計と再生時間からビットレート
+    let bytes = child(data, stbl, b"stsz").and_then(|stsz| {
+        let size = read_be(data, stsz.start + 4, 4)?;
+        let count = read_be(data, stsz.start + 8, 4)?;
+        if size > 0 {
+            return Some(size * count);
+        }
+        (0..count as usize).try_fold(0u64, |total, i| Some(total + read_be(data, stsz.start + 12 + i * 4, 4)?))
+    });
+    if let Some(bytes) = bytes.filter(|&b| b > 0) {
+        if ticks > 0 && timescale > 0 {
+            track.bitrate = Some((bytes as u128 * 8 * timescale as u128 / ticks as u128) as u64);
+        }
+    }
+
+    Some(track)
+}
+
+/// 変換行列の (a, b) から時計回りの回転角度（0/90/180/270）を求める
+fn matrix_rotation(a: i32, b: i32) -> u32 {
+    if a == 0 && b == 0 {
+        return 0;
+    }
+    let degrees = (b as f64).atan2(a as f64).to_degrees();
+    ((degrees / 90.0).round() as i32 * 90).rem_euclid(360) as u32
+}
+
+/// QuickTimeの国際テキスト（長さ2バイト + 言語2バイト + 文字列）
+fn quicktime_text(body: &[u8]) -> Option<String> {
+    let len = read_be(body, 0, 2)? as usize;
+    text(body.get(4..4 + len)?)
+}
+
+/// EBMLの可変長整数を読み取る（IDはマーカーを残し、サイズはマーカーを除く）
+///
+/// 戻り値は (値, バイト数, 全ビット1＝サイズ不明か)。
+fn ebml_vint(data: &[u8], pos: usize, keep_marker: bool) -> Option<(u64, usize, bool)> {
+    let first = *data.get(pos)?;
+    let len = first.leading_zeros() as usize + 1;
+    if len > 8 {
+        return None;
+    }
+    let raw = read_be(data, pos, len)?;
+    let value = if keep_marker { raw } else { raw & (u64::MAX >> (64 - 7 * len)) };
+    let unknown = !keep_marker && value == u64::MAX >> (64 - 7 * len);
+    Some((value, len, unknown))
+}
+
+/// マスター要素の子要素を (ID, 本体の範囲) として列挙
+fn ebml_elements(data: &[u8], range: Range<usize>) -> impl Iterator<Item = (u32, Range<usize>)> + '_ {
+    let mut pos = range.start;
+    let end = range.end.min(data.len());
+    std::iter::from_fn(move || {
+        let (id, id_len, _) = ebml_vint(data, pos, true)?;
+        let (size, size_len, unknown) = ebml_vint(data, pos + id_len, false)?;
+        let start = pos + id_len + size_len;
+        let body_end = if unknown {
+            end
+        } else {
+            start.checked_add(usize::try_from(size).ok()?)?
+        };
+        if body_end > end {
+            return None;
+        }
+        pos = body_end;
+        Some((id as u32, start..body_end))
     })
 }
 
-#[derive(Debug, Clone)]
-pub struct VideoMetadata {
-    pub creation_time: DateTime<Utc>,
-    pub width: u32,
-    pub height: u32,
-    pub duration_ms: u64,
+/// EBMLの浮動小数点数（4バイトまたは8バイト）
+fn ebml_float(data: &[u8]) -> Option<f64> {
+    match data.len() {
+        4 => Some(f32::from_be_bytes(data.try_into().ok()?) as f64),
+        8 => Some(f64::from_be_bytes(data.try_into().ok()?)),
+        _ => None,
+    }
+}
+
+/// ストリームから要素ヘッダーを読み取る（戻り値は ID, 本体のサイズ（不明ならNone））
+fn read_ebml_header<R: Read>(reader: &mut R) -> Result<(u32, Option<u64>)> {
+    let mut buf = [0u8; 12];
+    reader.read_exact(&mut buf[..1])?;
+    let id_len = buf[0].leading_zeros() as usize + 1;
+    if id_len > 4 {
+        bail!("Invalid EBML element ID");
+    }
+    reader.read_exact(&mut buf[1..id_len])?;
+    reader.read_exact(&mut buf[id_len..id_len + 1])?;
+    let size_len = buf[id_len].leading_zeros() as usize + 1;
+    if size_len > 8 {
+        bail!("Invalid EBML element size");
+    }
+    reader.read_exact(&mut buf[id_len + 1..id_len + size_len])?;
+
+    let (id, _, _) = ebml_vint(&buf, 0, true).context("Invalid EBML element ID")?;
+    let (size, _, unknown) = ebml_vint(&buf, id_len, false).context("Invalid EBML element size")?;
+    Ok((id as u32, (!unknown).then_some(size)))
+}
+
+/// Segment直下のInfoとTracksだけを読み込み、Clusterなどは読み飛ばす
+fn read_matroska<R: Read + Seek>(reader: &mut R) -> Result<VideoMetadata> {
+    let file_end = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(0))?;
+
+    let (id, size) = read_ebml_header(reader)?;
+    if id != EBML_HEADER {
+        bail!("Missing EBML header");
+    }
+    reader.seek(SeekFrom::Current(size.context("Invalid EBML header size")? as i64))?;
+
+    let (id, size) = read_ebml_header(reader)?;
+    if id != MKV_SEGMENT {
+        bail!("Missing Matroska segment");
+    }
+    let segment_end = size
+        .map(|size| reader.stream_position().map(|pos| (pos + size).min(file_end)))
+        .transpose()?
+        .unwrap_or(file_end);
+
+    let mut metadata = VideoMetadata::new(VideoContainer::Matroska);
+    let (mut info, mut tracks) = (None, None);
+    while reader.stream_position()? < segment_end && (info.is_none() || tracks.is_none()) {
+        let Ok((id, size)) = read_ebml_header(reader) else {
+            break;
+        };
+        match (id, size) {
+            (MKV_INFO, Some(size)) => info = Some(read_bytes(reader, size)?),
+            (MKV_TRACKS, Some(size)) => tracks = Some(read_bytes(reader, size)?),
+            (_, Some(size)) => {
+                reader.seek(SeekFrom::Current(size as i64))?;
+            }
+            // サイズ不明のCluster（ライブ配信の録画など）以降にはヘッダーが無い
+            (_, None) => break,
+        }
+    }
+
+    let info = info.context("No Info element found")?;
+    let mut timestamp_scale = 1_000_000u64;
+    let mut duration = None;
+    for (id, body) in ebml_elements(&info, 0..info.len()) {
+        let value = &info[body.clone()];
+        match id {
+            MKV_TIMESTAMP_SCALE => timestamp_scale = read_be(&info, body.start, body.len()).unwrap_or(timestamp_scale),
+            MKV_DURATION => duration = ebml_float(value),
+            MKV_DATE_UTC => {
+                // 2001-01-01からのナノ秒（符号付き）
+                let nanos = read_be(&info, body.start, body.len()).unwrap_or(0) as i64;
+                metadata.creation_time = DateTime::from_timestamp(
+                    MATROSKA_EPOCH + nanos.div_euclid(1_000_000_000),
+                    nanos.rem_euclid(1_000_000_000) as u32,
+                );
+            }
+            MKV_WRITING_APP => metadata.software = text(value),
+            _ => {}
+        }
+    }
+    if let Some(duration) = duration.filter(|d| d.is_finite() && *d > 0.0) {
+        metadata.duration_ms = (duration * timestamp_scale as f64 / 1_000_000.0) as u64;
+    }
+
+    if let Some(tracks) = tracks {
+        let uint = |body: &Range<usize>| read_be(&tracks, body.start, body.len()).unwrap_or(0);
+        // 最初の映像トラック（TrackType = 1）
+        let video = ebml_elements(&tracks, 0..tracks.len())
+            .filter(|(id, _)| *id == MKV_TRACK_ENTRY)
+            .find(|(_, entry)| {
+                ebml_elements(&tracks, entry.clone()).any(|(id, body)| id == MKV_TRACK_TYPE && uint(&body) == 1)
+            });
+        if let Some((_, entry)) = video {
+            for (id, body) in ebml_elements(&tracks, entry) {
+                match id {
+                    MKV_CODEC_ID => metadata.codec = text(&tracks[body]),
+                    // 1フレームの長さ（ナノ秒）
+                    MKV_DEFAULT_DURATION if uint(&body) > 0 => {
+                        metadata.frame_rate = Some(1_000_000_000.0 / uint(&body) as f64);
+                    }
+                    MKV_VIDEO => apply_matroska_video(&tracks, body, &mut metadata),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    Ok(metadata)
+}
+
+/// Video要素の画素数・表示サイズを反映（表示サイズの単位がピクセルの場合は表示サイズを優先）
+fn apply_matroska_video(data: &[u8], video: Range<usize>, metadata: &mut VideoMetadata) {
+    let (mut pixel, mut display, mut unit) = ((0, 0), (0, 0), 0);
+    for (id, body) in ebml_elements(data, video) {
+        let value = read_be(data, body.start, body.len()).unwrap_or(0) as u32;
+        match id {
+            MKV_PIXEL_WIDTH => pixel.0 = value,
+            MKV_PIXEL_HEIGHT => pixel.1 = value,
+            MKV_DISPLAY_WIDTH => display.0 = value,
+            MKV_DISPLAY_HEIGHT => display.1 = value,
+            MKV_DISPLAY_UNIT => unit = value,
+            _ => {}
+        }
+    }
+    (metadata.width, metadata.height) = if unit == 0 && display.0 > 0 && display.1 > 0 {
+        display
+    } else {
+        pixel
+    };
+}
+
+/// RIFFのチャンクを (FourCC, 本体の範囲) として列挙（奇数サイズは1バイトのパディング付き）
+fn riff_chunks(data: &[u8], range: Range<usize>) -> impl Iterator<Item = ([u8; 4], Range<usize>)> + '_ {
+    let mut pos = range.start;
+    let end = range.end.min(data.len());
+    std::iter::from_fn(move || {
+        let kind: [u8; 4] = data.get(pos..pos + 4)?.try_into().ok()?;
+        let size = usize::try_from(read_le(data, pos + 4, 4)?).ok()?;
+        let body = pos + 8..(pos + 8).checked_add(size)?.min(end);
+        pos = body.end + (size % 2);
+        (body.start <= end).then_some((kind, body))
+    })
+}
+
+/// トップレベルのhdrlとINFOだけを読み込み、moviなどは読み飛ばす
+fn read_avi<R: Read + Seek>(reader: &mut R) -> Result<VideoMetadata> {
+    let header = read_bytes(reader, 12)?;
+    let riff_end = 8 + read_le(&header, 4, 4).unwrap_or(0);
+    let file_end = reader.seek(SeekFrom::End(0))?;
+    let riff_end = riff_end.min(file_end);
+
+    let mut metadata = VideoMetadata::new(VideoContainer::Avi);
+    let mut pos = 12;
+    let mut found_header = false;
+    while pos + 12 <= riff_end {
+        reader.seek(SeekFrom::Start(pos))?;
+        let chunk = read_bytes(reader, 12)?;
+        let size = read_le(&chunk, 4, 4).unwrap_or(0);
+        if &chunk[0..4] == b"LIST" && matches!(&chunk[8..12], b"hdrl" | b"INFO") {
+            let list = read_bytes(reader, size.saturating_sub(4).min(riff_end - pos - 12))?;
+            found_header |= &chunk[8..12] == b"hdrl";
+            parse_avi_list(&list, &mut metadata);
+        }
+        pos += 8 + size + (size % 2);
+    }
+
+    if !found_header {
+        bail!("No hdrl list found");
+    }
+    Ok(metadata)
+}
+
+/// hdrl・INFOリストの中身を反映
+fn parse_avi_list(list: &[u8], metadata: &mut VideoMetadata) {
+    let mut total_frames = None;
+    let mut microseconds_per_frame = 0;
+
+    for (kind, body) in riff_chunks(list, 0..list.len()) {
+        let value = &list[body.clone()];
+        match &kind {
+            b"avih" => {
+                microseconds_per_frame = read_le(value, 0, 4).unwrap_or(0);
+                total_frames = total_frames.or(read_le(value, 16, 4));
+                metadata.width = read_le(value, 32, 4).unwrap_or(0) as u32;
+                metadata.height = read_le(value, 36, 4).unwrap_or(0) as u32;
+            }
+            // OpenDML（1GB超）のファイルはavihのフレーム数が最初のRIFFの分だけになる
+            b"LIST" if value.starts_with(b"odml") => {
+                if let Some((_, dmlh)) = riff_chunks(list, body.start + 4..body.end).find(|(k, _)| k == b"dmlh") {
+                    total_frames = read_le(list, dmlh.start, 4).or(total_frames);
+                }
+            }
+            // 最初の映像ストリーム（strhの種類が"vids"）のコーデックとフレームレート
+            b"LIST" if value.starts_with(b"strl") && metadata.codec.is_none() => {
+                apply_avi_stream(list, body.start + 4..body.end, metadata);
+            }
+            b"IDIT" => metadata.local_time = parse_riff_date(value).or(metadata.local_time),
+            b"ICRD" => metadata.local_time = metadata.local_time.or_else(|| parse_riff_date(value)),
+            b"ISFT" => metadata.software = text(value),
+            _ => {}
+        }
+    }
+
+    if let Some(frames) = total_frames {
+        metadata.duration_ms = frames * microseconds_per_frame / 1000;
+    }
+}
+
+/// strlリスト（strh + strf）が映像ストリームであればコーデックとフレームレートを反映
+fn apply_avi_stream(list: &[u8], strl: Range<usize>, metadata: &mut VideoMetadata) {
+    let mut chunks = riff_chunks(list, strl);
+    let Some((_, strh)) = chunks.find(|(kind, _)| kind == b"strh") else {
+        return;
+    };
+    if list.get(strh.start..strh.start + 4) != Some(b"vids") {
+        return;
+    }
+
+    // fccHandlerが空の場合はstrf（BITMAPINFOHEADER）のbiCompression
+    let handler = list.get(strh.start + 4..strh.start + 8).and_then(fourcc);
+    let compression = chunks
+        .find(|(kind, _)| kind == b"strf")
+        .and_then(|(_, strf)| list.get(strf.start + 16..strf.start + 20))
+        .and_then(fourcc);
+    metadata.codec = compression.or(handler);
+
+    let scale = read_le(list, strh.start + 20, 4).unwrap_or(0);
+    let rate = read_le(list, strh.start + 24, 4).unwrap_or(0);
+    if scale > 0 && rate > 0 {
+        metadata.frame_rate = Some(rate as f64 / scale as f64);
+    }
+}
+
+/// FourCC（空白・NULのみの場合はNone）
+fn fourcc(bytes: &[u8]) -> Option<String> {
+    text(bytes).filter(|code| code.chars().all(|c| c.is_ascii_graphic()))
+}
+
+/// AVIの日時文字列（"SAT JAN 01 12:34:56 2011" や "2011:01:01 12:34:56" など）を解析
+fn parse_riff_date(value: &[u8]) -> Option<NaiveDateTime> {
+    let value = text(value)?;
+    let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+    ["%a %b %d %H:%M:%S %Y", "%Y:%m:%d %H:%M:%S", "%Y/%m/%d %H:%M:%S", "%Y-%m-%d %H:%M:%S"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(&value, format).ok())
+}
+
+/// ヘッダーオブジェクトを読み込み、ファイルプロパティとストリームプロパティを解析
+fn read_asf<R: Read + Seek>(reader: &mut R) -> Result<VideoMetadata> {
+    let header = read_bytes(reader, 30)?;
+    let size = read_le(&header, 16, 8).unwrap_or(0);
+    if size < 30 {
+        bail!("Invalid ASF header size");
+    }
+    let objects = read_bytes(reader, size - 30)?;
+
+    let mut metadata = VideoMetadata::new(VideoContainer::Asf);
+    let mut found_properties = false;
+    let mut pos = 0;
+    while let (Some(guid), Some(size)) = (objects.get(pos..pos + 16), read_le(&objects, pos + 16, 8)) {
+        let Some(end) = usize::try_from(size).ok().and_then(|s| pos.checked_add(s)) else {
+            break;
+        };
+        if size < 24 || end > objects.len() {
+            break;
+        }
+        let body = &objects[pos + 24..end];
+
+        if guid == ASF_FILE_PROPERTIES_OBJECT {
+            found_properties = true;
+            // 1601-01-01からの100ナノ秒単位
+            metadata.creation_time = read_le(body, 40, 8).filter(|&t| t > 0).and_then(|t| {
+                DateTime::from_timestamp(
+                    (t / 10_000_000) as i64 - FILETIME_EPOCH_OFFSET,
+                    (t % 10_000_000) as u32 * 100,
+                )
+            });
+            // 再生時間（100ナノ秒単位）にはプリロール（ミリ秒）が含まれる
+            let play_duration = read_le(body, 64, 8).unwrap_or(0) / 10_000;
+            let preroll = read_le(body, 80, 8).unwrap_or(0);
+            metadata.duration_ms = play_duration.saturating_sub(preroll);
+        } else if guid == ASF_STREAM_PROPERTIES_OBJECT
+            && body.get(0..16) == Some(&ASF_VIDEO_MEDIA)
+            && metadata.width == 0
+        {
+            // 型固有データ（映像は幅・高さ・フラグ・サイズの後にBITMAPINFOHEADER）は54バイト目から
+            metadata.width = read_le(body, 54, 4).unwrap_or(0) as u32;
+            metadata.height = read_le(body, 58, 4).unwrap_or(0) as u32;
+            metadata.codec = body.get(81..85).and_then(fourcc);
+        }
+        pos = end;
+    }
+
+    if !found_properties {
+        bail!("No file properties object found");
+    }
+    Ok(metadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, TimeZone};
+    use std::io::Cursor;
+
+    fn atom(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut data = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        data.extend_from_slice(kind);
+        data.extend_from_slice(body);
+        data
+    }
+
+    fn read(data: Vec<u8>) -> VideoMetadata {
+        read_video_metadata(&mut Cursor::new(data)).unwrap()
+    }
+
+    #[test]
+    fn test_quicktime_metadata() {
+        let created = Utc.with_ymd_and_hms(2024, 5, 1, 3, 4, 5).unwrap();
+        let mut mvhd = vec![0; 4];
+        mvhd.extend_from_slice(&((created.timestamp() + QUICKTIME_EPOCH_OFFSET) as u32).to_be_bytes());
+        mvhd.extend_from_slice(&[0; 4]);
+        mvhd.extend_from_slice(&600u32.to_be_bytes());
+        mvhd.extend_from_slice(&(600u32 * 12 + 300).to_be_bytes());
+        mvhd.resize(100, 0);
+
+        let mut udta = atom(b"\xA9mak", b"\x00\x05\x00\x00Apple");
+        udta.extend(atom(b"\xA9mod", b"\x00\x09\x00\x00iPhone 15"));
+
+        let mut moov = atom(b"mvhd", &mvhd);
+        // トラック1は音声、トラック2が縦向き（90度回転）の映像
+        moov.extend(trak(b"soun", 0, 0, (1, 0)));
+        moov.extend(trak(b"vide", 1920, 1080, (0, 1)));
+        moov.extend(atom(b"udta", &udta));
+
+        // moovがmdatの後ろにあるファイル
+        let mut data = atom(b"ftyp", b"qt  \0\0\0\0");
+        data.extend(atom(b"mdat", &[0; 32]));
+        data.extend(atom(b"moov", &moov));
+
+        let metadata = read(data);
+        assert_eq!(metadata.source, VideoContainer::QuickTime);
+        assert_eq!(metadata.creation_time, Some(created));
+        assert_eq!(metadata.duration_ms, 12_500);
+        assert_eq!((metadata.width, metadata.height), (1080, 1920));
+        assert_eq!(metadata.rotation, 90);
+        assert_eq!(metadata.codec.as_deref(), Some("avc1"));
+        assert!((metadata.frame_rate.unwrap() - 29.97).abs() < 0.01);
+        assert_eq!(metadata.bitrate, Some(50_000 * 8 * 30_000 / 1001));
+        assert_eq!(metadata.make.as_deref(), Some("Apple"));
+        assert_eq!(metadata.model.as_deref(), Some("iPhone 15"));
+    }
+
+    /// tkhd（行列の a, b）・hdlr・mdhd・stbl（avc1、30000/1001 fps × 300フレーム、1フレーム50000バイト）を持つtrak
+    fn trak(handler: &[u8; 4], width: u32, height: u32, (a, b): (i32, i32)) -> Vec<u8> {
+        // 行列は (a, b, u, c, d, v, x, y, w)、a〜dは16.16、u・v・wは2.30の固定小数点
+        let mut tkhd = vec![0; 40];
+        for value in [a << 16, b << 16, 0, -b << 16, a << 16, 0, 0, 0, 1 << 30] {
+            tkhd.extend_from_slice(&value.to_be_bytes());
+        }
+        tkhd.extend_from_slice(&(width << 16).to_be_bytes());
+        tkhd.extend_from_slice(&(height << 16).to_be_bytes());
+
+        let mut mdhd = vec![0; 12];
+        mdhd.extend_from_slice(&30_000u32.to_be_bytes());
+        mdhd.extend_from_slice(&(300u32 * 1001).to_be_bytes());
+        let hdlr = [&[0u8; 8], handler.as_slice(), &[0; 12]].concat();
+
+        let stsd = [&[0u8; 4], &1u32.to_be_bytes(), atom(b"avc1", &[0; 78]).as_slice()].concat();
+        let stts = [[0u8; 4], 1u32.to_be_bytes(), 300u32.to_be_bytes(), 1001u32.to_be_bytes()].concat();
+        let stsz = [[0u8; 4], 50_000u32.to_be_bytes(), 300u32.to_be_bytes()].concat();
+        let stbl = [atom(b"stsd", &stsd), atom(b"stts", &stts), atom(b"stsz", &stsz)].concat();
+
+        let minf = atom(b"stbl", &stbl);
+        let mdia = [atom(b"mdhd", &mdhd), atom(b"hdlr", &hdlr), atom(b"minf", &minf)].concat();
+        atom(b"trak", &[atom(b"tkhd", &tkhd), atom(b"mdia", &mdia)].concat())
+    }
+
+    #[test]
+    fn test_matrix_rotation() {
+        assert_eq!(matrix_rotation(1 << 16, 0), 0);
+        assert_eq!(matrix_rotation(0, 1 << 16), 90);
+        assert_eq!(matrix_rotation(-1 << 16, 0), 180);
+        assert_eq!(matrix_rotation(0, -1 << 16), 270);
+    }
+
+    /// moov/meta（keys + ilst）に文字列のメタデータを持つiPhone形式のmoov
+    fn apple_moov(items: &[(&str, &str)]) -> Vec<u8> {
+        let mut keys = vec![0; 4];
+        keys.extend_from_slice(&(items.len() as u32).to_be_bytes());
+        let mut ilst = Vec::new();
+        for (index, (key, value)) in items.iter().enumerate() {
+            keys.extend(atom(b"mdta", key.as_bytes()));
+            let data = [&[0, 0, 0, 1, 0, 0, 0, 0], value.as_bytes()].concat();
+            ilst.extend(atom(&(index as u32 + 1).to_be_bytes(), &atom(b"data", &data)));
+        }
+        let mut meta = atom(b"hdlr", &[0; 24]);
+        meta.extend(atom(b"keys", &keys));
+        meta.extend(atom(b"ilst", &ilst));
+
+        let mut moov = atom(b"mvhd", &[0; 100]);
+        moov.extend(atom(b"meta", &meta));
+        moov
+    }
+
+    #[test]
+    fn test_apple_creation_date_and_location() {
+        let moov = apple_moov(&[
+            ("com.apple.quicktime.location.ISO6709", "+35.6586+139.7454+040.000/"),
+            ("com.apple.quicktime.make", "Apple"),
+            ("com.apple.quicktime.creationdate", "2024-05-01T21:34:56+0900"),
+            ("com.apple.quicktime.content.identifier", "5C1C7A0E-2B1D-4E4A-9D0B-1F2E3D4C5B6A"),
+        ]);
+        let metadata = parse_moov(&moov).unwrap();
+        assert_eq!(metadata.creation_time, None);
+        assert_eq!(
+            metadata.local_time,
+            NaiveDate::from_ymd_opt(2024, 5, 1).unwrap().and_hms_opt(21, 34, 56)
+        );
+        assert_eq!(metadata.timezone.as_deref(), Some("+09:00"));
+        assert_eq!(metadata.latitude, Some(35.6586));
+        assert_eq!(metadata.longitude, Some(139.7454));
+        assert_eq!(metadata.make.as_deref(), Some("Apple"));
+        assert_eq!(
+            metadata.content_identifier.as_deref(),
+            Some("5C1C7A0E-2B1D-4E4A-9D0B-1F2E3D4C5B6A")
+        );
+    }
+
+    #[test]
+    fn test_android_udta_location() {
+        let mut udta = atom(b"\xA9xyz", b"\x00\x12\x15\xC7-33.8688+151.2093/");
+        // iTunes形式（udta/meta/ilst）の©day
+        let day = atom(b"data", &[&[0, 0, 0, 1, 0, 0, 0, 0], b"2023-12-31T23:59:59-0330".as_slice()].concat());
+        let meta = [&[0u8; 4], atom(b"hdlr", &[0; 24]).as_slice(), &atom(b"ilst", &atom(b"\xA9day", &day))].concat();
+        udta.extend(atom(b"meta", &meta));
+
+        let mut moov = atom(b"mvhd", &[0; 100]);
+        moov.extend(atom(b"udta", &udta));
+        let metadata = parse_moov(&moov).unwrap();
+        assert_eq!((metadata.latitude, metadata.longitude), (Some(-33.8688), Some(151.2093)));
+        assert_eq!(
+            metadata.local_time,
+            NaiveDate::from_ymd_opt(2023, 12, 31).unwrap().and_hms_opt(23, 59, 59)
+        );
+        assert_eq!(metadata.timezone.as_deref(), Some("-03:30"));
+
+        assert_eq!(
+            parse_quicktime_date("2024-05-01T03:34:56Z"),
+            Some(QuickTimeDate::Instant(Utc.with_ymd_and_hms(2024, 5, 1, 3, 34, 56).unwrap()))
+        );
+        assert_eq!(parse_iso6709("+95.0+139.0/"), None);
+    }
+
+    fn ebml(id: u32, body: &[u8]) -> Vec<u8> {
+        let mut data: Vec<u8> = id.to_be_bytes().iter().copied().skip_while(|&b| b == 0).collect();
+        // サイズは8バイトの可変長整数で表す
+        data.extend_from_slice(&(body.len() as u64 | 1 << 56).to_be_bytes());
+        data.extend_from_slice(body);
+        data
+    }
+
+    #[test]
+    fn test_matroska_metadata() {
+        let created = Utc.with_ymd_and_hms(2023, 8, 15, 9, 30, 0).unwrap();
+        let mut info = ebml(MKV_TIMESTAMP_SCALE, &1_000_000u32.to_be_bytes());
+        info.extend(ebml(MKV_DURATION, &65_432.0f64.to_be_bytes()));
+        info.extend(ebml(
+            MKV_DATE_UTC,
+            &((created.timestamp() - MATROSKA_EPOCH) * 1_000_000_000).to_be_bytes(),
+        ));
+        info.extend(ebml(MKV_WRITING_APP, b"OBS Studio"));
+
+        let mut audio = ebml(MKV_TRACK_TYPE, &[2]);
+        audio.extend(ebml(MKV_VIDEO, &[]));
+        let mut video = ebml(MKV_TRACK_TYPE, &[1]);
+        video.extend(ebml(MKV_CODEC_ID, b"V_VP9"));
+        video.extend(ebml(MKV_DEFAULT_DURATION, &40_000_000u32.to_be_bytes()));
+        let mut dims = ebml(MKV_PIXEL_WIDTH, &1440u16.to_be_bytes());
+        dims.extend(ebml(MKV_PIXEL_HEIGHT, &1080u16.to_be_bytes()));
+        // アナモルフィック（表示は16:9）
+        dims.extend(ebml(MKV_DISPLAY_WIDTH, &1920u16.to_be_bytes()));
+        dims.extend(ebml(MKV_DISPLAY_HEIGHT, &1080u16.to_be_bytes()));
+        video.extend(ebml(MKV_VIDEO, &dims));
+        let mut tracks = ebml(MKV_TRACK_ENTRY, &audio);
+        tracks.extend(ebml(MKV_TRACK_ENTRY, &video));
+
+        let mut segment = ebml(0x114D_9B74, &[0; 8]);
+        segment.extend(ebml(MKV_INFO, &info));
+        segment.extend(ebml(MKV_TRACKS, &tracks));
+        segment.extend(ebml(0x1F43_B675, &[0; 16]));
+
+        let mut data = ebml(EBML_HEADER, &ebml(0x4282, b"webm"));
+        data.extend(ebml(MKV_SEGMENT, &segment));
+
+        let metadata = read(data);
+        assert_eq!(metadata.source, VideoContainer::Matroska);
+        assert_eq!(metadata.creation_time, Some(created));
+        assert_eq!(metadata.duration_ms, 65_432);
+        assert_eq!((metadata.width, metadata.height), (1920, 1080));
+        assert_eq!(metadata.codec.as_deref(), Some("V_VP9"));
+        assert_eq!(metadata.frame_rate, Some(25.0));
+        assert!(metadata.bitrate.is_some());
+        assert_eq!(metadata.software.as_deref(), Some("OBS Studio"));
+    }
+
+    fn chunk(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut data = kind.to_vec();
+        data.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        data.extend_from_slice(body);
+        if body.len() % 2 == 1 {
+            data.push(0);
+        }
+        data
+    }
+
+    fn list(kind: &[u8; 4], children: &[u8]) -> Vec<u8> {
+        chunk(b"LIST", &[kind.as_slice(), children].concat())
+    }
+
+    #[test]
+    fn test_avi_metadata() {
+        let mut avih = vec![0; 56];
+        avih[0..4].copy_from_slice(&33_333u32.to_le_bytes());
+        avih[16..20].copy_from_slice(&300u32.to_le_bytes());
+        avih[32..36].copy_from_slice(&640u32.to_le_bytes());
+        avih[36..40].copy_from_slice(&480u32.to_le_bytes());
+
+        let mut strh = b"vidsdvsd".to_vec();
+        strh.resize(20, 0);
+        strh.extend_from_slice(&1001u32.to_le_bytes());
+        strh.extend_from_slice(&30_000u32.to_le_bytes());
+        strh.resize(56, 0);
+        let strl = [chunk(b"strh", &strh), chunk(b"strf", &[0; 40])].concat();
+
+        let mut hdrl = chunk(b"avih", &avih);
+        hdrl.extend(list(b"strl", &strl));
+        hdrl.extend(chunk(b"IDIT", b"SAT JAN 01 12:34:56 2011\n\0"));
+
+        let mut data = b"RIFF\0\0\0\0AVI ".to_vec();
+        data.extend(list(b"hdrl", &hdrl));
+        data.extend(list(b"movi", &chunk(b"00dc", &[0; 7])));
+        data.extend(list(b"INFO", &chunk(b"ISFT", b"CanonMVI06\0")));
+        let size = (data.len() - 8) as u32;
+        data[4..8].copy_from_slice(&size.to_le_bytes());
+
+        let metadata = read(data);
+        assert_eq!(metadata.source, VideoContainer::Avi);
+        assert_eq!(metadata.creation_time, None);
+        assert_eq!(
+            metadata.local_time,
+            NaiveDate::from_ymd_opt(2011, 1, 1).unwrap().and_hms_opt(12, 34, 56)
+        );
+        assert_eq!(metadata.duration_ms, 9_999);
+        assert_eq!((metadata.width, metadata.height), (640, 480));
+        assert_eq!(metadata.codec.as_deref(), Some("dvsd"));
+        assert!((metadata.frame_rate.unwrap() - 29.97).abs() < 0.01);
+        assert_eq!(metadata.software.as_deref(), Some("CanonMVI06"));
+
+        assert_eq!(
+            parse_riff_date(b"2011:01:01 12:34:56"),
+            NaiveDate::from_ymd_opt(2011, 1, 1).unwrap().and_hms_opt(12, 34, 56)
+        );
+    }
+
+    fn asf_object(guid: &[u8; 16], body: &[u8]) -> Vec<u8> {
+        let mut data = guid.to_vec();
+        data.extend_from_slice(&((body.len() + 24) as u64).to_le_bytes());
+        data.extend_from_slice(body);
+        data
+    }
+
+    #[test]
+    fn test_asf_metadata() {
+        let created = Utc.with_ymd_and_hms(2009, 12, 24, 18, 0, 0).unwrap();
+        let mut properties = vec![0; 80];
+        properties[40..48].copy_from_slice(&(((created.timestamp() + FILETIME_EPOCH_OFFSET) as u64) * 10_000_000).to_le_bytes());
+        properties[64..72].copy_from_slice(&(33_000u64 * 10_000).to_le_bytes());
+        properties.extend_from_slice(&3_000u64.to_le_bytes());
+        properties.resize(104, 0);
+
+        let mut stream = ASF_VIDEO_MEDIA.to_vec();
+        stream.resize(54, 0);
+        stream.extend_from_slice(&320u32.to_le_bytes());
+        stream.extend_from_slice(&240u32.to_le_bytes());
+        stream.resize(81, 0);
+        stream.extend_from_slice(b"WMV3");
+
+        let mut objects = asf_object(&ASF_FILE_PROPERTIES_OBJECT, &properties);
+        objects.extend(asf_object(&ASF_STREAM_PROPERTIES_OBJECT, &stream));
+
+        let mut data = ASF_HEADER_OBJECT.to_vec();
+        data.extend_from_slice(&((objects.len() + 30) as u64).to_le_bytes());
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(&[1, 2]);
+        data.extend(objects);
+
+        let metadata = read(data);
+        assert_eq!(metadata.source, VideoContainer::Asf);
+        assert_eq!(metadata.creation_time, Some(created));
+        assert_eq!(metadata.duration_ms, 30_000);
+        assert_eq!((metadata.width, metadata.height), (320, 240));
+        assert_eq!(metadata.codec.as_deref(), Some("WMV3"));
+    }
+
+    #[test]
+    fn test_malformed_atom_size() {
+        // 64ビットのサイズが壊れたmdat（位置との和が桁あふれする）
+        let mut data = atom(b"ftyp", b"isom\0\0\0\0");
+        data.extend_from_slice(&1u32.to_be_bytes());
+        data.extend_from_slice(b"mdat");
+        data.extend_from_slice(&u64::MAX.to_be_bytes());
+        data.extend_from_slice(&[0; 8]);
+        let error = read_video_metadata(&mut Cursor::new(data)).unwrap_err();
+        assert!(format!("{:#}", error).contains("Invalid atom size"), "{:#}", error);
+    }
+
+    #[test]
+    fn test_unsupported_container() {
+        assert!(read_video_metadata(&mut Cursor::new(b"FLV\x01\x05".to_vec())).is_err());
+        assert!(read_video_metadata(&mut Cursor::new(vec![0, 0, 1, 0xBA, 0x44])).is_err());
+    }
 }
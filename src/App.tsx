@@ -1,5 +1,6 @@
 import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { open } from "@tauri-apps/plugin-dialog";
 import {
   useReactTable,
@@ -9,7 +10,7 @@ import {
 } from "@tanstack/react-table";
 import "./App.css";
 import { MOCK_ENABLED, mockMediaList, mockProcessResult } from "./mock-data";
-import type { MediaInfo, ProcessResult } from "./types";
+import type { BurstDetectorConfig, BurstPolicy, DuplicatePolicy, MediaInfo, ProcessResult, ProgressEvent, RotationMode, RotationStrategy, TransferMode } from "./types";
 import { MainLayout } from "./components/MainLayout";
 import { useMediaTableColumns } from "./hooks/useMediaTableColumns";
 
@@ -39,10 +40,23 @@ function App() {
   // 全体のデフォルト設定（静止画と動画で別）
   const [defaultPhotoDateSource, setDefaultPhotoDateSource] = useState<"Exif" | "FileName" | "FileCreated" | "FileModified">("Exif");
   const [defaultPhotoTimezoneOffset, setDefaultPhotoTimezoneOffset] = useState<string>("exif");
-  const [defaultPhotoRotationMode, setDefaultPhotoRotationMode] = useState<"none" | "exif" | "90" | "180" | "270">("exif");
+  const [defaultPhotoRotationMode, setDefaultPhotoRotationMode] = useState<RotationMode>("exif");
   const [defaultVideoDateSource, setDefaultVideoDateSource] = useState<"Exif" | "FileName" | "FileCreated" | "FileModified">("FileModified");
   const [defaultVideoTimezoneOffset, setDefaultVideoTimezoneOffset] = useState<string>("none");
-  const [defaultVideoRotationMode, setDefaultVideoRotationMode] = useState<"none" | "exif" | "90" | "180" | "270">("none");
+  const [defaultVideoRotationMode, setDefaultVideoRotationMode] = useState<RotationMode>("none");
+  const [rotationStrategy, setRotationStrategy] = useState<RotationStrategy>("Pixels");
+  const [extractMotionPhotoVideo, setExtractMotionPhotoVideo] = useState(false);
+  const [detectSimilar, setDetectSimilar] = useState(false);
+  const [detectOrientation, setDetectOrientation] = useState(false);
+  const [burstConfig, setBurstConfig] = useState<BurstDetectorConfig>({
+    max_interval_seconds: 3,
+    min_count: 3,
+    use_metadata: true,
+    select_best: true,
+  });
+  const [burstPolicy, setBurstPolicy] = useState<BurstPolicy>("Suffix");
+  const [transferMode, setTransferMode] = useState<TransferMode>("Copy");
+  const [duplicatePolicy, setDuplicatePolicy] = useState<DuplicatePolicy>("KeepBoth");
 
   // ユーザーの確認が必要な行（error）を自動展開
   useEffect(() => {
@@ -57,6 +71,27 @@ function App() {
     setExpanded(newExpanded);
   }, [mediaList]);
 
+  // バックエンドからの進捗イベント（書き込み段階のファイルを完了表示に）
+  useEffect(() => {
+    if (MOCK_ENABLED) return;
+
+    const unlisten = listen<ProgressEvent>("media-progress", (event) => {
+      const { phase, current_path } = event.payload;
+      if (phase !== "Processing") return;
+      setMediaList((prev) =>
+        prev.map((item) =>
+          item.original_path === current_path
+            ? { ...item, progress: 100, status: "processing" as const }
+            : item
+        )
+      );
+    });
+
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
   // ダークモード切り替え
   useEffect(() => {
     if (isDark) {
@@ -153,6 +188,9 @@ function App() {
         inputDir,
         includeVideos: true,
         parallel: true,
+        detectSimilar,
+        detectOrientation,
+        burstConfig,
       });
 
       // 初期ステータスとデフォルト設定を適用（静止画と動画で分ける）
@@ -243,24 +281,36 @@ function App() {
     setProcessResult(null);
 
     try {
-      const result = await invoke<ProcessResult>("process_media", {
-        inputDir,
+      // 行ごとの設定（日付ソース・TZ・回転）を反映するため、スキャン結果をそのまま渡す
+      const result = await invoke<ProcessResult>("process_plan", {
+        media: mediaList,
         outputDir,
         backupDir: null,
-        includeVideos: true,
         parallel: true,
-        timezoneOffset: null,
         cleanupTemp: true,
-        autoCorrectOrientation: true,
+        dryRun: false,
+        transferMode,
+        duplicatePolicy,
+        rotationStrategy,
+        extractMotionPhotoVideo,
+        burstPolicy,
       });
 
       setProcessResult(result);
 
-      // 処理結果を反映
+      // 処理結果を反映（キャンセル時の未処理ファイルはpendingのまま、バーストや重複で出力しなかったファイルは変更なし）
+      const skippedBurstPaths = new Set((result.skipped_burst_frames ?? []).map((frame) => frame.path));
       const updatedMedia = mediaList.map((item) => {
         const processed = result.media.find(
           (m: MediaInfo) => m.original_path === item.original_path
         );
+        const skippedDuplicate = duplicatePolicy === "Skip" && (processed?.duplicate_status ?? "Unique") !== "Unique";
+        if (skippedBurstPaths.has(item.original_path) || skippedDuplicate) {
+          return { ...item, progress: 100, status: "no_change" as const };
+        }
+        if (result.cancelled && !processed?.new_path) {
+          return { ...item, progress: 0, status: "pending" as const };
+        }
         return {
           ...item,
           progress: 100,
@@ -278,6 +328,15 @@ function App() {
     }
   };
 
+  // 実行中のスキャン・処理をキャンセル
+  const cancelOperation = async () => {
+    try {
+      await invoke("cancel_operation");
+    } catch (error) {
+      console.error("Cancel error:", error);
+    }
+  };
+
   // エラーファイルのみ再処理
   const retryFailedFiles = async () => {
     const errorFiles = mediaList.filter(item => item.status === "error");
@@ -346,10 +405,27 @@ function App() {
       onVideoDateSourceChange={setDefaultVideoDateSource}
       onVideoTimezoneOffsetChange={setDefaultVideoTimezoneOffset}
       onVideoRotationModeChange={setDefaultVideoRotationMode}
+      rotationStrategy={rotationStrategy}
+      onRotationStrategyChange={setRotationStrategy}
+      extractMotionPhotoVideo={extractMotionPhotoVideo}
+      onExtractMotionPhotoVideoChange={setExtractMotionPhotoVideo}
+      burstConfig={burstConfig}
+      onBurstConfigChange={setBurstConfig}
+      burstPolicy={burstPolicy}
+      onBurstPolicyChange={setBurstPolicy}
+      transferMode={transferMode}
+      onTransferModeChange={setTransferMode}
+      duplicatePolicy={duplicatePolicy}
+      onDuplicatePolicyChange={setDuplicatePolicy}
+      detectSimilar={detectSimilar}
+      onDetectSimilarChange={setDetectSimilar}
+      detectOrientation={detectOrientation}
+      onDetectOrientationChange={setDetectOrientation}
       onScanMedia={scanMedia}
       isScanning={isScanning}
       onProcessMedia={processMedia}
       onRetryFailed={retryFailedFiles}
+      onCancel={cancelOperation}
       isProcessing={isProcessing}
       mediaList={mediaList}
       processResult={processResult}
@@ -1,21 +1,38 @@
 import { HiPhoto, HiFilm, HiChevronDown } from "react-icons/hi2";
+import type { BurstDetectorConfig, BurstPolicy, DuplicatePolicy, RotationMode, RotationStrategy, TransferMode } from "../types";
 
 interface DefaultSettingsProps {
   // Photo settings
   defaultPhotoDateSource: "Exif" | "FileName" | "FileCreated" | "FileModified";
   defaultPhotoTimezoneOffset: string;
-  defaultPhotoRotationMode: "none" | "exif" | "90" | "180" | "270";
+  defaultPhotoRotationMode: RotationMode;
   onPhotoDateSourceChange: (value: "Exif" | "FileName" | "FileCreated" | "FileModified") => void;
   onPhotoTimezoneOffsetChange: (value: string) => void;
-  onPhotoRotationModeChange: (value: "none" | "exif" | "90" | "180" | "270") => void;
+  onPhotoRotationModeChange: (value: RotationMode) => void;
+  rotationStrategy: RotationStrategy;
+  onRotationStrategyChange: (value: RotationStrategy) => void;
+  extractMotionPhotoVideo: boolean;
+  onExtractMotionPhotoVideoChange: (value: boolean) => void;
+  burstConfig: BurstDetectorConfig;
+  onBurstConfigChange: (value: BurstDetectorConfig) => void;
+  burstPolicy: BurstPolicy;
+  onBurstPolicyChange: (value: BurstPolicy) => void;
+  transferMode: TransferMode;
+  onTransferModeChange: (value: TransferMode) => void;
+  duplicatePolicy: DuplicatePolicy;
+  onDuplicatePolicyChange: (value: DuplicatePolicy) => void;
+  detectSimilar: boolean;
+  onDetectSimilarChange: (value: boolean) => void;
+  detectOrientation: boolean;
+  onDetectOrientationChange: (value: boolean) => void;
 
   // Video settings
   defaultVideoDateSource: "Exif" | "FileName" | "FileCreated" | "FileModified";
   defaultVideoTimezoneOffset: string;
-  defaultVideoRotationMode: "none" | "exif" | "90" | "180" | "270";
+  defaultVideoRotationMode: RotationMode;
   onVideoDateSourceChange: (value: "Exif" | "FileName" | "FileCreated" | "FileModified") => void;
   onVideoTimezoneOffsetChange: (value: string) => void;
-  onVideoRotationModeChange: (value: "none" | "exif" | "90" | "180" | "270") => void;
+  onVideoRotationModeChange: (value: RotationMode) => void;
 }
 
 export function DefaultSettings({
@@ -25,6 +42,22 @@ export function DefaultSettings({
   onPhotoDateSourceChange,
   onPhotoTimezoneOffsetChange,
   onPhotoRotationModeChange,
+  rotationStrategy,
+  onRotationStrategyChange,
+  extractMotionPhotoVideo,
+  onExtractMotionPhotoVideoChange,
+  burstConfig,
+  onBurstConfigChange,
+  burstPolicy,
+  onBurstPolicyChange,
+  transferMode,
+  onTransferModeChange,
+  duplicatePolicy,
+  onDuplicatePolicyChange,
+  detectSimilar,
+  onDetectSimilarChange,
+  detectOrientation,
+  onDetectOrientationChange,
   defaultVideoDateSource,
   defaultVideoTimezoneOffset,
   defaultVideoRotationMode,
@@ -113,6 +146,121 @@ export function DefaultSettings({
                   <option value="90">90°</option>
                   <option value="180">180°</option>
                   <option value="270">270°</option>
+                  <option value="flip-horizontal">Flip H</option>
+                  <option value="flip-vertical">Flip V</option>
+                  <option value="transpose">Transpose</option>
+                  <option value="transverse">Transverse</option>
+                </select>
+                <HiChevronDown className="absolute right-1 top-1/2 -translate-y-1/2 w-4 h-4 pointer-events-none text-gray-600 dark:text-gray-400" />
+              </div>
+            </div>
+            <div className="flex items-center gap-2">
+              <label className="min-w-[80px] text-xs font-medium text-gray-700 dark:text-gray-300">Rotate by:</label>
+              <div className="relative flex-1">
+                <select
+                  value={rotationStrategy}
+                  onChange={(e) => onRotationStrategyChange(e.target.value as RotationStrategy)}
+                  title="EXIF tag only: keep pixels untouched and rewrite the EXIF Orientation tag"
+                  className="appearance-none w-full px-2 py-1 pr-6 text-xs border border-gray-300 dark:border-gray-600 rounded bg-white dark:bg-gray-700 text-gray-900 dark:text-gray-100 cursor-pointer"
+                >
+                  <option value="Pixels">Pixels</option>
+                  <option value="Metadata">EXIF tag only</option>
+                </select>
+                <HiChevronDown className="absolute right-1 top-1/2 -translate-y-1/2 w-4 h-4 pointer-events-none text-gray-600 dark:text-gray-400" />
+              </div>
+            </div>
+            <label
+              className="flex items-center gap-2 text-xs font-medium text-gray-700 dark:text-gray-300 cursor-pointer"
+              title="Save the video embedded in Google/Samsung motion photos as an .mp4 next to the photo"
+            >
+              <input
+                type="checkbox"
+                checked={extractMotionPhotoVideo}
+                onChange={(e) => onExtractMotionPhotoVideoChange(e.target.checked)}
+              />
+              Extract motion photo video
+            </label>
+            <label
+              className="flex items-center gap-2 text-xs font-medium text-gray-700 dark:text-gray-300 cursor-pointer"
+              title="Group near-duplicate photos when scanning (decodes every photo, so scanning takes longer)"
+            >
+              <input
+                type="checkbox"
+                checked={detectSimilar}
+                onChange={(e) => onDetectSimilarChange(e.target.checked)}
+              />
+              Find similar photos
+            </label>
+            <label
+              className="flex items-center gap-2 text-xs font-medium text-gray-700 dark:text-gray-300 cursor-pointer"
+              title="Estimate the orientation of photos without EXIF Orientation from their content (shown as a suggestion in the Rotate column)"
+            >
+              <input
+                type="checkbox"
+                checked={detectOrientation}
+                onChange={(e) => onDetectOrientationChange(e.target.checked)}
+              />
+              Suggest orientation
+            </label>
+            <div className="flex items-center gap-2">
+              <label className="min-w-[80px] text-xs font-medium text-gray-700 dark:text-gray-300">Burst:</label>
+              <input
+                type="number"
+                min={0.1}
+                step={0.1}
+                value={burstConfig.max_interval_seconds}
+                onChange={(e) => onBurstConfigChange({ ...burstConfig, max_interval_seconds: Number(e.target.value) || 0 })}
+                className="w-16 px-2 py-1 text-xs border border-gray-300 dark:border-gray-600 rounded bg-white dark:bg-gray-700 text-gray-900 dark:text-gray-100"
+                title="Maximum gap between frames (seconds, fractions allowed)"
+              />
+              <span className="text-xs text-gray-600 dark:text-gray-400">s,</span>
+              <input
+                type="number"
+                min={2}
+                step={1}
+                value={burstConfig.min_count}
+                onChange={(e) => onBurstConfigChange({ ...burstConfig, min_count: Math.max(2, Number(e.target.value) || 2) })}
+                className="w-14 px-2 py-1 text-xs border border-gray-300 dark:border-gray-600 rounded bg-white dark:bg-gray-700 text-gray-900 dark:text-gray-100"
+                title="Minimum number of frames"
+              />
+              <span className="text-xs text-gray-600 dark:text-gray-400">frames</span>
+            </div>
+            <label
+              className="flex items-center gap-2 text-xs font-medium text-gray-700 dark:text-gray-300 cursor-pointer"
+              title="Group bursts by the burst ID or sequence number recorded by the camera before using time gaps"
+            >
+              <input
+                type="checkbox"
+                checked={burstConfig.use_metadata}
+                onChange={(e) => onBurstConfigChange({ ...burstConfig, use_metadata: e.target.checked })}
+              />
+              Use camera burst info
+            </label>
+            <label
+              className="flex items-center gap-2 text-xs font-medium text-gray-700 dark:text-gray-300 cursor-pointer"
+              title="Mark the sharpest, best exposed frame of each burst as the keeper"
+            >
+              <input
+                type="checkbox"
+                checked={burstConfig.select_best}
+                onChange={(e) => onBurstConfigChange({ ...burstConfig, select_best: e.target.checked })}
+              />
+              Pick best burst frame
+            </label>
+            <div className="flex items-center gap-2">
+              <label className="min-w-[80px] text-xs font-medium text-gray-700 dark:text-gray-300">Burst output:</label>
+              <div className="relative flex-1">
+                <select
+                  value={burstPolicy}
+                  onChange={(e) => onBurstPolicyChange(e.target.value as BurstPolicy)}
+                  title="How to output each detected burst (skipped frames are listed in the summary)"
+                  className="appearance-none w-full px-2 py-1 pr-6 text-xs border border-gray-300 dark:border-gray-600 rounded bg-white dark:bg-gray-700 text-gray-900 dark:text-gray-100 cursor-pointer"
+                >
+                  <option value="Suffix">Number suffix</option>
+                  <option value="Subfolder">Subfolder per burst</option>
+                  <option value="KeepBest">Best frame only</option>
+                  <option value="KeepFirst">First frame only</option>
+                  <option value="Ignore">Ignore bursts</option>
                 </select>
                 <HiChevronDown className="absolute right-1 top-1/2 -translate-y-1/2 w-4 h-4 pointer-events-none text-gray-600 dark:text-gray-400" />
               </div>
@@ -201,6 +349,42 @@ export function DefaultSettings({
           </div>
         </div>
       </div>
+
+      {/* 出力の設定（静止画・動画共通） */}
+      <div className="flex flex-wrap items-center gap-6 mt-4">
+        <div className="flex items-center gap-2">
+          <label className="text-xs font-medium text-gray-700 dark:text-gray-300">Transfer:</label>
+          <div className="relative">
+            <select
+              value={transferMode}
+              onChange={(e) => onTransferModeChange(e.target.value as TransferMode)}
+              title="Move removes the originals after a verified copy; Hard link shares disk space with the originals"
+              className="appearance-none px-2 py-1 pr-6 text-xs border border-gray-300 dark:border-gray-600 rounded bg-white dark:bg-gray-700 text-gray-900 dark:text-gray-100 cursor-pointer"
+            >
+              <option value="Copy">Copy</option>
+              <option value="Move">Move</option>
+              <option value="HardLink">Hard link</option>
+            </select>
+            <HiChevronDown className="absolute right-1 top-1/2 -translate-y-1/2 w-4 h-4 pointer-events-none text-gray-600 dark:text-gray-400" />
+          </div>
+        </div>
+        <div className="flex items-center gap-2">
+          <label className="text-xs font-medium text-gray-700 dark:text-gray-300">Duplicates:</label>
+          <div className="relative">
+            <select
+              value={duplicatePolicy}
+              onChange={(e) => onDuplicatePolicyChange(e.target.value as DuplicatePolicy)}
+              title="What to do with files whose content is identical to another input file or a file already in the output"
+              className="appearance-none px-2 py-1 pr-6 text-xs border border-gray-300 dark:border-gray-600 rounded bg-white dark:bg-gray-700 text-gray-900 dark:text-gray-100 cursor-pointer"
+            >
+              <option value="KeepBoth">Keep both</option>
+              <option value="Skip">Skip</option>
+              <option value="Link">Hard link</option>
+            </select>
+            <HiChevronDown className="absolute right-1 top-1/2 -translate-y-1/2 w-4 h-4 pointer-events-none text-gray-600 dark:text-gray-400" />
+          </div>
+        </div>
+      </div>
     </div>
   );
 }
@@ -11,6 +11,18 @@ interface LightBoxProps {
   isMockMode: boolean;
 }
 
+// 動画のコーデック・フレームレート・ビットレート・回転（例："avc1 · 29.97 fps · 12.0 Mbps · 90°"）
+function videoDetails(media: MediaInfo): string | null {
+  if (media.media_type !== "Video") return null;
+  const parts = [
+    media.video_codec,
+    media.frame_rate ? `${media.frame_rate.toFixed(2)} fps` : null,
+    media.bitrate ? `${(media.bitrate / 1_000_000).toFixed(1)} Mbps` : null,
+    media.video_rotation ? `${media.video_rotation}°` : null,
+  ].filter(Boolean);
+  return parts.length > 0 ? parts.join(" · ") : null;
+}
+
 export function LightBox({
   mediaList,
   currentIndex,
@@ -21,6 +33,7 @@ export function LightBox({
 }: LightBoxProps) {
   const currentMedia = mediaList[currentIndex];
   if (!currentMedia) return null;
+  const details = videoDetails(currentMedia);
 
   return (
     <div
@@ -79,6 +92,7 @@ export function LightBox({
                   Resolution: {currentMedia.width} × {currentMedia.height}
                 </p>
               )}
+              {details && <p className="text-gray-300">Video: {details}</p>}
               <p className="text-sm text-gray-400 mt-4">
                 {currentIndex + 1} / {mediaList.length}
               </p>
@@ -97,6 +111,7 @@ export function LightBox({
             />
             <div className="mt-4 text-center text-white bg-black bg-opacity-70 px-4 py-2 rounded">
               <p className="font-semibold">{currentMedia.file_name}</p>
+              {details && <p className="text-sm text-gray-300">{details}</p>}
               <p className="text-sm text-gray-300">
                 {currentIndex + 1} / {mediaList.length}
               </p>
@@ -1,6 +1,6 @@
 import { Table, flexRender } from "@tanstack/react-table";
-import { HiOutlineMagnifyingGlass, HiOutlineCog } from "react-icons/hi2";
-import type { MediaInfo, ProcessResult } from "../types";
+import { HiOutlineMagnifyingGlass, HiOutlineCog, HiOutlineXMark } from "react-icons/hi2";
+import type { BurstDetectorConfig, BurstPolicy, DuplicatePolicy, MediaInfo, ProcessResult, RotationMode, RotationStrategy, TransferMode } from "../types";
 import { Header } from "./Header";
 import { Footer } from "./Footer";
 import { ScrollToTopButton } from "./ScrollToTopButton";
@@ -24,24 +24,41 @@ interface MainLayoutProps {
   // Default settings for photos
   defaultPhotoDateSource: "Exif" | "FileName" | "FileCreated" | "FileModified";
   defaultPhotoTimezoneOffset: string;
-  defaultPhotoRotationMode: "none" | "exif" | "90" | "180" | "270";
+  defaultPhotoRotationMode: RotationMode;
   onPhotoDateSourceChange: (value: "Exif" | "FileName" | "FileCreated" | "FileModified") => void;
   onPhotoTimezoneOffsetChange: (value: string) => void;
-  onPhotoRotationModeChange: (value: "none" | "exif" | "90" | "180" | "270") => void;
+  onPhotoRotationModeChange: (value: RotationMode) => void;
+  rotationStrategy: RotationStrategy;
+  onRotationStrategyChange: (value: RotationStrategy) => void;
+  extractMotionPhotoVideo: boolean;
+  onExtractMotionPhotoVideoChange: (value: boolean) => void;
+  burstConfig: BurstDetectorConfig;
+  onBurstConfigChange: (value: BurstDetectorConfig) => void;
+  burstPolicy: BurstPolicy;
+  onBurstPolicyChange: (value: BurstPolicy) => void;
+  transferMode: TransferMode;
+  onTransferModeChange: (value: TransferMode) => void;
+  duplicatePolicy: DuplicatePolicy;
+  onDuplicatePolicyChange: (value: DuplicatePolicy) => void;
+  detectSimilar: boolean;
+  onDetectSimilarChange: (value: boolean) => void;
+  detectOrientation: boolean;
+  onDetectOrientationChange: (value: boolean) => void;
 
   // Default settings for videos
   defaultVideoDateSource: "Exif" | "FileName" | "FileCreated" | "FileModified";
   defaultVideoTimezoneOffset: string;
-  defaultVideoRotationMode: "none" | "exif" | "90" | "180" | "270";
+  defaultVideoRotationMode: RotationMode;
   onVideoDateSourceChange: (value: "Exif" | "FileName" | "FileCreated" | "FileModified") => void;
   onVideoTimezoneOffsetChange: (value: string) => void;
-  onVideoRotationModeChange: (value: "none" | "exif" | "90" | "180" | "270") => void;
+  onVideoRotationModeChange: (value: RotationMode) => void;
 
   // Actions
   onScanMedia: () => void;
   isScanning: boolean;
   onProcessMedia: () => void;
   onRetryFailed: () => void;
+  onCancel: () => void;
   isProcessing: boolean;
 
   // Data
@@ -75,6 +92,22 @@ export function MainLayout({
   onPhotoDateSourceChange,
   onPhotoTimezoneOffsetChange,
   onPhotoRotationModeChange,
+  rotationStrategy,
+  onRotationStrategyChange,
+  extractMotionPhotoVideo,
+  onExtractMotionPhotoVideoChange,
+  burstConfig,
+  onBurstConfigChange,
+  burstPolicy,
+  onBurstPolicyChange,
+  transferMode,
+  onTransferModeChange,
+  duplicatePolicy,
+  onDuplicatePolicyChange,
+  detectSimilar,
+  onDetectSimilarChange,
+  detectOrientation,
+  onDetectOrientationChange,
   defaultVideoDateSource,
   defaultVideoTimezoneOffset,
   defaultVideoRotationMode,
@@ -85,6 +118,7 @@ export function MainLayout({
   isScanning,
   onProcessMedia,
   onRetryFailed,
+  onCancel,
   isProcessing,
   mediaList,
   processResult,
@@ -123,6 +157,22 @@ export function MainLayout({
             onPhotoDateSourceChange={onPhotoDateSourceChange}
             onPhotoTimezoneOffsetChange={onPhotoTimezoneOffsetChange}
             onPhotoRotationModeChange={onPhotoRotationModeChange}
+            rotationStrategy={rotationStrategy}
+            onRotationStrategyChange={onRotationStrategyChange}
+            extractMotionPhotoVideo={extractMotionPhotoVideo}
+            onExtractMotionPhotoVideoChange={onExtractMotionPhotoVideoChange}
+            burstConfig={burstConfig}
+            onBurstConfigChange={onBurstConfigChange}
+            burstPolicy={burstPolicy}
+            onBurstPolicyChange={onBurstPolicyChange}
+            transferMode={transferMode}
+            onTransferModeChange={onTransferModeChange}
+            duplicatePolicy={duplicatePolicy}
+            onDuplicatePolicyChange={onDuplicatePolicyChange}
+            detectSimilar={detectSimilar}
+            onDetectSimilarChange={onDetectSimilarChange}
+            detectOrientation={detectOrientation}
+            onDetectOrientationChange={onDetectOrientationChange}
             defaultVideoDateSource={defaultVideoDateSource}
             defaultVideoTimezoneOffset={defaultVideoTimezoneOffset}
             defaultVideoRotationMode={defaultVideoRotationMode}
@@ -148,6 +198,15 @@ export function MainLayout({
               <HiOutlineCog className={`w-5 h-5 ${isProcessing ? 'animate-spin' : ''}`} />
               {isProcessing ? "Processing..." : "Process & Rename"}
             </button>
+            {(isScanning || isProcessing) && (
+              <button
+                onClick={onCancel}
+                className="px-6 py-3 text-base font-semibold rounded-lg transition-all bg-red-500 hover:bg-red-600 active:scale-95 text-white shadow-md hover:shadow-lg flex items-center gap-2"
+              >
+                <HiOutlineXMark className="w-5 h-5" />
+                Cancel
+              </button>
+            )}
           </div>
         </div>
       </section>
@@ -6,12 +6,15 @@ interface ProcessSummaryProps {
   onRetryFailed?: () => void;
 }
 
-export function ProcessSummary({ mediaList, onRetryFailed }: ProcessSummaryProps) {
+export function ProcessSummary({ processResult, mediaList, onRetryFailed }: ProcessSummaryProps) {
   // ステータス別にカウント
   const completedCount = mediaList.filter(item => item.status === "completed").length;
   const errorCount = mediaList.filter(item => item.status === "error").length;
   const skippedCount = mediaList.filter(item => item.status === "no_change").length;
 
+  const skippedBurstFrames = processResult.skipped_burst_frames ?? [];
+  const fileName = (path: string) => path.split(/[\\/]/).pop() ?? path;
+
   // エラーファイルのみ抽出
   const errorFiles = mediaList
     .map((item, index) => ({ item, index }))
@@ -33,7 +36,9 @@ export function ProcessSummary({ mediaList, onRetryFailed }: ProcessSummaryProps
     <section className="bg-green-50 dark:bg-green-900/20 rounded-lg p-5 mb-5 border-l-4 border-green-600">
       <div className="flex items-start justify-between">
         <div className="flex-1">
-          <h3 className="text-green-700 dark:text-green-400 font-semibold mb-3">Processing Complete</h3>
+          <h3 className="text-green-700 dark:text-green-400 font-semibold mb-3">
+            {processResult.cancelled ? "Processing Cancelled" : "Processing Complete"}
+          </h3>
 
           <div className="space-y-2 text-sm">
             <div className="flex items-center gap-2">
@@ -48,6 +53,13 @@ export function ProcessSummary({ mediaList, onRetryFailed }: ProcessSummaryProps
               </div>
             )}
 
+            {skippedBurstFrames.length > 0 && (
+              <div className="flex items-center gap-2">
+                <span className="text-gray-600 dark:text-gray-400 font-semibold">⊘ Burst frames:</span>
+                <span className="text-gray-900 dark:text-gray-100 font-mono">{skippedBurstFrames.length} files not output</span>
+              </div>
+            )}
+
             {errorCount > 0 && (
               <div className="flex items-center gap-2">
                 <span className="text-red-600 dark:text-red-400 font-semibold">✗ Failed:</span>
@@ -91,6 +103,22 @@ export function ProcessSummary({ mediaList, onRetryFailed }: ProcessSummaryProps
           </ul>
         </details>
       )}
+
+      {skippedBurstFrames.length > 0 && (
+        <details className="mt-4">
+          <summary className="cursor-pointer font-semibold text-gray-700 dark:text-gray-300">
+            Skipped Burst Frames ({skippedBurstFrames.length})
+          </summary>
+          <ul className="mt-2 space-y-1 text-sm">
+            {skippedBurstFrames.map((frame) => (
+              <li key={frame.path} className="px-3 py-1" title={frame.path}>
+                <span className="text-gray-900 dark:text-gray-100 font-semibold">{fileName(frame.path)}</span>
+                <span className="text-xs text-gray-600 dark:text-gray-400 ml-2">kept {fileName(frame.kept)}</span>
+              </li>
+            ))}
+          </ul>
+        </details>
+      )}
     </section>
   );
 }
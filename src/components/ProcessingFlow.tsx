@@ -3,18 +3,33 @@ import { HiCheckCircle, HiXCircle, HiMinusCircle, HiDocumentText } from "react-i
 import type { MediaInfo } from "../types";
 import { LogViewer } from "./LogViewer";
 
-// EXIF orientationを角度に変換
-function getOrientationDegrees(orientation: number | null): string | null {
+// EXIF orientationを表示用ラベルに変換
+function getOrientationLabel(orientation: number | null): string | null {
   if (!orientation) return null;
   switch (orientation) {
     case 1: return "0°";
+    case 2: return "Flip H";
     case 3: return "180°";
+    case 4: return "Flip V";
+    case 5: return "Transpose";
     case 6: return "90°";
+    case 7: return "Transverse";
     case 8: return "270°";
     default: return null;
   }
 }
 
+// 手動指定の回転方法を表示用ラベルに変換
+const ROTATION_MODE_LABELS: Record<string, string> = {
+  "90": "90°",
+  "180": "180°",
+  "270": "270°",
+  "flip-horizontal": "Flip H",
+  "flip-vertical": "Flip V",
+  transpose: "Transpose",
+  transverse: "Transverse",
+};
+
 // 処理フロー表示コンポーネント
 export function ProcessingFlow({ media }: { media: MediaInfo }) {
   const [showLogViewer, setShowLogViewer] = useState(false);
@@ -102,11 +117,11 @@ export function ProcessingFlow({ media }: { media: MediaInfo }) {
   // ⑥ Rotation
   const rotationMode = media.rotation_mode ?? (media.exif_orientation && media.exif_orientation !== 1 ? "exif" : "none");
   if (rotationMode !== "none") {
-    const degrees = rotationMode === "exif" ? getOrientationDegrees(media.exif_orientation) : rotationMode;
+    const label = rotationMode === "exif" ? getOrientationLabel(media.exif_orientation) : ROTATION_MODE_LABELS[rotationMode];
     steps.push({
       label: "Rotation",
       status: media.rotation_applied ? "success" : "pending",
-      details: `Rotate ${degrees}`,
+      details: `Rotate ${label}`,
     });
   } else {
     steps.push({
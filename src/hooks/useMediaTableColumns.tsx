@@ -8,25 +8,123 @@ import {
   HiChevronDown,
   HiChevronRight as HiChevronRightCollapsed,
   HiOutlineRectangleStack,
+  HiStar,
   HiOutlineBars3,
   HiOutlineSquare3Stack3D,
 } from "react-icons/hi2";
-import type { MediaInfo } from "../types";
+import type { MediaInfo, PairKind, RotationMode } from "../types";
 
 const columnHelper = createColumnHelper<MediaInfo>();
 
-// EXIF orientationを角度に変換
-function getOrientationDegrees(orientation: number | null): string | null {
+// 組になったファイルの種類ごとのバッジ
+const PAIR_BADGES: Record<PairKind, { label: string; title: string }> = {
+  LivePhoto: { label: "LIVE", title: "Live Photo: saved under the same base name as its pair" },
+  MotionPhoto: { label: "MOTION", title: "Motion photo: contains an embedded video" },
+  RawJpeg: { label: "RAW+JPEG", title: "RAW+JPEG: saved under the same base name as its pair" },
+};
+
+// EXIF orientationを表示用ラベルに変換
+function getOrientationLabel(orientation: number | null): string | null {
   if (!orientation) return null;
   switch (orientation) {
     case 1: return "0°";
+    case 2: return "Flip H";
     case 3: return "180°";
+    case 4: return "Flip V";
+    case 5: return "Transpose";
     case 6: return "90°";
+    case 7: return "Transverse";
     case 8: return "270°";
     default: return null;
   }
 }
 
+// 画像解析で推定した向き（EXIF Orientation値）を回転方法に変換
+function suggestedRotationMode(orientation: number): RotationMode {
+  switch (orientation) {
+    case 3: return "180";
+    case 6: return "90";
+    case 8: return "270";
+    default: return "none";
+  }
+}
+
+// 回転方法をEXIF orientation値（1〜8）に変換
+function rotationModeToOrientation(mode: RotationMode, exifOrientation: number | null): number {
+  switch (mode) {
+    case "exif": return exifOrientation ?? 1;
+    case "flip-horizontal": return 2;
+    case "180": return 3;
+    case "flip-vertical": return 4;
+    case "transpose": return 5;
+    case "90": return 6;
+    case "transverse": return 7;
+    case "270": return 8;
+    default: return 1;
+  }
+}
+
+// EXIF orientation値をCSS transformに変換（右側の関数から順に適用される）
+function orientationToTransform(orientation: number): string | null {
+  switch (orientation) {
+    case 2: return "scaleX(-1)";
+    case 3: return "rotate(180deg)";
+    case 4: return "scaleY(-1)";
+    case 5: return "scaleX(-1) rotate(90deg)";
+    case 6: return "rotate(90deg)";
+    case 7: return "scaleX(-1) rotate(270deg)";
+    case 8: return "rotate(270deg)";
+    default: return null;
+  }
+}
+
+// 壁時計の時刻（撮影地の時刻）
+interface WallClock {
+  year: number;
+  month: number;
+  day: number;
+  hour: number;
+  minute: number;
+  second: number;
+}
+
+const pad = (n: number, width = 2) => String(n).padStart(width, "0");
+
+// "+09:00" 形式のオフセットを分に変換
+function parseOffsetMinutes(offset: string): number | null {
+  const match = offset.match(/^([+-])(\d{2}):?(\d{2})$/);
+  if (!match) return null;
+  const sign = match[1] === "+" ? 1 : -1;
+  return sign * (parseInt(match[2], 10) * 60 + parseInt(match[3], 10));
+}
+
+// バックエンドの日時（"2025-01-15T10:30:00+09:00"）から撮影地の壁時計の時刻を取り出す
+function wallClockOf(date: string): WallClock | null {
+  const match = date.match(/^(\d{4})-(\d{2})-(\d{2})T(\d{2}):(\d{2}):(\d{2})/);
+  if (!match) return null;
+  const [year, month, day, hour, minute, second] = match.slice(1).map((v) => parseInt(v, 10));
+  return { year, month, day, hour, minute, second };
+}
+
+// 絶対時刻を指定オフセットの壁時計の時刻に変換
+function wallClockAt(date: string, offsetMinutes: number): WallClock {
+  const d = new Date(Date.parse(date) + offsetMinutes * 60 * 1000);
+  return {
+    year: d.getUTCFullYear(),
+    month: d.getUTCMonth() + 1,
+    day: d.getUTCDate(),
+    hour: d.getUTCHours(),
+    minute: d.getUTCMinutes(),
+    second: d.getUTCSeconds(),
+  };
+}
+
+// 写真のEXIF日時・AVIのIDITなどとファイル名は壁時計の時刻、それ以外（動画・ファイル日時）は絶対時刻（photo_core::time_kind と同じ規則）
+function isWallClockSource(media: MediaInfo): boolean {
+  if (media.date_source === "FileName") return true;
+  return media.date_source === "Exif" && (media.media_type === "Photo" || media.video_wall_clock === true);
+}
+
 interface UseMediaTableColumnsProps {
   setLightboxIndex: (index: number | null) => void;
   setMediaList: React.Dispatch<React.SetStateAction<MediaInfo[]>>;
@@ -130,6 +228,8 @@ export function useMediaTableColumns({
         header: "Type",
         cell: (info) => {
           const hasExif = !!info.row.original.exif_date;
+          const pairKind = info.row.original.pair_kind;
+          const sidecars = info.row.original.sidecars ?? [];
           return (
             <div className="flex items-center gap-1">
               <span
@@ -144,6 +244,22 @@ export function useMediaTableColumns({
               {info.getValue() === "Photo" && hasExif && (
                 <HiOutlineCamera className="w-4 h-4 text-green-600 dark:text-green-400" title="EXIF data available" />
               )}
+              {pairKind && (
+                <span
+                  className="px-1 rounded text-[10px] font-semibold bg-yellow-100 text-yellow-800 dark:bg-yellow-900/40 dark:text-yellow-300"
+                  title={PAIR_BADGES[pairKind].title}
+                >
+                  {PAIR_BADGES[pairKind].label}
+                </span>
+              )}
+              {sidecars.length > 0 && (
+                <span
+                  className="px-1 rounded text-[10px] font-semibold bg-gray-100 text-gray-700 dark:bg-gray-700 dark:text-gray-300"
+                  title={`Sidecars saved with the same name:\n${sidecars.map((path) => path.split(/[\\/]/).pop()).join("\n")}`}
+                >
+                  +{sidecars.length}
+                </span>
+              )}
             </div>
           );
         },
@@ -251,9 +367,11 @@ export function useMediaTableColumns({
 
           if (!date) return <span className="text-gray-900 dark:text-gray-100">N/A</span>;
 
-          // 元の時刻をそのまま表示（TZ補正は適用しない）
-          const d = new Date(date);
-          const formatted = `${d.getFullYear()}-${String(d.getMonth() + 1).padStart(2, '0')}-${String(d.getDate()).padStart(2, '0')} ${String(d.getHours()).padStart(2, '0')}:${String(d.getMinutes()).padStart(2, '0')}:${String(d.getSeconds()).padStart(2, '0')}`;
+          // 撮影地の時刻をそのまま表示（行ごとのTZ指定は適用しない）
+          const w = wallClockOf(date);
+          const formatted = w
+            ? `${w.year}-${pad(w.month)}-${pad(w.day)} ${pad(w.hour)}:${pad(w.minute)}:${pad(w.second)}`
+            : date;
 
           const handleOffsetChange = (e: React.ChangeEvent<HTMLSelectElement>) => {
             setMediaList(prevList =>
@@ -324,7 +442,7 @@ export function useMediaTableColumns({
         id: "burst",
         header: "Burst",
         cell: (info) => {
-          const { burst_group_id, burst_index } = info.row.original;
+          const { burst_group_id, burst_index, burst_best } = info.row.original;
 
           if (burst_group_id === null || burst_index === null) {
             return <span className="text-gray-400 dark:text-gray-500 text-xs">-</span>;
@@ -338,8 +456,9 @@ export function useMediaTableColumns({
                   G{burst_group_id}
                 </span>
               </div>
-              <span className="text-xs text-orange-600 dark:text-orange-400 font-semibold">
+              <span className="flex items-center gap-0.5 text-xs text-orange-600 dark:text-orange-400 font-semibold">
                 #{burst_index}
+                {burst_best && <HiStar className="w-3 h-3 text-yellow-500" title="Best frame in this burst" />}
               </span>
             </div>
           );
@@ -392,13 +511,13 @@ export function useMediaTableColumns({
           const media = info.row.original;
           const { exif_orientation } = media;
           const rotationMode = media.rotation_mode ?? (exif_orientation && exif_orientation !== 1 ? "exif" : "none");
-          const exifDegrees = getOrientationDegrees(exif_orientation);
+          const exifLabel = getOrientationLabel(exif_orientation);
 
-          const handleRotationChange = (e: React.ChangeEvent<HTMLSelectElement>) => {
+          const setRotationMode = (mode: RotationMode) => {
             setMediaList(prevList =>
               prevList.map((item, idx) =>
                 idx === info.row.index
-                  ? { ...item, rotation_mode: e.target.value as any }
+                  ? { ...item, rotation_mode: mode }
                   : item
               )
             );
@@ -406,24 +525,39 @@ export function useMediaTableColumns({
 
           return (
             <div className="flex flex-col gap-1">
-              {exifDegrees && (
+              {exifLabel && (
                 <span className="text-xs text-gray-400 dark:text-gray-500" title="EXIF Orientation (reference only)">
-                  EXIF: {exifDegrees}
+                  EXIF: {exifLabel}
                 </span>
               )}
+              {!exifLabel && media.suggested_orientation && (
+                <button
+                  type="button"
+                  onClick={() => setRotationMode(suggestedRotationMode(media.suggested_orientation!))}
+                  className="text-left text-xs text-amber-600 dark:text-amber-400 hover:underline"
+                  title="Estimated from image content (no EXIF Orientation). Click to apply."
+                >
+                  Suggested: {getOrientationLabel(media.suggested_orientation)}
+                  {media.orientation_confidence != null && ` (${Math.round(media.orientation_confidence * 100)}%)`}
+                </button>
+              )}
               <div className="relative w-32">
                 <select
                   value={rotationMode}
-                  onChange={handleRotationChange}
+                  onChange={(e) => setRotationMode(e.target.value as RotationMode)}
                   className="appearance-none w-full px-2 py-0.5 pr-6 text-xs border border-gray-300 dark:border-gray-600 rounded bg-white dark:bg-gray-700 text-gray-900 dark:text-gray-100 cursor-pointer"
                 >
                   <option value="none">None</option>
                   <option value="exif">
-                    EXIF{exifDegrees ? ` (${exifDegrees})` : ""}
+                    EXIF{exifLabel ? ` (${exifLabel})` : ""}
                   </option>
                   <option value="90">90°</option>
                   <option value="180">180°</option>
                   <option value="270">270°</option>
+                  <option value="flip-horizontal">Flip H</option>
+                  <option value="flip-vertical">Flip V</option>
+                  <option value="transpose">Transpose</option>
+                  <option value="transverse">Transverse</option>
                 </select>
                 <HiChevronDown className="absolute right-1 top-1/2 -translate-y-1/2 w-4 h-4 pointer-events-none text-gray-600 dark:text-gray-400" />
               </div>
@@ -442,28 +576,11 @@ export function useMediaTableColumns({
           const { exif_orientation } = media;
           const rotationMode = media.rotation_mode ?? (exif_orientation && exif_orientation !== 1 ? "exif" : "none");
 
-          // 回転角度を計算
-          const getRotationDegrees = (): number => {
-            if (rotationMode === "none") return 0;
-            if (rotationMode === "exif" && exif_orientation) {
-              switch (exif_orientation) {
-                case 1: return 0;
-                case 3: return 180;
-                case 6: return 90;
-                case 8: return 270;
-                default: return 0;
-              }
-            }
-            if (rotationMode === "90") return 90;
-            if (rotationMode === "180") return 180;
-            if (rotationMode === "270") return 270;
-            return 0;
-          };
-
-          const degrees = getRotationDegrees();
+          // 回転・反転をCSS transformに変換
+          const transform = orientationToTransform(rotationModeToOrientation(rotationMode, exif_orientation));
 
           // 回転がない場合は「-」を表示
-          if (degrees === 0) {
+          if (!transform) {
             return (
               <div className="w-16 h-16 flex items-center justify-center text-gray-400 dark:text-gray-500">
                 <span className="text-xs">-</span>
@@ -477,7 +594,7 @@ export function useMediaTableColumns({
               return (
                 <div
                   className="w-16 h-16 bg-gray-200 dark:bg-gray-700 rounded flex items-center justify-center"
-                  style={{ transform: `rotate(${degrees}deg)` }}
+                  style={{ transform }}
                 >
                   <HiPhoto className="w-8 h-8 text-gray-400" />
                 </div>
@@ -486,7 +603,7 @@ export function useMediaTableColumns({
               return (
                 <div
                   className="w-16 h-16 bg-gray-200 dark:bg-gray-700 rounded flex items-center justify-center"
-                  style={{ transform: `rotate(${degrees}deg)` }}
+                  style={{ transform }}
                 >
                   <HiFilm className="w-8 h-8 text-gray-400" />
                 </div>
@@ -503,7 +620,7 @@ export function useMediaTableColumns({
                   alt="rotated preview"
                   className="object-cover rounded border border-gray-300 dark:border-gray-600"
                   style={{
-                    transform: `rotate(${degrees}deg)`,
+                    transform,
                     width: '64px',
                     height: '64px',
                   }}
@@ -516,7 +633,7 @@ export function useMediaTableColumns({
             return (
               <div
                 className="w-16 h-16 bg-gray-200 dark:bg-gray-700 rounded flex items-center justify-center border border-gray-300 dark:border-gray-600"
-                style={{ transform: `rotate(${degrees}deg)` }}
+                style={{ transform }}
               >
                 <HiFilm className="w-8 h-8 text-purple-600 dark:text-purple-400" />
               </div>
@@ -532,39 +649,28 @@ export function useMediaTableColumns({
           const newPath = media.new_path;
           const hasNewPath = newPath && newPath !== "";
 
-          // TZ補正を適用してファイル名を動的に生成
-          const calculateNewName = (): string => {
+          // 行ごとのTZ指定（撮影地）を適用してファイル名を動的に生成
+          const calculateNewName = (media: MediaInfo): string => {
             const dateTaken = media.date_taken;
             if (!dateTaken) return "unknown_date";
 
-            let d = new Date(dateTaken);
             const selectedOffset = media.timezone_offset ?? "none";
-            const exifTimezone = media.timezone;
-
-            // TZ補正を適用
-            let offsetToUse = selectedOffset;
-            if (selectedOffset === "exif" && exifTimezone) {
-              offsetToUse = exifTimezone;
-            }
+            const zone = selectedOffset === "exif" ? media.timezone : selectedOffset;
+            const offsetMinutes = zone && zone !== "none" ? parseOffsetMinutes(zone) : null;
 
-            if (offsetToUse !== "none" && offsetToUse !== "exif") {
-              const match = offsetToUse.match(/([+-])(\d{2}):(\d{2})/);
-              if (match) {
-                const sign = match[1] === '+' ? 1 : -1;
-                const hours = parseInt(match[2], 10);
-                const minutes = parseInt(match[3], 10);
-                const offsetMinutes = sign * (hours * 60 + minutes);
-                d = new Date(d.getTime() + offsetMinutes * 60 * 1000);
-              }
-            }
+            // 壁時計の時刻はそのまま、絶対時刻は指定オフセットの時刻に変換
+            const w = offsetMinutes !== null && !isWallClockSource(media)
+              ? wallClockAt(dateTaken, offsetMinutes)
+              : wallClockOf(dateTaken);
+            if (!w) return "unknown_date";
 
             // ファイル名を生成
-            const year = d.getFullYear();
-            const month = String(d.getMonth() + 1).padStart(2, '0');
-            const day = String(d.getDate()).padStart(2, '0');
-            const hour = String(d.getHours()).padStart(2, '0');
-            const minute = String(d.getMinutes()).padStart(2, '0');
-            const second = String(d.getSeconds()).padStart(2, '0');
+            const year = w.year;
+            const month = pad(w.month);
+            const day = pad(w.day);
+            const hour = pad(w.hour);
+            const minute = pad(w.minute);
+            const second = pad(w.second);
 
             // 拡張子を取得
             const extension = media.file_name.split('.').pop() || 'jpg';
@@ -578,7 +684,13 @@ export function useMediaTableColumns({
             }
           };
 
-          const newName = calculateNewName();
+          // Live Photoの動画は静止画と同じ名前（拡張子だけ自分のもの）
+          const primary = media.companion_of
+            ? info.table.getCoreRowModel().rows.find((row) => row.original.original_path === media.companion_of)?.original
+            : undefined;
+          const newName = primary
+            ? calculateNewName(primary).replace(/\.[^.]*$/, "") + "." + (media.file_name.split('.').pop() || "")
+            : calculateNewName(media);
           const hasNewName = newName && newName !== "unknown_date";
 
           return (
@@ -227,6 +227,12 @@ export const mockProcessResult = {
   success: true,
   total_files: 8,
   processed_files: 7,
+  skipped_files: 0,
+  duplicate_files: 0,
+  plan: null,
+  journal_path: null,
+  cancelled: false,
+  skipped_burst_frames: [],
   media: mockMediaList,
   errors: ["Failed to process IMG_9999.jpg: No EXIF date found"],
 };
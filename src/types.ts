@@ -8,6 +8,59 @@ export interface LogEntry {
   message: string;
 }
 
+// 回転方法（EXIF Orientation 1〜8 に対応する回転・反転）
+export type RotationMode =
+  | "none"
+  | "exif"
+  | "90"
+  | "180"
+  | "270"
+  | "flip-horizontal"
+  | "flip-vertical"
+  | "transpose"
+  | "transverse";
+
+// 回転・反転の適用方法（画素を書き換える / EXIF Orientationだけを書き換える）
+export type RotationStrategy = "Pixels" | "Metadata";
+
+// バースト検出の設定
+export interface BurstDetectorConfig {
+  max_interval_seconds: number; // バーストとみなす最大時間間隔（秒、秒未満も可）
+  min_count: number; // バーストとみなす最小枚数
+  use_metadata: boolean; // カメラが記録した連写の情報を先に使う
+  select_best: boolean; // グループ内で残す候補をシャープさと露出から選ぶ
+}
+
+// 処理時のバーストグループの扱い
+export type BurstPolicy = "Suffix" | "Subfolder" | "KeepBest" | "KeepFirst" | "Ignore";
+
+// 回転・反転の種類（EXIF Orientation値に対応）
+export type Orientation =
+  | "Normal"
+  | "FlipHorizontal"
+  | "Rotate180"
+  | "FlipVertical"
+  | "Transpose"
+  | "Rotate90CW"
+  | "Transverse"
+  | "Rotate90CCW"
+  | "Unknown";
+
+// 重複の状態
+export type DuplicateStatus = "Unique" | "InInput" | "InOutput";
+
+// 重複ファイルの扱い
+export type DuplicatePolicy = "Skip" | "Link" | "KeepBoth";
+
+// 同じベース名で出力する関連ファイルの種類
+export type PairKind = "LivePhoto" | "MotionPhoto" | "RawJpeg";
+
+// モーションフォトに埋め込まれた動画の位置（バイト）
+export interface EmbeddedVideo {
+  offset: number;
+  length: number;
+}
+
 // Rust側のMediaInfo型に対応
 export interface MediaInfo {
   original_path: string;
@@ -26,15 +79,45 @@ export interface MediaInfo {
   file_size: number;
   burst_group_id: number | null;
   burst_index: number | null;
+  burst_best?: boolean; // バーストグループ内で残す候補か
+  burst_id?: string | null; // カメラが記録したバーストの識別子（BurstUUID、ファイル名のBURSTなど）
+  sequence_number?: number | null; // 連写中の通し番号（Canon・Sony）
   date_source: "Exif" | "FileName" | "FileCreated" | "FileModified" | "None";
+  video_wall_clock?: boolean; // 動画のメタデータ日時がタイムゾーンなしの時刻か（AVIのIDITなど）
   exif_orientation: number | null;
   rotation_applied: boolean;
   // ユーザー選択：TZオフセット補正（例："+09:00", "none"）
   timezone_offset?: string;
-  // ユーザー選択：回転方法（"none", "exif", "90", "180", "270"）
-  rotation_mode?: "none" | "exif" | "90" | "180" | "270";
+  // ユーザー選択：回転方法（"none", "exif", "90", "180", "270", 反転系）
+  rotation_mode?: RotationMode;
+  suggested_orientation?: number | null; // 画像解析で推定した向き（EXIF Orientation値）
+  orientation_confidence?: number | null; // 推定の確からしさ（0〜1）
   width: number | null;
   height: number | null;
+  video_rotation?: number | null; // 動画の回転（時計回り、0/90/180/270）
+  video_codec?: string | null; // 動画のコーデック（"avc1" など）
+  frame_rate?: number | null; // 動画のフレームレート（fps）
+  bitrate?: number | null; // 動画のビットレート（bps）
+  camera_make?: string | null; // EXIF Make
+  camera_model?: string | null; // EXIF Model
+  latitude?: number | null; // 撮影地の緯度（動画の位置情報）
+  longitude?: number | null; // 撮影地の経度（動画の位置情報）
+  // ユーザー選択：処理対象に含めるか（省略時はtrue）
+  selected?: boolean;
+  // ユーザー選択：手動で編集した出力ファイル名（null=日付から自動生成）
+  custom_name?: string | null;
+  content_hash?: string | null; // ファイル内容のSHA-256（処理時に計算）
+  duplicate_status?: DuplicateStatus;
+  duplicate_of?: string | null; // 同一内容のファイル
+  perceptual_hash?: string | null; // 知覚ハッシュ（dHash、16進数）
+  similar_group_id?: number | null; // 類似写真グループID
+  similarity?: number | null; // 残す候補との類似度（0.0〜1.0）
+  similar_best?: boolean; // 類似グループ内で残す候補か
+  pair_kind?: PairKind | null; // Live Photo・モーションフォト・RAW+JPEG
+  companion_of?: string | null; // 同じベース名で出力する主ファイル（Live Photoの動画では静止画、RAW+JPEGのRAWではJPEG）
+  content_identifier?: string | null; // Live Photoの識別子
+  embedded_video?: EmbeddedVideo | null; // モーションフォトの埋め込み動画
+  sidecars?: string[]; // 一緒に出力するサイドカー（XMPなど）
   progress?: number; // 進捗（0-100）
   status?: "pending" | "processing" | "completed" | "error" | "no_change";
   error_message?: string;
@@ -45,6 +128,59 @@ export interface ProcessResult {
   success: boolean;
   total_files: number;
   processed_files: number;
+  skipped_files: number;
+  duplicate_files: number;
   media: MediaInfo[];
   errors: string[];
+  plan: OperationPlan | null; // ドライラン時の操作計画
+  journal_path: string | null; // 取り消し用ジャーナル
+  cancelled: boolean; // 途中でキャンセルされたか
+  skipped_burst_frames?: SkippedBurstFrame[]; // バーストで1枚だけ残したため出力しなかったファイル
+}
+
+// バーストグループから出力しなかった1ファイル
+export interface SkippedBurstFrame {
+  path: string;
+  kept: string; // 代わりに出力した同じグループの写真
+  burst_group_id: number;
+}
+
+// undo_run の結果
+export interface UndoResult {
+  removed_files: number;
+  restored_files: number;
+  removed_directories: number;
+  errors: string[];
+}
+
+// 出力先へのファイル転送方法
+export type TransferMode = "Copy" | "Move" | "HardLink";
+
+// ドライランで予測された1ファイル分の操作
+export interface PlannedOperation {
+  source: string;
+  target_path: string;
+  transfer_mode: TransferMode;
+  conflict_counter: number | null;
+  rotation: Orientation | null;
+  backup_path: string | null;
+  embedded_video_path?: string | null; // モーションフォトから抽出する動画
+  sidecar_paths?: string[]; // 一緒に転送するサイドカー
+}
+
+// ドライランの操作計画
+export interface OperationPlan {
+  directories: string[];
+  operations: PlannedOperation[];
+}
+
+// 処理の段階
+export type ProgressPhase = "Scanning" | "Hashing" | "Processing";
+
+// "media-progress" イベントの内容（1ファイル完了ごと）
+export interface ProgressEvent {
+  phase: ProgressPhase;
+  index: number; // 完了したファイル数（1始まり）
+  total: number;
+  current_path: string;
 }